@@ -0,0 +1,100 @@
+"""Expected values of the Curve tests in src/curve.
+
+Runs the integer arithmetic of get_D, get_y, get_dy, get_dx and exchange of Curve's StableSwap3Pool.vy (3pool) on the
+balances used by the tests, independently of the Rust port. Run with `python3 scripts/curve_vectors.py`.
+"""
+
+A_PRECISION = 100
+FEE_DENOMINATOR = 10**10
+PRECISION = 10**18
+
+
+def get_D(xp, amp):
+    n = len(xp)
+    S = sum(xp)
+    if S == 0:
+        return 0
+
+    D = S
+    Ann = amp * n
+    for _ in range(255):
+        D_P = D
+        for x in xp:
+            D_P = D_P * D // (x * n)
+        Dprev = D
+        D = (Ann * S // A_PRECISION + D_P * n) * D // ((Ann - A_PRECISION) * D // A_PRECISION + (n + 1) * D_P)
+        if abs(D - Dprev) <= 1:
+            return D
+    raise ValueError("D did not converge")
+
+
+def get_y(i, j, x, xp, amp):
+    n = len(xp)
+    D = get_D(xp, amp)
+    Ann = amp * n
+
+    c = D
+    S_ = 0
+    for k in range(n):
+        if k == i:
+            _x = x
+        elif k != j:
+            _x = xp[k]
+        else:
+            continue
+        S_ += _x
+        c = c * D // (_x * n)
+    c = c * D * A_PRECISION // (Ann * n)
+    b = S_ + D * A_PRECISION // Ann
+
+    y = D
+    for _ in range(255):
+        y_prev = y
+        y = (y * y + c) // (2 * y + b - D)
+        if abs(y - y_prev) <= 1:
+            return y
+    raise ValueError("y did not converge")
+
+
+def xp_mem(balances, rates):
+    return [rate * balance // PRECISION for rate, balance in zip(rates, balances)]
+
+
+def get_dy(i, j, dx, balances, rates, amp, fee):
+    xp = xp_mem(balances, rates)
+    x = xp[i] + dx * rates[i] // PRECISION
+    y = get_y(i, j, x, xp, amp)
+    dy = xp[j] - y - 1
+    return (dy - fee * dy // FEE_DENOMINATOR) * PRECISION // rates[j]
+
+
+def get_dx(i, j, dy, balances, rates, amp, fee):
+    xp = xp_mem(balances, rates)
+    y = xp[j] - (dy * rates[j] // PRECISION + 1) * FEE_DENOMINATOR // (FEE_DENOMINATOR - fee)
+    x = get_y(j, i, y, xp, amp)
+    return (x - xp[i]) * PRECISION // rates[i]
+
+
+def exchange(i, j, dx, balances, rates, amp, fee, admin_fee):
+    """Returns the amount out and the admin fee leaving the pool, in coin j"""
+    xp = xp_mem(balances, rates)
+    x = xp[i] + dx * rates[i] // PRECISION
+    y = get_y(i, j, x, xp, amp)
+    dy = xp[j] - y - 1
+    dy_fee = dy * fee // FEE_DENOMINATOR
+    dy_admin_fee = dy_fee * admin_fee // FEE_DENOMINATOR * PRECISION // rates[j]
+    return (dy - dy_fee) * PRECISION // rates[j], dy_admin_fee
+
+
+if __name__ == "__main__":
+    # 170m DAI, 160m USDC and 60m USDT with A = 2000, a 0.01% fee and half of it as admin fee
+    rates = [10**18, 10**30, 10**30]
+    balances = [170000000 * 10**18, 160000000 * 10**6, 60000000 * 10**6]
+    amp = 2000 * A_PRECISION
+    fee, admin_fee = 1000000, 5000000000
+
+    print("get_d_test D:", get_D(xp_mem(balances, rates), amp))
+    print("curve_amm_test get_dy(0, 1, 1m DAI):", get_dy(0, 1, 10**24, balances, rates, amp, fee))
+    print("curve_amm_test get_dy(2, 0, 5m USDT):", get_dy(2, 0, 5 * 10**12, balances, rates, amp, fee))
+    print("curve_amm_test get_dx(0, 1, 1m USDC):", get_dx(0, 1, 10**12, balances, rates, amp, fee))
+    print("curve_amm_test exchange(0, 1, 1000 DAI) out, admin fee:", *exchange(0, 1, 10**21, balances, rates, amp, fee, admin_fee))
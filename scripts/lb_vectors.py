@@ -0,0 +1,186 @@
+"""Expected values of the Liquidity Book tests in src/liquidity_book.
+
+Runs the integer arithmetic of Trader Joe's LB v2.1 Uint128x128Math.pow, PriceHelper.getPriceFromId, the fee
+helpers and the bin loop of LBPair.swap on the state used by the tests, independently of the Rust port. Run with
+`python3 scripts/lb_vectors.py`.
+"""
+
+SCALE_OFFSET = 128
+SCALE = 1 << SCALE_OFFSET
+REAL_ID_SHIFT = 1 << 23
+BASIS_POINT_MAX = 10000
+PRECISION = 10**18
+MAX_UINT256 = 2**256 - 1
+
+
+def pow_(x, y):
+    """Uint128x128Math.pow, x^y for a 128.128-binary fixed-point x"""
+    if y == 0:
+        return SCALE
+
+    invert = y < 0
+    abs_y = abs(y)
+    result = 0
+    if abs_y < 0x100000:
+        result = SCALE
+        squared = x
+        if x > 2**128 - 1:
+            squared = MAX_UINT256 // squared
+            invert = not invert
+        for bit in range(20):
+            if abs_y & (1 << bit):
+                result = (result * squared) >> 128
+            squared = (squared * squared) >> 128
+
+    if result == 0:
+        raise ValueError("Uint128x128Math__PowUnderflow")
+    return MAX_UINT256 // result if invert else result
+
+
+def get_base(bin_step):
+    return SCALE + (bin_step << SCALE_OFFSET) // BASIS_POINT_MAX
+
+
+def get_price_from_id(id, bin_step):
+    return pow_(get_base(bin_step), id - REAL_ID_SHIFT)
+
+
+def mul_shift(x, y, round_up):
+    result = (x * y) >> SCALE_OFFSET
+    if round_up and (x * y) % SCALE:
+        result += 1
+    return result
+
+
+def shift_div(x, y, round_up):
+    result = (x << SCALE_OFFSET) // y
+    if round_up and (x << SCALE_OFFSET) % y:
+        result += 1
+    return result
+
+
+def get_fee_amount_from(amount_with_fees, total_fee):
+    return (amount_with_fees * total_fee + PRECISION - 1) // PRECISION
+
+
+def get_fee_amount(amount, total_fee):
+    denominator = PRECISION - total_fee
+    return (amount * total_fee + denominator - 1) // denominator
+
+
+def get_total_fee(parameters, bin_step):
+    base_fee = parameters["base_factor"] * bin_step * 10**10
+    variable_fee = 0
+    if parameters["variable_fee_control"]:
+        product = parameters["volatility_accumulator"] * bin_step
+        variable_fee = (product * product * parameters["variable_fee_control"] + 99) // 100
+    return base_fee + variable_fee
+
+
+def update_references(parameters, timestamp):
+    dt = timestamp - parameters["time_of_last_update"]
+    if dt >= parameters["filter_period"]:
+        parameters["id_reference"] = parameters["active_id"]
+        if dt < parameters["decay_period"]:
+            parameters["volatility_reference"] = (
+                parameters["volatility_accumulator"] * parameters["reduction_factor"] // BASIS_POINT_MAX
+            )
+        else:
+            parameters["volatility_reference"] = 0
+    parameters["time_of_last_update"] = timestamp
+
+
+def update_volatility_accumulator(parameters, id):
+    delta_id = abs(id - parameters["id_reference"])
+    parameters["volatility_accumulator"] = min(
+        parameters["volatility_reference"] + delta_id * BASIS_POINT_MAX,
+        parameters["max_volatility_accumulator"]
+    )
+
+
+def swap(parameters, bins, bin_step, swap_for_y, amount_in, timestamp):
+    """Returns the amount in left, amount out, total fee and protocol fee, updating parameters and bins"""
+    update_references(parameters, timestamp)
+
+    amount_in_left, amount_out, fee, protocol_fee = amount_in, 0, 0, 0
+    id = parameters["active_id"]
+    while True:
+        reserve_x, reserve_y = bins.get(id, (0, 0))
+        bin_reserve_out = reserve_y if swap_for_y else reserve_x
+        if bin_reserve_out != 0:
+            update_volatility_accumulator(parameters, id)
+            price = get_price_from_id(id, bin_step)
+
+            max_amount_in = shift_div(bin_reserve_out, price, True) if swap_for_y else mul_shift(bin_reserve_out, price, True)
+            total_fee = get_total_fee(parameters, bin_step)
+            max_fee = get_fee_amount(max_amount_in, total_fee)
+            max_amount_in += max_fee
+
+            if amount_in_left >= max_amount_in:
+                bin_in, bin_out, bin_fee = max_amount_in, bin_reserve_out, max_fee
+            else:
+                bin_fee = get_fee_amount_from(amount_in_left, total_fee)
+                amount_in_without_fee = amount_in_left - bin_fee
+                if swap_for_y:
+                    bin_out = mul_shift(amount_in_without_fee, price, False)
+                else:
+                    bin_out = shift_div(amount_in_without_fee, price, False)
+                bin_out = min(bin_out, bin_reserve_out)
+                bin_in = amount_in_left
+
+            if bin_in > 0:
+                amount_in_left -= bin_in
+                amount_out += bin_out
+                fee += bin_fee
+                bin_protocol_fee = bin_fee * parameters["protocol_share"] // BASIS_POINT_MAX
+                protocol_fee += bin_protocol_fee
+                if swap_for_y:
+                    bins[id] = (reserve_x + bin_in - bin_protocol_fee, reserve_y - bin_out)
+                else:
+                    bins[id] = (reserve_x - bin_out, reserve_y + bin_in - bin_protocol_fee)
+
+        if amount_in_left == 0:
+            break
+        next_ids = [k for k, v in bins.items() if (k < id if swap_for_y else k > id) and v != (0, 0)]
+        if not next_ids:
+            break
+        id = max(next_ids) if swap_for_y else min(next_ids)
+
+    parameters["active_id"] = id
+    return amount_in_left, amount_out, fee, protocol_fee
+
+
+if __name__ == "__main__":
+    print("get_price_from_id_test id + 100:", get_price_from_id(REAL_ID_SHIFT + 100, 25))
+    print("get_price_from_id_test id - 100:", get_price_from_id(REAL_ID_SHIFT - 100, 25))
+
+    # pair_at_price_one: bin step 25, 500 of both tokens in the active bin, 1000 X in each of the 5 bins above and
+    # 1000 Y in each of the 5 bins below, swapped at timestamp 1000
+    parameters = {
+        "base_factor": 5000,
+        "filter_period": 30,
+        "decay_period": 600,
+        "reduction_factor": 5000,
+        "variable_fee_control": 40000,
+        "protocol_share": 1000,
+        "max_volatility_accumulator": 350000,
+        "volatility_accumulator": 0,
+        "volatility_reference": 0,
+        "id_reference": REAL_ID_SHIFT,
+        "time_of_last_update": 0,
+        "active_id": REAL_ID_SHIFT
+    }
+    amount = 10**21
+    bins = {REAL_ID_SHIFT: (amount // 2, amount // 2)}
+    for offset in range(1, 6):
+        bins[REAL_ID_SHIFT + offset] = (amount, 0)
+        bins[REAL_ID_SHIFT - offset] = (0, amount)
+
+    amount_in_left, amount_out, fee, protocol_fee = swap(parameters, bins, 25, True, 2 * 10**21, 1000)
+    print("swap_across_bins_test amount in left, amount out, fee:", amount_in_left, amount_out, fee)
+    print("swap_across_bins_test protocol fee:", protocol_fee)
+    print(
+        "swap_across_bins_test active id shift, volatility accumulator:",
+        parameters["active_id"] - REAL_ID_SHIFT,
+        parameters["volatility_accumulator"]
+    )
@@ -0,0 +1,75 @@
+"""Expected values of the Solidly tests in src/solidly.
+
+Runs the integer arithmetic of _k, _f, _d, _get_y and _getAmountOut of the Velodrome V2 / Aerodrome Pool on the
+reserves used by the tests, independently of the Rust port. Run with `python3 scripts/solidly_vectors.py`.
+"""
+
+ONE = 10**18
+FEE_DENOMINATOR = 10000
+
+
+def _k(x, y, decimals0, decimals1, stable):
+    if stable:
+        _x = x * ONE // decimals0
+        _y = y * ONE // decimals1
+        _a = _x * _y // ONE
+        _b = _x * _x // ONE + _y * _y // ONE
+        return _a * _b // ONE
+    return x * y
+
+
+def _f(x0, y):
+    _a = x0 * y // ONE
+    _b = x0 * x0 // ONE + y * y // ONE
+    return _a * _b // ONE
+
+
+def _d(x0, y):
+    return 3 * x0 * (y * y // ONE) // ONE + (x0 * x0 // ONE) * x0 // ONE
+
+
+def _get_y(x0, xy, y, decimals0, decimals1):
+    for _ in range(255):
+        k = _f(x0, y)
+        if k < xy:
+            dy = (xy - k) * ONE // _d(x0, y)
+            if dy == 0:
+                if k == xy:
+                    return y
+                if _k(x0, y + 1, decimals0, decimals1, True) > xy:
+                    return y + 1
+                dy = 1
+            y += dy
+        else:
+            dy = (k - xy) * ONE // _d(x0, y)
+            if dy == 0:
+                if k == xy or _f(x0, y - 1) < xy:
+                    return y
+                dy = 1
+            y -= dy
+    raise ValueError("!y")
+
+
+def get_amount_out(amount_in, zero_for_one, reserve0, reserve1, decimals0, decimals1, stable, fee):
+    amount_in -= amount_in * fee // FEE_DENOMINATOR
+    if stable:
+        xy = _k(reserve0, reserve1, decimals0, decimals1, True)
+        reserve0 = reserve0 * ONE // decimals0
+        reserve1 = reserve1 * ONE // decimals1
+        reserve_a, reserve_b = (reserve0, reserve1) if zero_for_one else (reserve1, reserve0)
+        amount_in = amount_in * ONE // decimals0 if zero_for_one else amount_in * ONE // decimals1
+        y = reserve_b - _get_y(amount_in + reserve_a, xy, reserve_b, decimals0, decimals1)
+        return y * (decimals1 if zero_for_one else decimals0) // ONE
+
+    reserve_a, reserve_b = (reserve0, reserve1) if zero_for_one else (reserve1, reserve0)
+    return amount_in * reserve_b // (reserve_a + amount_in)
+
+
+if __name__ == "__main__":
+    # stable pool of 5m USDC and 5.2m DAI with a 0.05% fee
+    reserve0, reserve1 = 5000000 * 10**6, 5200000 * 10**18
+    print("get_amount_out_test 10k USDC in:", get_amount_out(10000 * 10**6, True, reserve0, reserve1, 10**6, ONE, True, 5))
+    print("get_amount_out_test 10k DAI in:", get_amount_out(10000 * ONE, False, reserve0, reserve1, 10**6, ONE, True, 5))
+
+    # volatile pool of 1000 WETH and 2m USDC with a 0.3% fee
+    print("get_amount_out_test 1 WETH in:", get_amount_out(ONE, True, 1000 * ONE, 2000000 * 10**6, ONE, 10**6, False, 30))
@@ -0,0 +1,164 @@
+use alloy::{
+    primitives::{Address, I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    transports::http::{Client, Http}
+};
+use crate::{
+    amm::Amm,
+    uniswap_v3::{math::{safe_cast, tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO}}, utils::sqrt_price_x96_to_price}
+};
+use super::pool::AlgebraPoolState;
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+impl AlgebraPoolState {
+    fn zero_for_one(&self, token_in: Address, token_out: Address) -> Result<bool> {
+        let (token0, token1) = (self.engine.token0.address, self.engine.token1.address);
+        if token_in == token0 && token_out == token1 {
+            Ok(true)
+        } else if token_in == token1 && token_out == token0 {
+            Ok(false)
+        } else {
+            Err(eyre!("Pool {} does not trade {} for {}", self.engine.pool_address, token_in, token_out))
+        }
+    }
+
+    async fn swap_exact(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        zero_for_one: bool,
+        amount_specified: I256
+    ) -> Result<(U256, U256)> {
+        let sqrt_price_limit_x96 = if zero_for_one {MIN_SQRT_RATIO + U256::from(1)} else {MAX_SQRT_RATIO - U256::from(1)};
+        let (amount0, amount1) = self.swap(provider, zero_for_one, amount_specified, sqrt_price_limit_x96).await?;
+
+        let (amount_in, amount_out) = if zero_for_one {(amount0, amount1)} else {(amount1, amount0)};
+        Ok((amount_in.unsigned_abs(), amount_out.unsigned_abs()))
+    }
+}
+
+impl Amm for AlgebraPoolState {
+    fn address(&self) -> Address {
+        self.engine.pool_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.engine.token0.address, self.engine.token1.address]
+    }
+
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let price = sqrt_price_x96_to_price(self.global_state.price, self.engine.token0.decimals, self.engine.token1.decimals);
+        if self.zero_for_one(base, quote)? {Ok(price)} else {Ok(1.0 / price)}
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (swapped_in, amount_out) = self.clone().swap_exact(provider, zero_for_one, safe_cast::to_int256(amount_in)?).await?;
+            if swapped_in != amount_in {
+                return Err(eyre!("Not enough liquidity to swap {} of {}", amount_in, token_in))
+            }
+            Ok(amount_out)
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (amount_in, swapped_out) = self.clone().swap_exact(provider, zero_for_one, -safe_cast::to_int256(amount_out)?).await?;
+            if swapped_out != amount_out {
+                return Err(eyre!("Not enough liquidity to receive {} of {}", amount_out, token_out))
+            }
+            Ok(amount_in)
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (_, amount_out) = self.swap_exact(provider, zero_for_one, safe_cast::to_int256(amount_in)?).await?;
+            Ok(amount_out)
+        })
+    }
+
+    // the pool is reloaded, its fee as of `to_block` replaces a fee given through `set_fee`
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            *self = AlgebraPoolState::load_at_block(
+                provider,
+                self.engine.multicall_address,
+                self.engine.pool_address,
+                self.version,
+                BlockId::number(to_block)
+            ).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algebra::pool::{AlgebraVersion, GlobalState},
+        test_utils::offline_provider,
+        uniswap_v3::position::tests::pool_state_with_liquidity
+    };
+    use super::*;
+
+    #[tokio::test]
+    async fn algebra_amm_test() {
+        let provider = offline_provider();
+        let engine = pool_state_with_liquidity().await;
+        let (token0, token1) = (engine.token0.address, engine.token1.address);
+
+        let global_state = GlobalState {
+            price: engine.slot0.sqrt_price_x96,
+            tick: engine.slot0.tick,
+            fee_zto: 3000,
+            fee_otz: 3000,
+            community_fee_token0: 0,
+            community_fee_token1: 0,
+            unlocked: true
+        };
+        let mut pool = AlgebraPoolState {version: AlgebraVersion::V1, global_state, engine: engine.clone()};
+
+        // at the fee of the V3 pool holding the same liquidity the quotes match
+        let amount_in = U256::from(1000000000000000000u128);
+        let quoted_out = pool.quote_exact_input(&provider, token0, token1, amount_in).await.unwrap();
+        assert_eq!(quoted_out, engine.quote_exact_input(&provider, token0, token1, amount_in).await.unwrap());
+
+        // a lower fee for the next block pays out more
+        pool.set_fee(500, 500);
+        let mut pool: Box<dyn Amm> = Box::new(pool);
+        let cheaper_out = pool.quote_exact_input(&provider, token0, token1, amount_in).await.unwrap();
+        assert!(cheaper_out > quoted_out);
+        assert_eq!(pool.quote_exact_output(&provider, token0, token1, cheaper_out).await.unwrap(), amount_in);
+        assert!(pool.spot_price(token0, Address::ZERO).is_err());
+
+        assert_eq!(pool.swap_exact_input(&provider, token0, token1, amount_in).await.unwrap(), cheaper_out);
+        assert!(pool.spot_price(token0, token1).unwrap() < 1.0);
+    }
+}
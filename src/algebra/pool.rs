@@ -0,0 +1,359 @@
+use alloy::{
+    primitives::{Address, Bytes, I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::{
+    math::{tick::Info, tick_math::{MAX_TICK, MIN_TICK}},
+    multicall::multicall,
+    pool::{get_block_timestamp, load_tokens, PoolState, Slot0},
+    swap
+};
+use eyre::Result;
+use std::collections::HashMap;
+
+sol! {
+    #[sol(rpc)]
+    interface IAlgebraPool {
+        function globalState()
+            external
+            view
+            returns (
+                uint160 price,
+                int24 tick,
+                uint16 fee,
+                uint16 timepointIndex,
+                uint8 communityFeeToken0,
+                uint8 communityFeeToken1,
+                bool unlocked
+            );
+
+        function liquidity() external view returns (uint128);
+
+        function tickSpacing() external view returns (int24);
+
+        function token0() external view returns (address);
+
+        function token1() external view returns (address);
+
+        function totalFeeGrowth0Token() external view returns (uint256);
+
+        function totalFeeGrowth1Token() external view returns (uint256);
+
+        function tickTable(int16 wordPosition) external view returns (uint256);
+
+        // leading fields of the tick, the layout of the remaining fields differs between versions
+        function ticks(int24 tick)
+            external
+            view
+            returns (
+                uint128 liquidityTotal,
+                int128 liquidityDelta,
+                uint256 outerFeeGrowth0Token,
+                uint256 outerFeeGrowth1Token
+            );
+    }
+}
+
+sol! {
+    #[sol(rpc)]
+    interface IAlgebraPoolDirectionalFee {
+        function globalState()
+            external
+            view
+            returns (
+                uint160 price,
+                int24 tick,
+                uint16 feeZto,
+                uint16 feeOtz,
+                uint16 timepointIndex,
+                uint8 communityFeeToken0,
+                uint8 communityFeeToken1,
+                bool unlocked
+            );
+    }
+}
+
+/// Layout of `globalState`, Algebra V1 pools (QuickSwap V3) charge one fee while directional fee pools (Camelot)
+/// charge one per swap direction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlgebraVersion {
+    V1,
+    DirectionalFee
+}
+
+/// Fields of `globalState` shared by the versions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalState {
+    pub price: U256,
+    pub tick: i32,
+    // dynamic fee in hundredths of a bip for zero for one and one for zero swaps
+    pub fee_zto: u16,
+    pub fee_otz: u16,
+    // share of the fee going to the community vault, in thousandths
+    pub community_fee_token0: u8,
+    pub community_fee_token1: u8,
+    pub unlocked: bool
+}
+
+/// State of an Algebra pool. Ticks, liquidity and prices live in a V3 `PoolState` so swaps run through the V3 engine
+/// with the dynamic fee of the swap direction.
+///
+/// The pool recomputes its fee on the first swap of a block, so the fee in `globalState` is the one of the last block
+/// with a swap. Quotes for a later block use that fee unless it is replaced through `set_fee`.
+#[derive(Clone)]
+pub struct AlgebraPoolState {
+    pub version: AlgebraVersion,
+    pub global_state: GlobalState,
+    pub engine: PoolState
+}
+
+impl AlgebraVersion {
+    fn global_state_call(&self) -> Vec<u8> {
+        match self {
+            AlgebraVersion::V1 => IAlgebraPool::globalStateCall{}.abi_encode(),
+            AlgebraVersion::DirectionalFee => IAlgebraPoolDirectionalFee::globalStateCall{}.abi_encode()
+        }
+    }
+
+    fn decode_global_state(&self, data: &Bytes) -> Result<GlobalState> {
+        Ok(match self {
+            AlgebraVersion::V1 => {
+                let state = IAlgebraPool::globalStateCall::abi_decode_returns(data, true)?;
+                GlobalState {
+                    price: state.price,
+                    tick: state.tick,
+                    fee_zto: state.fee,
+                    fee_otz: state.fee,
+                    community_fee_token0: state.communityFeeToken0,
+                    community_fee_token1: state.communityFeeToken1,
+                    unlocked: state.unlocked
+                }
+            },
+            AlgebraVersion::DirectionalFee => {
+                let state = IAlgebraPoolDirectionalFee::globalStateCall::abi_decode_returns(data, true)?;
+                GlobalState {
+                    price: state.price,
+                    tick: state.tick,
+                    fee_zto: state.feeZto,
+                    fee_otz: state.feeOtz,
+                    community_fee_token0: state.communityFeeToken0,
+                    community_fee_token1: state.communityFeeToken1,
+                    unlocked: state.unlocked
+                }
+            }
+        })
+    }
+}
+
+impl AlgebraPoolState {
+    /// Loads the pool with its full tick table and every initialized tick. The whole table is read up front as the
+    /// V3 engine can only load missing words from V3 pools.
+    pub async fn load_at_block(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_address: Address,
+        version: AlgebraVersion,
+        block: BlockId
+    ) -> Result<Self> {
+        let encoded_calls = vec![
+            version.global_state_call(),
+            IAlgebraPool::liquidityCall{}.abi_encode(),
+            IAlgebraPool::tickSpacingCall{}.abi_encode(),
+            IAlgebraPool::token0Call{}.abi_encode(),
+            IAlgebraPool::token1Call{}.abi_encode(),
+            IAlgebraPool::totalFeeGrowth0TokenCall{}.abi_encode(),
+            IAlgebraPool::totalFeeGrowth1TokenCall{}.abi_encode()
+        ];
+        let return_data: Vec<Bytes> = multicall(provider, multicall_address, pool_address, false, encoded_calls, block).await?
+            .into_iter()
+            .map(|result| result.returnData)
+            .collect();
+
+        let global_state = version.decode_global_state(&return_data[0])?;
+        let liquidity = IAlgebraPool::liquidityCall::abi_decode_returns(&return_data[1], true)?._0;
+        let tick_spacing = IAlgebraPool::tickSpacingCall::abi_decode_returns(&return_data[2], true)?._0;
+        let token0_address = IAlgebraPool::token0Call::abi_decode_returns(&return_data[3], true)?._0;
+        let token1_address = IAlgebraPool::token1Call::abi_decode_returns(&return_data[4], true)?._0;
+        let fee_growth_global0_x128 = IAlgebraPool::totalFeeGrowth0TokenCall::abi_decode_returns(&return_data[5], true)?._0;
+        let fee_growth_global1_x128 = IAlgebraPool::totalFeeGrowth1TokenCall::abi_decode_returns(&return_data[6], true)?._0;
+
+        let min_word_pos = (MIN_TICK.div_euclid(tick_spacing) >> 8) as i16;
+        let max_word_pos = ((MAX_TICK / tick_spacing) >> 8) as i16;
+        let word_calls = (min_word_pos..=max_word_pos)
+            .map(|word_position| IAlgebraPool::tickTableCall{wordPosition: word_position}.abi_encode())
+            .collect();
+
+        let mut tick_bitmap = HashMap::new();
+        let mut initialized_ticks = Vec::new();
+        for (word_pos, data) in (min_word_pos..=max_word_pos).zip(multicall(provider, multicall_address, pool_address, false, word_calls, block).await?) {
+            let word = IAlgebraPool::tickTableCall::abi_decode_returns(&data.returnData, true)?._0;
+            for bit_pos in 0..256 {
+                if word.bit(bit_pos) {
+                    initialized_ticks.push(((word_pos as i32) * 256 + bit_pos as i32) * tick_spacing);
+                }
+            }
+            tick_bitmap.insert(word_pos, word);
+        }
+
+        let tick_calls = initialized_ticks.iter().map(|&tick| IAlgebraPool::ticksCall{tick}.abi_encode()).collect();
+        let mut ticks = HashMap::new();
+        for (tick, data) in initialized_ticks.into_iter().zip(multicall(provider, multicall_address, pool_address, false, tick_calls, block).await?) {
+            // only the leading fields are decoded
+            let info = IAlgebraPool::ticksCall::abi_decode_returns(&data.returnData, false)?;
+            ticks.insert(tick, Info {
+                liquidity_gross: info.liquidityTotal,
+                liquidity_net: info.liquidityDelta,
+                fee_growth_outside0_x128: info.outerFeeGrowth0Token,
+                fee_growth_outside1_x128: info.outerFeeGrowth1Token,
+                initialized: true,
+                ..Default::default()
+            });
+        }
+
+        let tokens = load_tokens(provider, multicall_address, vec![token0_address, token1_address], block).await?;
+
+        let engine = PoolState {
+            pool_address,
+            multicall_address,
+            tick_spacing,
+            fee: global_state.fee_zto as u32,
+            fee_growth_global0_x128,
+            fee_growth_global1_x128,
+            protocol_fees: Default::default(),
+            token0: tokens[&token0_address].clone(),
+            token1: tokens[&token1_address].clone(),
+            tick_bitmap,
+            slot0: Slot0 {
+                sqrt_price_x96: global_state.price,
+                tick: global_state.tick,
+                observation_index: 0,
+                observation_cardinality: 0,
+                observation_cardinality_next: 0,
+                fee_protocol: 0,
+                unlocked: global_state.unlocked
+            },
+            liquidity,
+            ticks,
+            observations: HashMap::new(),
+            block,
+            block_timestamp: get_block_timestamp(provider, block).await? as u32
+        };
+
+        Ok(AlgebraPoolState {version, global_state, engine})
+    }
+
+    /// Reads the fee stored in `globalState` at `block`, the fee charged by the last swap up to `block`
+    pub async fn load_fee(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        block: BlockId
+    ) -> Result<()> {
+        let return_data = multicall(provider, self.engine.multicall_address, self.engine.pool_address, false, vec![self.version.global_state_call()], block).await?;
+        let global_state = self.version.decode_global_state(&return_data[0].returnData)?;
+
+        self.set_fee(global_state.fee_zto, global_state.fee_otz);
+        Ok(())
+    }
+
+    /// Replaces the fees of both directions, in hundredths of a bip, e.g. with the fee the pool's data storage operator
+    /// computes for the next block
+    pub fn set_fee(&mut self, fee_zto: u16, fee_otz: u16) {
+        self.global_state.fee_zto = fee_zto;
+        self.global_state.fee_otz = fee_otz;
+    }
+
+    /// Fee of a swap in the given direction, in hundredths of a bip
+    pub fn fee(&self, zero_for_one: bool) -> u32 {
+        if zero_for_one {self.global_state.fee_zto as u32} else {self.global_state.fee_otz as u32}
+    }
+
+    /// Simulates a swap with the fee loaded for its direction, the fee is not recomputed during the swap. Amounts are
+    /// exact, the community fee is left in the LP fee growth.
+    pub async fn swap(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256
+    ) -> Result<(I256, I256)> {
+        self.engine.fee = self.fee(zero_for_one);
+        let amounts = swap::swap(provider, &mut self.engine, zero_for_one, amount_specified, sqrt_price_limit_x96).await?;
+
+        self.global_state.price = self.engine.slot0.sqrt_price_x96;
+        self.global_state.tick = self.engine.slot0.tick;
+        Ok(amounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_utils::offline_provider,
+        uniswap_v3::{math::tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO}, position::tests::pool_state_with_liquidity}
+    };
+    use super::*;
+
+    #[tokio::test]
+    async fn directional_fee_swap_test() {
+        let provider = offline_provider();
+        let engine = pool_state_with_liquidity().await;
+
+        let global_state = GlobalState {
+            price: engine.slot0.sqrt_price_x96,
+            tick: engine.slot0.tick,
+            fee_zto: 100,
+            fee_otz: 3000,
+            community_fee_token0: 0,
+            community_fee_token1: 0,
+            unlocked: true
+        };
+        let pool = AlgebraPoolState {version: AlgebraVersion::DirectionalFee, global_state, engine};
+        let amount_in = I256::from_raw(U256::from(1000000000000000000u128));
+
+        // the cheap direction pays out more for the same input at the same price
+        let mut zero_for_one = pool.clone();
+        let (_, amount1) = zero_for_one.swap(&provider, true, amount_in, MIN_SQRT_RATIO + U256::from(1)).await.unwrap();
+        let (amount0, _) = pool.clone().swap(&provider, false, amount_in, MAX_SQRT_RATIO - U256::from(1)).await.unwrap();
+        assert!(amount1 < amount0);
+
+        assert!(zero_for_one.global_state.tick < 0);
+        assert_eq!(zero_for_one.global_state.price, zero_for_one.engine.slot0.sqrt_price_x96);
+    }
+
+    #[test]
+    fn decode_global_state_test() {
+        let word = |value: i64| I256::try_from(value).unwrap().to_be_bytes::<32>();
+        let price = U256::from(79228162514264337593543950336u128);
+
+        // price, tick, fee, timepointIndex, communityFeeToken0, communityFeeToken1, unlocked
+        let v1: Vec<u8> = [price.to_be_bytes::<32>(), word(-887272), word(500), word(7), word(100), word(150), word(1)].concat();
+        assert_eq!(AlgebraVersion::V1.decode_global_state(&v1.clone().into()).unwrap(), GlobalState {
+            price,
+            tick: -887272,
+            fee_zto: 500,
+            fee_otz: 500,
+            community_fee_token0: 100,
+            community_fee_token1: 150,
+            unlocked: true
+        });
+
+        // price, tick, feeZto, feeOtz, timepointIndex, communityFeeToken0, communityFeeToken1, unlocked
+        let directional_fee: Vec<u8> = [price.to_be_bytes::<32>(), word(42), word(100), word(3000), word(7), word(0), word(200), word(0)].concat();
+        assert_eq!(AlgebraVersion::DirectionalFee.decode_global_state(&directional_fee.into()).unwrap(), GlobalState {
+            price,
+            tick: 42,
+            fee_zto: 100,
+            fee_otz: 3000,
+            community_fee_token0: 0,
+            community_fee_token1: 200,
+            unlocked: false
+        });
+
+        // the shorter V1 layout does not decode as the directional fee one
+        assert!(AlgebraVersion::DirectionalFee.decode_global_state(&v1.into()).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::eth::{BlockId, BlockNumberOrTag, BlockTransactionsKind},
+    transports::http::{Client, Http}
+};
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+use std::future::Future;
+
+/// Protocol neutral view of a pool. Amounts are raw token amounts, prices are adjusted for token decimals.
+/// Quotes leave the pool untouched, `swap_exact_input` applies the trade to the local state the way the pool would.
+/// The trait is object safe so pools of different protocols can be held together as `Box<dyn Amm>`, they are loaded
+/// through `LoadAmm`.
+pub trait Amm {
+    /// Address the pool is identified by
+    fn address(&self) -> Address;
+
+    /// Tokens traded by the pool
+    fn tokens(&self) -> Vec<Address>;
+
+    /// Marginal price of `base` quoted in `quote`
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64>;
+
+    /// Amount of `token_out` received for exactly `amount_in` of `token_in`
+    fn quote_exact_input<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>>;
+
+    /// Amount of `token_in` needed to receive exactly `amount_out` of `token_out`
+    fn quote_exact_output<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>>;
+
+    /// Swaps exactly `amount_in` of `token_in` on the local state and returns the amount of `token_out` received
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>>;
+
+    /// Brings the local state forward to `to_block` (inclusive) from the pool's events. Event based implementations
+    /// need the state pinned to a block number, which `LoadAmm::load` takes care of.
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Loading of a pool, kept apart from `Amm` as it returns the concrete pool type
+pub trait LoadAmm: Amm + Sized {
+    /// Loads the pool at `address` as of `block` through the Multicall3 at `multicall_address`. Tags like `latest` are
+    /// resolved to a block number first, so the state can be synced forward.
+    fn load(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        address: Address,
+        block: BlockId
+    ) -> impl Future<Output = Result<Self>> + Send;
+}
+
+/// Number of the block `block` points to, leaving block numbers as they are
+pub async fn resolve_block_number(
+    provider: &RootProvider<Http<Client>>,
+    block: BlockId
+) -> Result<BlockId> {
+    let block = match block {
+        BlockId::Number(BlockNumberOrTag::Number(_)) => return Ok(block),
+        BlockId::Number(number) => provider.get_block_by_number(number, false).await?,
+        BlockId::Hash(hash) => provider.get_block_by_hash(hash.block_hash, BlockTransactionsKind::Hashes).await?
+    };
+
+    let number = block.ok_or(eyre!("Block not found"))?.header.number.ok_or(eyre!("Block is pending"))?;
+    Ok(BlockId::number(number))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::offline_provider, uniswap_v3::position::tests::pool_state_with_liquidity};
+    use super::*;
+
+    // pays 997 of the second token per 1000 of the first, standing in for a pool of another protocol
+    struct ConstantRatePool {
+        tokens: [Address; 2]
+    }
+
+    impl Amm for ConstantRatePool {
+        fn address(&self) -> Address {
+            Address::ZERO
+        }
+
+        fn tokens(&self) -> Vec<Address> {
+            self.tokens.to_vec()
+        }
+
+        fn spot_price(&self, _base: Address, _quote: Address) -> Result<f64> {
+            Ok(0.997)
+        }
+
+        fn quote_exact_input<'a>(
+            &'a self,
+            _provider: &'a RootProvider<Http<Client>>,
+            _token_in: Address,
+            _token_out: Address,
+            amount_in: U256
+        ) -> BoxFuture<'a, Result<U256>> {
+            Box::pin(async move {Ok(amount_in * U256::from(997) / U256::from(1000))})
+        }
+
+        fn quote_exact_output<'a>(
+            &'a self,
+            _provider: &'a RootProvider<Http<Client>>,
+            _token_in: Address,
+            _token_out: Address,
+            amount_out: U256
+        ) -> BoxFuture<'a, Result<U256>> {
+            Box::pin(async move {Ok((amount_out * U256::from(1000) + U256::from(996)) / U256::from(997))})
+        }
+
+        fn swap_exact_input<'a>(
+            &'a mut self,
+            provider: &'a RootProvider<Http<Client>>,
+            token_in: Address,
+            token_out: Address,
+            amount_in: U256
+        ) -> BoxFuture<'a, Result<U256>> {
+            self.quote_exact_input(provider, token_in, token_out, amount_in)
+        }
+
+        fn sync<'a>(
+            &'a mut self,
+            _provider: &'a RootProvider<Http<Client>>,
+            _to_block: u64
+        ) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async {Ok(())})
+        }
+    }
+
+    #[tokio::test]
+    async fn dyn_amm_test() {
+        let provider = offline_provider();
+        let pool_state = pool_state_with_liquidity().await;
+        let stub = ConstantRatePool {tokens: [pool_state.token0.address, pool_state.token1.address]};
+        let mut pools: Vec<Box<dyn Amm>> = vec![Box::new(pool_state), Box::new(stub)];
+
+        let amount_in = U256::from(1000000000000000000u128);
+        for pool in pools.iter_mut() {
+            let (token_in, token_out) = (pool.tokens()[0], pool.tokens()[1]);
+            let quoted_out = pool.quote_exact_input(&provider, token_in, token_out, amount_in).await.unwrap();
+            assert!(quoted_out > U256::ZERO && quoted_out < amount_in);
+            assert!(pool.quote_exact_output(&provider, token_in, token_out, quoted_out).await.unwrap() <= amount_in);
+            assert_eq!(pool.swap_exact_input(&provider, token_in, token_out, amount_in).await.unwrap(), quoted_out);
+        }
+
+        assert_eq!(resolve_block_number(&provider, BlockId::number(100)).await.unwrap(), BlockId::number(100));
+    }
+}
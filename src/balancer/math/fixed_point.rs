@@ -0,0 +1,92 @@
+use alloy::primitives::U256;
+use super::log_exp_math;
+use eyre::{eyre, Result};
+
+/// 18 decimal fixed point one
+pub const ONE: U256 = U256::from_limbs([1000000000000000000, 0, 0, 0]);
+const TWO: U256 = U256::from_limbs([2000000000000000000, 0, 0, 0]);
+const FOUR: U256 = U256::from_limbs([4000000000000000000, 0, 0, 0]);
+// 10^(-14), the maximum relative error of LogExpMath.pow
+const MAX_POW_RELATIVE_ERROR: U256 = U256::from_limbs([10000, 0, 0, 0]);
+
+pub fn mul_down(a: U256, b: U256) -> U256 {
+    a * b / ONE
+}
+
+pub fn mul_up(a: U256, b: U256) -> U256 {
+    let product = a * b;
+    if product.is_zero() {
+        U256::ZERO
+    } else {
+        // The traditional divUp formula is:
+        // divUp(x, y) := (x + y - 1) / y
+        // To avoid intermediate overflow in the addition, we distribute the division and get:
+        // divUp(x, y) := (x - 1) / y + 1
+        // Note that this requires x != 0, which we already tested for.
+        (product - U256::from(1)) / ONE + U256::from(1)
+    }
+}
+
+pub fn div_down(a: U256, b: U256) -> Result<U256> {
+    if b.is_zero() {
+        return Err(eyre!("ZERO_DIVISION"))
+    }
+    Ok(a * ONE / b)
+}
+
+pub fn div_up(a: U256, b: U256) -> Result<U256> {
+    if b.is_zero() {
+        return Err(eyre!("ZERO_DIVISION"))
+    }
+    if a.is_zero() {
+        Ok(U256::ZERO)
+    } else {
+        Ok((a * ONE - U256::from(1)) / b + U256::from(1))
+    }
+}
+
+/// @dev Returns x^y, assuming both are fixed point numbers, rounding down. The result is guaranteed to not be above
+/// the true value (that is, the error function expected - actual is always positive).
+pub fn pow_down(x: U256, y: U256) -> Result<U256> {
+    // Optimize for when y equals 1.0, 2.0 or 4.0, as those are very simple to implement and occur often in 50/50
+    // and 80/20 Weighted Pools
+    if y == ONE {
+        Ok(x)
+    } else if y == TWO {
+        Ok(mul_down(x, x))
+    } else if y == FOUR {
+        let square = mul_down(x, x);
+        Ok(mul_down(square, square))
+    } else {
+        let raw = log_exp_math::pow(x, y)?;
+        let max_error = mul_up(raw, MAX_POW_RELATIVE_ERROR) + U256::from(1);
+        Ok(raw.saturating_sub(max_error))
+    }
+}
+
+/// @dev Returns x^y, assuming both are fixed point numbers, rounding up. The result is guaranteed to not be below
+/// the true value (that is, the error function expected - actual is always negative).
+pub fn pow_up(x: U256, y: U256) -> Result<U256> {
+    // Optimize for when y equals 1.0, 2.0 or 4.0, as those are very simple to implement and occur often in 50/50
+    // and 80/20 Weighted Pools
+    if y == ONE {
+        Ok(x)
+    } else if y == TWO {
+        Ok(mul_up(x, x))
+    } else if y == FOUR {
+        let square = mul_up(x, x);
+        Ok(mul_up(square, square))
+    } else {
+        let raw = log_exp_math::pow(x, y)?;
+        let max_error = mul_up(raw, MAX_POW_RELATIVE_ERROR) + U256::from(1);
+        Ok(raw + max_error)
+    }
+}
+
+/// @dev Returns the complement of a value (1 - x), capped to 0 if x is larger than 1.
+///
+/// Useful when computing the complement for values with some level of relative error, as it strips this error and
+/// prevents intermediate negative values.
+pub fn complement(x: U256) -> U256 {
+    if x < ONE {ONE - x} else {U256::ZERO}
+}
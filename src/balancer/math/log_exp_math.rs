@@ -0,0 +1,343 @@
+use alloy::primitives::{I256, U256};
+use eyre::{eyre, Result};
+
+// Port of Balancer's LogExpMath: exponentiation and logarithm with 18 decimal fixed point numbers. Intermediate values
+// use 20 decimals (36 for logarithms close to one) and the results have an absolute error of about 1e-18.
+
+const fn i256(limbs: [u64; 4]) -> I256 {
+    I256::from_raw(U256::from_limbs(limbs))
+}
+
+// All fixed point multiplications and divisions are inlined. This means we need to divide by ONE when multiplying
+// two numbers, and multiply by ONE when dividing them.
+
+// All arguments and return values are 18 decimal fixed point numbers.
+const ONE_18: I256 = i256([1000000000000000000, 0, 0, 0]);
+
+// Internally, intermediate values are computed with higher precision as 20 decimal fixed point numbers, and in the
+// case of ln36, 36 decimals.
+const ONE_20: I256 = i256([7766279631452241920, 5, 0, 0]);
+const ONE_36: I256 = i256([12919594847110692864, 54210108624275221, 0, 0]);
+
+const TWO: I256 = i256([2, 0, 0, 0]);
+const HUNDRED: I256 = i256([100, 0, 0, 0]);
+
+// The domain of natural exponentiation is bound by the word size and number of decimals used.
+//
+// Because internally the result will be stored using 20 decimals, the largest possible result is
+// (2^255 - 1) / 10^20, which makes the largest exponent ln((2^255 - 1) / 10^20) = 130.700829182905140221.
+// The smallest possible result is 10^(-18), which makes largest negative argument
+// ln(10^(-18)) = -41.446531673892822312.
+// We use 130.0 and -41.0 to have some safety margin.
+const MAX_NATURAL_EXPONENT: I256 = i256([872791484033138688, 7, 0, 0]);
+const MIN_NATURAL_EXPONENT: I256 = i256([14340232221128654848, u64::MAX - 2, u64::MAX, u64::MAX]);
+
+// Bounds for ln_36's argument. Both ln(0.9) and ln(1.1) can be represented with 36 decimal places in a fixed point
+// 256 bit integer.
+const LN_36_LOWER_BOUND: I256 = i256([900000000000000000, 0, 0, 0]);
+const LN_36_UPPER_BOUND: I256 = i256([1100000000000000000, 0, 0, 0]);
+
+// 2^254 / ONE_20
+const MILD_EXPONENT_BOUND: U256 = U256::from_limbs([4720311721447089458, 12146009947018874712, 850705917302346158, 0]);
+
+// 18 decimal constants
+const X0: I256 = i256([17319535557742690304, 6, 0, 0]); // 2ˆ7
+const A0: I256 = i256([171843153341448192, 17670479068478958691, 114249481722274167, 0]); // eˆ(x0) (no decimals)
+const X1: I256 = i256([8659767778871345152, 3, 0, 0]); // 2ˆ6
+const A1: I256 = i256([17696838799657497472, 338008108, 0, 0]); // eˆ(x1) (no decimals)
+
+// 20 decimal constants, pairs of 2ˆ5 to 2ˆ-4 and their exponential
+const X_A: [(I256, I256); 10] = [
+    (i256([8713275248247570432, 173, 0, 0]), i256([17871857890508685312, 428059064879743, 0, 0])),
+    (i256([13580009660978561024, 86, 0, 0]), i256([12108528782385981184, 48171701, 0, 0])),
+    (i256([6790004830489280512, 43, 0, 0]), i256([14861217100182911056, 16159, 0, 0])),
+    (i256([12618374452099416064, 21, 0, 0]), i256([18025501570106181090, 295, 0, 0])),
+    (i256([15532559262904483840, 10, 0, 0]), i256([1035846944682958083, 40, 0, 0])),
+    (i256([7766279631452241920, 5, 0, 0]), i256([13573765813970800912, 14, 0, 0])),
+    (i256([13106511852580896768, 2, 0, 0]), i256([17298174480336401757, 8, 0, 0])),
+    (i256([6553255926290448384, 1, 0, 0]), i256([17722077226516838711, 6, 0, 0])),
+    (i256([12500000000000000000, 0, 0, 0]), i256([2634380864425321987, 6, 0, 0])),
+    (i256([6250000000000000000, 0, 0, 0]), i256([14215725523238184876, 5, 0, 0]))
+];
+
+/// @dev Exponentiation (x^y) with unsigned 18 decimal fixed point base and exponent.
+/// Reverts if ln(x) * y is smaller than `MIN_NATURAL_EXPONENT`, or larger than `MAX_NATURAL_EXPONENT`.
+pub fn pow(x: U256, y: U256) -> Result<U256> {
+    if y.is_zero() {
+        // We solve the 0^0 indetermination by making it equal one.
+        return Ok(ONE_18.into_raw())
+    }
+
+    if x.is_zero() {
+        return Ok(U256::ZERO)
+    }
+
+    // Instead of computing x^y directly, we instead rely on the properties of logarithms and exponentiation to
+    // arrive at that result. In particular, exp(ln(x)) = x, and ln(x^y) = y * ln(x). This means
+    // x^y = exp(y * ln(x)).
+
+    // The ln function takes a signed value, so we need to make sure x fits in the signed 256 bit range.
+    if x.bit(255) {
+        return Err(eyre!("X_OUT_OF_BOUNDS"))
+    }
+    let x_int256 = I256::from_raw(x);
+
+    // We will compute y * ln(x) in a single step. Depending on the value of x, we can either use ln or ln_36. In
+    // both cases, we leave the division by ONE_18 (due to fixed point multiplication) to the end.
+
+    // This prevents y * ln(x) from overflowing, and at the same time guarantees y fits in the signed 256 bit range.
+    if y >= MILD_EXPONENT_BOUND {
+        return Err(eyre!("Y_OUT_OF_BOUNDS"))
+    }
+    let y_int256 = I256::from_raw(y);
+
+    let logx_times_y = if LN_36_LOWER_BOUND < x_int256 && x_int256 < LN_36_UPPER_BOUND {
+        let ln_36_x = ln_36(x_int256);
+
+        // ln_36_x has 36 decimal places, so multiplying by y_int256 isn't as straightforward, since we can't just
+        // bring y_int256 to 36 decimal places, as it might overflow. Instead, we perform two 18 decimal
+        // multiplications and add the results: one with the first 18 decimals of ln_36_x, and one with the
+        // (downscaled) last 18 decimals.
+        (ln_36_x / ONE_18) * y_int256 + ((ln_36_x % ONE_18) * y_int256) / ONE_18
+    } else {
+        _ln(x_int256) * y_int256
+    };
+    let logx_times_y = logx_times_y / ONE_18;
+
+    // Finally, we compute exp(y * ln(x)) to arrive at x^y
+    if logx_times_y < MIN_NATURAL_EXPONENT || logx_times_y > MAX_NATURAL_EXPONENT {
+        return Err(eyre!("PRODUCT_OUT_OF_BOUNDS"))
+    }
+
+    Ok(exp(logx_times_y)?.into_raw())
+}
+
+/// @dev Natural exponentiation (e^x) with signed 18 decimal fixed point exponent.
+/// Reverts if `x` is smaller than MIN_NATURAL_EXPONENT, or larger than `MAX_NATURAL_EXPONENT`.
+pub fn exp(x: I256) -> Result<I256> {
+    if x < MIN_NATURAL_EXPONENT || x > MAX_NATURAL_EXPONENT {
+        return Err(eyre!("INVALID_EXPONENT"))
+    }
+
+    if x.is_negative() {
+        // We only handle positive exponents: e^(-x) is computed as 1 / e^x. We can safely make x positive since it
+        // fits in the signed 256 bit range (as it is larger than MIN_NATURAL_EXPONENT).
+        // Fixed point division requires multiplying by ONE_18.
+        return Ok((ONE_18 * ONE_18) / exp(-x)?)
+    }
+
+    // First, we use the fact that e^(x+y) = e^x * e^y to decompose x into a sum of powers of two, which we call x_n,
+    // where x_n == 2^(7 - n), and e^x_n = a_n has been precomputed. We choose the first x_n, x0, to equal 2^7
+    // because all larger powers are larger than MAX_NATURAL_EXPONENT, and therefore not present in the
+    // decomposition.
+    // At the end of this process we will have the product of all e^x_n = a_n that apply, and the remainder of this
+    // decomposition, which will be lower than the smallest x_n.
+    // exp(x) = k_0 * a_0 * k_1 * a_1 * ... + k_n * a_n * exp(remainder), where each k_n equals either 0 or 1.
+    // We mutate x by subtracting x_n, making it the remainder of the decomposition.
+
+    // The first two a_n (e^(2^7) and e^(2^6)) are too large if stored as 18 decimal numbers, and could cause
+    // intermediate overflows. Instead we store them as plain integers, with 0 decimals.
+    // Additionally, x0 + x1 is larger than MAX_NATURAL_EXPONENT, which means they will not both be present in the
+    // decomposition.
+
+    // For each x_n, we test if that term is present in the decomposition (if x is larger than it), and if so deduct
+    // it and compute the accumulated product.
+    let (mut x, first_an) = if x >= X0 {
+        (x - X0, A0)
+    } else if x >= X1 {
+        (x - X1, A1)
+    } else {
+        (x, I256::ONE)
+    };
+
+    // We now transform x into a 20 decimal fixed point number, to have enhanced precision when computing the
+    // smaller terms.
+    x *= HUNDRED;
+
+    // `product` is the accumulated product of all a_n (except a0 and a1), which starts at 20 decimal fixed point
+    // one. Recall that fixed point multiplication requires dividing by ONE_20.
+    let mut product = ONE_20;
+    for (x_n, a_n) in X_A.iter().take(8) {
+        if x >= *x_n {
+            x -= *x_n;
+            product = (product * *a_n) / ONE_20;
+        }
+    }
+    // x10 and x11 are unnecessary here since we have high enough precision already.
+
+    // Now we need to compute e^x, where x is small (in particular, it is smaller than x9). We use the Taylor series
+    // expansion for e^x: 1 + x + (x^2 / 2!) + (x^3 / 3!) + ... + (x^n / n!).
+
+    // The initial term is simply x.
+    let mut series_sum = ONE_20 + x;
+    let mut term = x;
+
+    // Each term (x^n / n!) equals the previous one times x, divided by n. Since x is a fixed point number,
+    // multiplying by it requires dividing by ONE_20, but dividing by the non-fixed point n values does not.
+    // 12 Taylor terms are sufficient for 18 decimal precision.
+    for n in 2..=12u64 {
+        term = ((term * x) / ONE_20) / I256::from_raw(U256::from(n));
+        series_sum += term;
+    }
+
+    // We now have the first a_n (with no decimals), and the product of all other a_n present, and the Taylor
+    // approximation of the exponentiation of the remainder (both with 20 decimals). All that remains is to multiply
+    // all three (one 20 decimal fixed point multiplication, dividing by ONE_20, and one integer multiplication),
+    // and then drop two digits to return an 18 decimal value.
+    Ok((((product * series_sum) / ONE_20) * first_an) / HUNDRED)
+}
+
+/// @dev Natural logarithm (ln(a)) with signed 18 decimal fixed point argument.
+pub fn ln(a: I256) -> Result<I256> {
+    // The real natural logarithm is not defined for negative numbers or zero.
+    if a <= I256::ZERO {
+        return Err(eyre!("OUT_OF_BOUNDS"))
+    }
+    if LN_36_LOWER_BOUND < a && a < LN_36_UPPER_BOUND {
+        Ok(ln_36(a) / ONE_18)
+    } else {
+        Ok(_ln(a))
+    }
+}
+
+/// @dev Internal natural logarithm (ln(a)) with signed 18 decimal fixed point argument.
+fn _ln(a: I256) -> I256 {
+    if a < ONE_18 {
+        // Since ln(a^k) = k * ln(a), we can compute ln(a) as ln(a) = ln((1/a)^(-1)) = - ln((1/a)). If a is less
+        // than one, 1/a will be greater than one, and this if statement will not be entered in the recursive call.
+        // Fixed point division requires multiplying by ONE_18.
+        return -_ln((ONE_18 * ONE_18) / a)
+    }
+
+    // First, we use the fact that ln^(a * b) = ln(a) + ln(b) to decompose ln(a) into a sum of powers of two, which
+    // we call x_n, where x_n == 2^(7 - n), which are the natural logarithm of precomputed quantities a_n (that is,
+    // ln(a_n) = x_n). We choose the first x_n, x0, to equal 2^7 because the exponential of all larger powers cannot
+    // be represented as 18 fixed point decimal numbers in 256 bit integers.
+    // At the end of this process we will have the sum of all x_n = ln(a_n) that apply, and the remainder of this
+    // decomposition, which will be lower than the smallest a_n.
+    // ln(a) = k_0 * x_0 + k_1 * x_1 + ... + k_n * x_n + ln(remainder), where each k_n equals either 0 or 1.
+    // We mutate a by subtracting a_n, making it the remainder of the decomposition.
+
+    // For reasons related to how `exp` works, the first two a_n (e^(2^7) and e^(2^6)) are not stored as fixed point
+    // numbers with 18 decimals, but instead as plain integers with 0 decimals, so we need to multiply them by
+    // ONE_18 to convert them to fixed point.
+    // For each a_n, we test if that term is present in the decomposition (if a is larger than it), and if so divide
+    // by it and compute the accumulated sum.
+    let mut a = a;
+    let mut sum = I256::ZERO;
+    if a >= A0 * ONE_18 {
+        a /= A0; // Integer, not fixed point division
+        sum += X0;
+    }
+
+    if a >= A1 * ONE_18 {
+        a /= A1; // Integer, not fixed point division
+        sum += X1;
+    }
+
+    // All other a_n and x_n are stored as 20 digit fixed point numbers, so we convert the sum and a to this format.
+    sum *= HUNDRED;
+    a *= HUNDRED;
+
+    // Because further a_n are  20 digit fixed point numbers, we multiply by ONE_20 when dividing by them.
+    for (x_n, a_n) in X_A.iter() {
+        if a >= *a_n {
+            a = (a * ONE_20) / *a_n;
+            sum += *x_n;
+        }
+    }
+
+    // a is now a small number (smaller than a_11, which roughly equals 1.06). This means we can use a Taylor series
+    // that converges rapidly for values of `a` close to one - the same one used in ln_36.
+    // Let z = (a - 1) / (a + 1).
+    // ln(a) = 2 * (z + z^3 / 3 + z^5 / 5 + z^7 / 7 + ... + z^(2 * n + 1) / (2 * n + 1))
+
+    // Recall that 20 digit fixed point division requires multiplying by ONE_20, and multiplication requires
+    // division by ONE_20.
+    let z = ((a - ONE_20) * ONE_20) / (a + ONE_20);
+    let z_squared = (z * z) / ONE_20;
+
+    // num is the numerator of the series: the z^(2 * n + 1) term
+    let mut num = z;
+
+    // seriesSum holds the accumulated sum of each term in the series, starting with the initial z
+    let mut series_sum = num;
+
+    // In each step, the numerator is multiplied by z^2
+    for denominator in [3u64, 5, 7, 9, 11] {
+        num = (num * z_squared) / ONE_20;
+        series_sum += num / I256::from_raw(U256::from(denominator));
+    }
+
+    // 6 Taylor terms are sufficient for 36 decimal precision.
+
+    // Finally, we multiply by 2 (non fixed point) to compute ln(remainder)
+    series_sum *= TWO;
+
+    // We now have the sum of all x_n present, and the Taylor approximation of the logarithm of the remainder (both
+    // with 20 decimals). All that remains is to sum these two, and then drop two digits to return a 18 decimal
+    // value.
+    (sum + series_sum) / HUNDRED
+}
+
+/// @dev Internal high precision (36 decimal places) natural logarithm (ln(x)) with signed 18 decimal fixed point argument,
+/// for x close to one.
+///
+/// Should only be used if x is between LN_36_LOWER_BOUND and LN_36_UPPER_BOUND.
+fn ln_36(x: I256) -> I256 {
+    // Since ln(1) = 0, a value of x close to one will yield a very small result, which makes using 36 digits
+    // worthwhile.
+
+    // First, we transform x to a 36 digit fixed point value.
+    let x = x * ONE_18;
+
+    // We will use the following Taylor expansion, which converges very rapidly. Let z = (x - 1) / (x + 1).
+    // ln(x) = 2 * (z + z^3 / 3 + z^5 / 5 + z^7 / 7 + ... + z^(2 * n + 1) / (2 * n + 1))
+
+    // Recall that 36 digit fixed point division requires multiplying by ONE_36, and multiplication requires
+    // division by ONE_36.
+    let z = ((x - ONE_36) * ONE_36) / (x + ONE_36);
+    let z_squared = (z * z) / ONE_36;
+
+    // num is the numerator of the series: the z^(2 * n + 1) term
+    let mut num = z;
+
+    // seriesSum holds the accumulated sum of each term in the series, starting with the initial z
+    let mut series_sum = num;
+
+    // In each step, the numerator is multiplied by z^2
+    for denominator in [3u64, 5, 7, 9, 11, 13, 15] {
+        num = (num * z_squared) / ONE_36;
+        series_sum += num / I256::from_raw(U256::from(denominator));
+    }
+
+    // 8 Taylor terms are sufficient for 36 decimal precision.
+
+    // All that remains is multiplying by 2 (non fixed point).
+    series_sum * TWO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pow_test() {
+        // pinned to this port's rounding, one or two wei below the exact values sqrt(2) = 1.414213562373095048801...
+        // and 1.05^2.5 = 1.129726321947045721750..., well within the 1e-14 MAX_POW_RELATIVE_ERROR of the library
+        let one = U256::from(1000000000000000000u64);
+        assert_eq!(pow(U256::from(2) * one, one / U256::from(2)).unwrap(), U256::from(1414213562373095047u64));
+        assert_eq!(pow(U256::from(1050000000000000000u64), U256::from(2500000000000000000u64)).unwrap(), U256::from(1129726321947045720u64));
+        assert_eq!(pow(U256::from(12345), U256::ZERO).unwrap(), one);
+
+        // ln takes the 36 decimal path close to one and truncates towards zero for negative results, ln(10) is
+        // 2.302585092994045684017...
+        assert_eq!(ln(I256::from_raw(U256::from(10) * one)).unwrap(), I256::from_raw(U256::from(2302585092994045683u64)));
+        assert_eq!(ln(I256::from_raw(U256::from(1010000000000000000u64))).unwrap(), I256::from_raw(U256::from(9950330853168082u64)));
+        assert_eq!(ln(I256::from_raw(one / U256::from(2))).unwrap(), -I256::from_raw(U256::from(693147180559945309u64)));
+        assert!(ln(I256::ZERO).is_err());
+
+        assert_eq!(exp(-I256::from_raw(U256::from(3) * one)).unwrap(), I256::from_raw(U256::from(49787068367863942u64)));
+        assert!(exp(I256::from_raw(U256::from(131) * one)).is_err());
+    }
+}
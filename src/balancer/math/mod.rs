@@ -0,0 +1,3 @@
+pub mod log_exp_math;
+pub mod fixed_point;
+pub mod weighted_math;
@@ -0,0 +1,111 @@
+use alloy::primitives::U256;
+use super::fixed_point::{complement, div_down, div_up, mul_down, mul_up, pow_up, ONE};
+use eyre::{eyre, Result};
+
+// Swap limits: amounts swapped may not be larger than this percentage of total balance.
+pub const MAX_IN_RATIO: U256 = U256::from_limbs([300000000000000000, 0, 0, 0]);
+pub const MAX_OUT_RATIO: U256 = U256::from_limbs([300000000000000000, 0, 0, 0]);
+
+/// @notice Computes how many tokens can be taken out of a pool if `amountIn` are sent, given the current balances
+/// and weights.
+/// @dev All values are upscaled 18 decimal fixed point numbers, the swap fee is already deducted from `amountIn`
+pub fn calc_out_given_in(
+    balance_in: U256,
+    weight_in: U256,
+    balance_out: U256,
+    weight_out: U256,
+    amount_in: U256
+) -> Result<U256> {
+    /**********************************************************************************************
+    // outGivenIn                                                                                //
+    // aO = amountOut                                                                            //
+    // bO = balanceOut                                                                           //
+    // bI = balanceIn              /      /            bI             \    (wI / wO) \           //
+    // aI = amountIn    aO = bO * |  1 - | --------------------------  | ^            |          //
+    // wI = weightIn               \      \       ( bI + aI )         /              /           //
+    // wO = weightOut                                                                            //
+    **********************************************************************************************/
+
+    // Amount out, so we round down overall.
+
+    // The multiplication rounds down, and the subtrahend (power) rounds up (so the base rounds up too).
+    // Because bI / (bI + aI) <= 1, the exponent rounds down.
+
+    // Cannot exceed maximum in ratio
+    if amount_in > mul_down(balance_in, MAX_IN_RATIO) {
+        return Err(eyre!("MAX_IN_RATIO"))
+    }
+
+    let denominator = balance_in + amount_in;
+    let base = div_up(balance_in, denominator)?;
+    let exponent = div_down(weight_in, weight_out)?;
+    let power = pow_up(base, exponent)?;
+
+    Ok(mul_down(balance_out, complement(power)))
+}
+
+/// @notice Computes how many tokens must be sent to a pool in order to take `amountOut`, given the current balances
+/// and weights.
+/// @dev All values are upscaled 18 decimal fixed point numbers, the swap fee is added to the result afterwards
+pub fn calc_in_given_out(
+    balance_in: U256,
+    weight_in: U256,
+    balance_out: U256,
+    weight_out: U256,
+    amount_out: U256
+) -> Result<U256> {
+    /**********************************************************************************************
+    // inGivenOut                                                                                //
+    // aO = amountOut                                                                            //
+    // bO = balanceOut                                                                           //
+    // bI = balanceIn              /  /            bO             \    (wO / wI)      \          //
+    // aI = amountIn    aI = bI * |  | --------------------------  | ^            - 1  |         //
+    // wI = weightIn               \  \       ( bO - aO )         /                   /          //
+    // wO = weightOut                                                                            //
+    **********************************************************************************************/
+
+    // Amount in, so we round up overall.
+
+    // The multiplication rounds up, and the power rounds up (so the base rounds up too).
+    // Because b0 / (b0 - a0) >= 1, the exponent rounds up.
+
+    // Cannot exceed maximum out ratio
+    if amount_out > mul_down(balance_out, MAX_OUT_RATIO) {
+        return Err(eyre!("MAX_OUT_RATIO"))
+    }
+
+    let base = div_up(balance_out, balance_out - amount_out)?;
+    let exponent = div_up(weight_out, weight_in)?;
+    let power = pow_up(base, exponent)?;
+
+    // Because the base is larger than one (and the power rounds up), the power should always be larger than one, so
+    // the following subtraction should never revert.
+    let ratio = power - ONE;
+
+    Ok(mul_up(balance_in, ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calc_out_given_in_test() {
+        // 80/20 pool of 10m BAL and 5000 WETH, 990 BAL in after a 1% fee. The amount is this port's output, the closed
+        // form Bo * (1 - (Bi / (Bi + Ai)) ^ (Wi / Wo)) checks it up to the pow error.
+        let (balance_in, balance_out) = (U256::from(10000000) * ONE, U256::from(5000) * ONE);
+        let (weight_in, weight_out) = (U256::from(800000000000000000u64), U256::from(200000000000000000u64));
+        let amount_in = U256::from(990) * ONE;
+        let amount_out = calc_out_given_in(balance_in, weight_in, balance_out, weight_out, amount_in).unwrap();
+        assert_eq!(amount_out, U256::from(1979510047013060000u64));
+        let closed_form = 5000.0 * (1.0 - (1e7f64 / (1e7 + 990.0)).powi(4));
+        assert!((f64::from(amount_out) / 1e18 - closed_form).abs() / closed_form < 1e-10);
+
+        // buying the output back costs at least the input
+        let amount_in_back = calc_in_given_out(balance_in, weight_in, balance_out, weight_out, amount_out).unwrap();
+        assert!(amount_in_back >= amount_in && amount_in_back - amount_in < ONE / U256::from(1000000));
+
+        assert!(calc_out_given_in(balance_in, weight_in, balance_out, weight_out, balance_in).is_err());
+        assert!(calc_in_given_out(balance_in, weight_in, balance_out, weight_out, balance_out / U256::from(2)).is_err());
+    }
+}
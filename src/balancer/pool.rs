@@ -0,0 +1,311 @@
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use crate::{
+    amm::{resolve_block_number, Amm, LoadAmm},
+    uniswap_v3::{
+        multicall::multicall,
+        pool::{load_tokens, Token}
+    }
+};
+use super::math::{
+    fixed_point::{complement, div_down, div_up, mul_down, mul_up, ONE},
+    weighted_math
+};
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+sol! {
+    #[sol(rpc)]
+    interface IWeightedPool {
+        function getPoolId() external view returns (bytes32);
+
+        function getVault() external view returns (address);
+
+        function getNormalizedWeights() external view returns (uint256[] memory);
+
+        function getSwapFeePercentage() external view returns (uint256);
+
+        function getScalingFactors() external view returns (uint256[] memory);
+    }
+}
+
+sol! {
+    #[sol(rpc)]
+    interface IVault {
+        function getPoolTokens(bytes32 poolId)
+            external
+            view
+            returns (
+                address[] memory tokens,
+                uint256[] memory balances,
+                uint256 lastChangeBlock
+            );
+    }
+}
+
+/// State of a Balancer V2 weighted pool. Balances are held by the Vault in the decimals of each token, scaling
+/// factors upscale them to 18 decimals (and apply rate providers when the pool has them).
+#[derive(Clone)]
+pub struct WeightedPoolState {
+    pub pool_address: Address,
+    // Multicall3 the pool is loaded and synced through
+    pub multicall_address: Address,
+    pub pool_id: B256,
+    pub vault: Address,
+    pub tokens: Vec<Token>,
+    pub balances: Vec<U256>,
+    // normalized weights, 18 decimals summing to one
+    pub weights: Vec<U256>,
+    pub scaling_factors: Vec<U256>,
+    // 18 decimals, 1e16 is a 1% fee
+    pub swap_fee_percentage: U256,
+    pub block: BlockId
+}
+
+impl WeightedPoolState {
+    /// Loads the weights and swap fee from the pool and its tokens and balances from the Vault `getPoolTokens`. Pools
+    /// without `getScalingFactors` are scaled from the token decimals.
+    pub async fn load_at_block(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let encoded_calls = vec![
+            IWeightedPool::getPoolIdCall{}.abi_encode(),
+            IWeightedPool::getVaultCall{}.abi_encode(),
+            IWeightedPool::getNormalizedWeightsCall{}.abi_encode(),
+            IWeightedPool::getSwapFeePercentageCall{}.abi_encode(),
+            IWeightedPool::getScalingFactorsCall{}.abi_encode()
+        ];
+        let return_data = multicall(provider, multicall_address, pool_address, true, encoded_calls, block).await?;
+        if return_data[..4].iter().any(|result| !result.success) {
+            return Err(eyre!("{} is not a Balancer weighted pool", pool_address))
+        }
+
+        let pool_id = IWeightedPool::getPoolIdCall::abi_decode_returns(&return_data[0].returnData, true)?._0;
+        let vault = IWeightedPool::getVaultCall::abi_decode_returns(&return_data[1].returnData, true)?._0;
+        let weights = IWeightedPool::getNormalizedWeightsCall::abi_decode_returns(&return_data[2].returnData, true)?._0;
+        let swap_fee_percentage = IWeightedPool::getSwapFeePercentageCall::abi_decode_returns(&return_data[3].returnData, true)?._0;
+
+        let pool_tokens = IVault::new(vault, provider).getPoolTokens(pool_id).block(block).call().await?;
+        if pool_tokens.tokens.len() != weights.len() {
+            return Err(eyre!("Pool {} has {} tokens and {} weights", pool_address, pool_tokens.tokens.len(), weights.len()))
+        }
+
+        let token_data = load_tokens(provider, multicall_address, pool_tokens.tokens.clone(), block).await?;
+        let tokens: Vec<Token> = pool_tokens.tokens.iter().map(|token| token_data[token].clone()).collect();
+
+        let scaling_factors = if return_data[4].success {
+            IWeightedPool::getScalingFactorsCall::abi_decode_returns(&return_data[4].returnData, true)?._0
+        } else {
+            tokens.iter().map(|token| ONE * U256::from(10).pow(U256::from(18 - token.decimals as u64))).collect()
+        };
+
+        Ok(WeightedPoolState {
+            pool_address,
+            multicall_address,
+            pool_id,
+            vault,
+            tokens,
+            balances: pool_tokens.balances,
+            weights,
+            scaling_factors,
+            swap_fee_percentage,
+            block
+        })
+    }
+
+    /// Index of `token` in the pool
+    pub fn token_index(&self, token: Address) -> Result<usize> {
+        self.tokens
+            .iter()
+            .position(|pool_token| pool_token.address == token)
+            .ok_or(eyre!("Balancer pool {} does not hold {}", self.pool_address, token))
+    }
+
+    /// Amount of token `j` received for exactly `amount_in` of token `i`, as `onSwap` with GIVEN_IN
+    pub fn on_swap_given_in(&self, i: usize, j: usize, amount_in: U256) -> Result<U256> {
+        self.check_indices(i, j)?;
+
+        // Fees are subtracted before scaling, to reduce the complexity of the rounding direction analysis.
+        let amount_in = amount_in - mul_up(amount_in, self.swap_fee_percentage);
+
+        // All token amounts are upscaled.
+        let balance_in = self.upscale(i, self.balances[i]);
+        let balance_out = self.upscale(j, self.balances[j]);
+        let amount_in = self.upscale(i, amount_in);
+
+        let amount_out = weighted_math::calc_out_given_in(balance_in, self.weights[i], balance_out, self.weights[j], amount_in)?;
+
+        // amountOut tokens are exiting the Pool, so we round down.
+        div_down(amount_out, self.scaling_factors[j])
+    }
+
+    /// Amount of token `i` needed to receive exactly `amount_out` of token `j`, as `onSwap` with GIVEN_OUT
+    pub fn on_swap_given_out(&self, i: usize, j: usize, amount_out: U256) -> Result<U256> {
+        self.check_indices(i, j)?;
+
+        // All token amounts are upscaled.
+        let balance_in = self.upscale(i, self.balances[i]);
+        let balance_out = self.upscale(j, self.balances[j]);
+        let amount_out = self.upscale(j, amount_out);
+
+        let amount_in = weighted_math::calc_in_given_out(balance_in, self.weights[i], balance_out, self.weights[j], amount_out)?;
+
+        // amountIn tokens are entering the Pool, so we round up.
+        let amount_in = div_up(amount_in, self.scaling_factors[i])?;
+
+        // Fees are added after scaling happens, to reduce the complexity of the rounding direction analysis.
+        div_up(amount_in, complement(self.swap_fee_percentage))
+    }
+
+    /// Simulates a GIVEN_IN swap through the Vault and returns the amount received. The swap fee stays in the pool
+    /// balance, protocol fees are only charged on joins and exits.
+    pub fn swap(&mut self, i: usize, j: usize, amount_in: U256) -> Result<U256> {
+        let amount_out = self.on_swap_given_in(i, j, amount_in)?;
+        self.balances[i] += amount_in;
+        self.balances[j] -= amount_out;
+        Ok(amount_out)
+    }
+
+    fn upscale(&self, index: usize, amount: U256) -> U256 {
+        mul_down(amount, self.scaling_factors[index])
+    }
+
+    fn check_indices(&self, i: usize, j: usize) -> Result<()> {
+        if i == j || i >= self.tokens.len() || j >= self.tokens.len() {
+            return Err(eyre!("Invalid token indices {} and {} for Balancer pool {}", i, j, self.pool_address))
+        }
+        Ok(())
+    }
+}
+
+impl Amm for WeightedPoolState {
+    fn address(&self) -> Address {
+        self.pool_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        self.tokens.iter().map(|token| token.address).collect()
+    }
+
+    /// Spot price before fees, (balance quote / weight quote) / (balance base / weight base) in upscaled balances
+    /// converted back to whole tokens
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let (i, j) = (self.token_index(base)?, self.token_index(quote)?);
+        let (balance_base, balance_quote) = (self.upscale(i, self.balances[i]), self.upscale(j, self.balances[j]));
+        let price = (f64::from(balance_quote) / f64::from(self.weights[j])) / (f64::from(balance_base) / f64::from(self.weights[i]));
+
+        // scaling factors map a raw amount to 18 decimals, whole tokens are 10^decimals raw amounts
+        Ok(price * f64::from(self.scaling_factors[i]) / f64::from(self.scaling_factors[j])
+            * 10f64.powi(self.tokens[i].decimals as i32 - self.tokens[j].decimals as i32))
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.on_swap_given_in(self.token_index(token_in)?, self.token_index(token_out)?, amount_in)
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.on_swap_given_out(self.token_index(token_in)?, self.token_index(token_out)?, amount_out)
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let (i, j) = (self.token_index(token_in)?, self.token_index(token_out)?);
+            self.swap(i, j, amount_in)
+        })
+    }
+
+    /// Reloads the pool at `to_block`, joins and exits move the Vault balances as well as swaps
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            *self = WeightedPoolState::load_at_block(provider, self.multicall_address, self.pool_address, BlockId::number(to_block)).await?;
+            Ok(())
+        })
+    }
+}
+
+impl LoadAmm for WeightedPoolState {
+    async fn load(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let block = resolve_block_number(provider, block).await?;
+        WeightedPoolState::load_at_block(provider, multicall_address, address, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use crate::{test_utils::{offline_provider, token}, uniswap_v3::multicall::MULTICALL3_ADDRESS};
+    use super::*;
+
+    #[tokio::test]
+    async fn weighted_pool_amm_test() {
+        let provider = offline_provider();
+        let (usdc, weth) = (address!("0000000000000000000000000000000000000001"), address!("0000000000000000000000000000000000000002"));
+        // 50/50 pool of 10m USDC and 5000 WETH with a 0.3% fee. Equal weights make the swap constant product, the
+        // pinned amounts agree with 5000 * (1 - 1e7 / (1e7 + 2000 * 0.997)) = 0.99680123783...
+        let mut pool = WeightedPoolState {
+            pool_address: Address::ZERO,
+            multicall_address: MULTICALL3_ADDRESS,
+            pool_id: B256::ZERO,
+            vault: Address::ZERO,
+            tokens: vec![token(usdc, 6), token(weth, 18)],
+            balances: vec![U256::from(10000000000000u64), U256::from(5000) * ONE],
+            weights: vec![ONE / U256::from(2); 2],
+            scaling_factors: vec![ONE * U256::from(1000000000000u64), ONE],
+            swap_fee_percentage: U256::from(3000000000000000u64),
+            block: BlockId::number(1)
+        };
+
+        assert!((pool.spot_price(weth, usdc).unwrap() - 2000.0).abs() < 1e-9);
+        assert_eq!(pool.quote_exact_output(&provider, usdc, weth, ONE).await.unwrap(), U256::from(2006419340u64));
+
+        let amount_in = U256::from(2000000000u64);
+        assert_eq!(pool.quote_exact_input(&provider, usdc, weth, amount_in).await.unwrap(), U256::from(996801237833175000u64));
+        assert!((0.996801237833175f64 - 5000.0 * (1.0 - 1e7 / (1e7 + 2000.0 * 0.997))).abs() < 1e-12);
+
+        // the whole input including the fee stays in the pool
+        assert_eq!(pool.swap_exact_input(&provider, usdc, weth, amount_in).await.unwrap(), U256::from(996801237833175000u64));
+        assert_eq!(pool.balances[0], U256::from(10002000000000u64));
+        assert_eq!(pool.balances[1], U256::from(5000) * ONE - U256::from(996801237833175000u64));
+        assert!(pool.quote_exact_input(&provider, usdc, Address::ZERO, amount_in).await.is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use alloy::{
+    primitives::{address, Address, B256},
+    providers::{Provider, RootProvider},
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::{
+    dex::{Dex, UNISWAP_V3},
+    multicall::MULTICALL3_ADDRESS,
+    utils::{UNISWAP_V3_POOL_INIT_CODE_HASH, UNISWAP_V3_POSITION_MANAGER_ADDRESS}
+};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+/// Uniswap V3 deployment and Multicall3 of a chain, the Multicall3 address is what every loader of the crate takes as
+/// `multicall_address`. Deployments of chains that are not built in are read from JSON,
+/// where `multicall` and `pool_init_code_hash` default to the usual Multicall3 and Uniswap V3 pool init code hash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainDeployment {
+    pub chain_id: u64,
+    pub name: String,
+    pub factory: Address,
+    // QuoterV2
+    pub quoter: Address,
+    pub position_manager: Address,
+    #[serde(default = "default_multicall")]
+    pub multicall: Address,
+    #[serde(default = "default_pool_init_code_hash")]
+    pub pool_init_code_hash: B256
+}
+
+fn default_multicall() -> Address {
+    MULTICALL3_ADDRESS
+}
+
+fn default_pool_init_code_hash() -> B256 {
+    UNISWAP_V3_POOL_INIT_CODE_HASH
+}
+
+pub fn mainnet() -> ChainDeployment {
+    ChainDeployment {
+        chain_id: 1,
+        name: "Ethereum".to_string(),
+        factory: UNISWAP_V3.factory,
+        quoter: UNISWAP_V3.quoter,
+        position_manager: UNISWAP_V3_POSITION_MANAGER_ADDRESS,
+        multicall: MULTICALL3_ADDRESS,
+        pool_init_code_hash: UNISWAP_V3.pool_init_code_hash
+    }
+}
+
+pub fn arbitrum() -> ChainDeployment {
+    ChainDeployment {chain_id: 42161, name: "Arbitrum One".to_string(), ..mainnet()}
+}
+
+pub fn optimism() -> ChainDeployment {
+    ChainDeployment {chain_id: 10, name: "OP Mainnet".to_string(), ..mainnet()}
+}
+
+pub fn polygon() -> ChainDeployment {
+    ChainDeployment {chain_id: 137, name: "Polygon".to_string(), ..mainnet()}
+}
+
+pub fn base() -> ChainDeployment {
+    ChainDeployment {
+        chain_id: 8453,
+        name: "Base".to_string(),
+        factory: address!("33128a8fC17869897dcE68Ed026d694621f6FDfD"),
+        quoter: address!("3d4e44Eb1374240CE5F1B871ab261CD16335B76a"),
+        position_manager: address!("03a520b32C04BF3bEEf7BEb72E919cf822Ed34f1"),
+        ..mainnet()
+    }
+}
+
+pub fn bnb() -> ChainDeployment {
+    ChainDeployment {
+        chain_id: 56,
+        name: "BNB Smart Chain".to_string(),
+        factory: address!("dB1d10011AD0Ff90774D0C6Bb92e5C5c8b4461F7"),
+        quoter: address!("78D78E420Da98ad378D7799bE8f4AF69033EB077"),
+        position_manager: address!("7b8A01B39D58278b5DE7e48c8449c9f4F5170613"),
+        ..mainnet()
+    }
+}
+
+/// Deployments known without a config file
+pub fn builtin_chains() -> Vec<ChainDeployment> {
+    vec![mainnet(), arbitrum(), optimism(), base(), polygon(), bnb()]
+}
+
+impl ChainDeployment {
+    /// Uniswap V3 of the chain as a `Dex`, for address derivation and QuoterV2 quotes
+    pub fn dex(&self) -> Dex {
+        Dex {
+            factory: self.factory,
+            pool_deployer: self.factory,
+            pool_init_code_hash: self.pool_init_code_hash,
+            quoter: self.quoter,
+            ..UNISWAP_V3
+        }
+    }
+}
+
+/// Deployments by chain id, the default registry holds `builtin_chains` and others are added with `register` or
+/// read from a config file with `load_config`
+#[derive(Debug, Clone)]
+pub struct ChainRegistry {
+    pub deployments: HashMap<u64, ChainDeployment>
+}
+
+impl Default for ChainRegistry {
+    fn default() -> Self {
+        ChainRegistry {deployments: builtin_chains().into_iter().map(|deployment| (deployment.chain_id, deployment)).collect()}
+    }
+}
+
+impl ChainRegistry {
+    /// Adds the deployment of a chain, replacing and returning the one registered for the same chain id
+    pub fn register(&mut self, deployment: ChainDeployment) -> Option<ChainDeployment> {
+        self.deployments.insert(deployment.chain_id, deployment)
+    }
+
+    /// Registers every deployment of a JSON array on top of the current ones
+    pub fn register_json(&mut self, json: &str) -> Result<()> {
+        let deployments: Vec<ChainDeployment> = serde_json::from_str(json)?;
+        for deployment in deployments {
+            self.register(deployment);
+        }
+        Ok(())
+    }
+
+    /// Built in deployments with the ones of the JSON config at `path` added, entries of a built in chain replace it
+    pub fn load_config(path: impl AsRef<Path>) -> Result<Self> {
+        let mut registry = ChainRegistry::default();
+        registry.register_json(&fs::read_to_string(path)?)?;
+        Ok(registry)
+    }
+
+    /// Deployment of `chain_id`, errors for chains that are not registered
+    pub fn get(&self, chain_id: u64) -> Result<&ChainDeployment> {
+        self.deployments.get(&chain_id).ok_or(eyre!("No deployment registered for chain {}", chain_id))
+    }
+
+    /// Deployment of the chain the provider is connected to
+    pub async fn for_provider(&self, provider: &RootProvider<Http<Client>>) -> Result<&ChainDeployment> {
+        self.get(provider.get_chain_id().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::uniswap_v3::utils::UNISWAP_V3_POOL_FACTORY_ADDRESS;
+    use super::*;
+
+    #[test]
+    fn chain_registry_test() {
+        let mut registry = ChainRegistry::default();
+        assert_eq!(registry.get(8453).unwrap(), &base());
+        assert_eq!(registry.get(42161).unwrap().factory, UNISWAP_V3_POOL_FACTORY_ADDRESS);
+        assert!(registry.get(324).is_err());
+
+        // WETH/USDC 0.05% on Base
+        let weth = address!("4200000000000000000000000000000000000006");
+        let usdc = address!("833589fCD6eDb6E08f4c7C32D4f71b54bdA02913");
+        assert_eq!(base().dex().compute_pool_address(weth, usdc, 500), address!("d0b53D9277642d899DF5C87A3966A349A798F224"));
+
+        let custom = ChainDeployment {chain_id: 31337, name: "Anvil".to_string(), ..mainnet()};
+        assert!(registry.register(custom.clone()).is_none());
+        assert_eq!(registry.get(31337).unwrap().name, "Anvil");
+        assert_eq!(registry.register(ChainDeployment {name: "Local".to_string(), ..custom.clone()}), Some(custom));
+
+        // a new chain and a built in one replaced, leaving out the fields that have defaults
+        let path = std::env::temp_dir().join("chain_registry_test.json");
+        fs::write(&path, r#"[
+            {
+                "chain_id": 900,
+                "name": "Devnet",
+                "factory": "0x0000000000000000000000000000000000000001",
+                "quoter": "0x0000000000000000000000000000000000000002",
+                "position_manager": "0x0000000000000000000000000000000000000003",
+                "multicall": "0x0000000000000000000000000000000000000004",
+                "pool_init_code_hash": "0x1111111111111111111111111111111111111111111111111111111111111111"
+            },
+            {
+                "chain_id": 8453,
+                "name": "Base fork",
+                "factory": "0x33128a8fC17869897dcE68Ed026d694621f6FDfD",
+                "quoter": "0x3d4e44Eb1374240CE5F1B871ab261CD16335B76a",
+                "position_manager": "0x03a520b32C04BF3bEEf7BEb72E919cf822Ed34f1"
+            }
+        ]"#).unwrap();
+        let registry = ChainRegistry::load_config(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(registry.deployments.len(), 7);
+        assert_eq!(registry.get(900).unwrap().multicall, address!("0000000000000000000000000000000000000004"));
+        assert_eq!(registry.get(8453).unwrap(), &ChainDeployment {name: "Base fork".to_string(), ..base()});
+        assert_eq!(registry.get(1).unwrap(), &mainnet());
+        assert!(ChainRegistry::default().register_json(r#"[{"chain_id": 900}]"#).is_err());
+    }
+}
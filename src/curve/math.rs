@@ -0,0 +1,169 @@
+use alloy::primitives::U256;
+use eyre::{eyre, Result};
+
+// fees and admin fees are expressed in 1e10
+pub const FEE_DENOMINATOR: U256 = U256::from_limbs([10000000000, 0, 0, 0]);
+// balances are scaled to 18 decimals by the rates
+pub const PRECISION: U256 = U256::from_limbs([1000000000000000000, 0, 0, 0]);
+// A of pools exposing A_precise is stored multiplied by 100, older pools like 3pool store A itself
+pub const A_PRECISION: U256 = U256::from_limbs([100, 0, 0, 0]);
+
+// iterations of the Newton's method before giving up, as in the pools
+const MAX_ITERATIONS: usize = 255;
+
+/// @notice Handle ramping A up or down
+/// @dev A moves linearly from initial_A at initial_A_time to future_A at future_A_time
+pub fn get_a(
+    initial_a: U256,
+    initial_a_time: u64,
+    future_a: U256,
+    future_a_time: u64,
+    timestamp: u64
+) -> U256 {
+    if timestamp >= future_a_time {
+        return future_a
+    }
+
+    let elapsed = U256::from(timestamp.saturating_sub(initial_a_time));
+    let duration = U256::from(future_a_time - initial_a_time);
+    if future_a > initial_a {
+        initial_a + (future_a - initial_a) * elapsed / duration
+    } else {
+        initial_a - (initial_a - future_a) * elapsed / duration
+    }
+}
+
+/// @notice D invariant calculation in non-overflowing integer operations iteratively
+/// @dev A * sum(x_i) * n**n + D = A * D * n**n + D**(n+1) / (n**n * prod(x_i))
+/// Converging solution:
+/// D[j+1] = (A * n**n * sum(x_i) - D[j]**(n+1) / (n**n prod(x_i))) / (A * n**n - 1)
+/// @param xp Balances scaled to 18 decimals
+/// @param amp A of the pool multiplied by a_precision
+/// @param a_precision Precision of amp, A_PRECISION or 1 for pools without A_precise
+pub fn get_d(xp: &[U256], amp: U256, a_precision: U256) -> Result<U256> {
+    let n_coins = U256::from(xp.len());
+    let s: U256 = xp.iter().sum();
+    if s.is_zero() {
+        return Ok(U256::ZERO)
+    }
+    if xp.iter().any(|x| x.is_zero()) {
+        return Err(eyre!("Pool is missing a coin balance"))
+    }
+
+    let mut d = s;
+    let ann = amp * n_coins;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = d;
+        for x in xp {
+            // If division by 0, this will be borked: only withdrawal will work. And that is good
+            d_p = d_p * d / (*x * n_coins);
+        }
+        let d_prev = d;
+        d = (ann * s / a_precision + d_p * n_coins) * d
+            / ((ann - a_precision) * d / a_precision + (n_coins + U256::from(1)) * d_p);
+        // Equality with the precision of 1
+        if d.abs_diff(d_prev) <= U256::from(1) {
+            return Ok(d)
+        }
+    }
+
+    // convergence typically occurs in 4 rounds or less, this should be unreachable!
+    // if it does happen the pool is borked and LPs can withdraw via `remove_liquidity`
+    Err(eyre!("D did not converge"))
+}
+
+/// @notice Calculate x[j] if one makes x[i] = x
+/// @dev Done by solving quadratic equation iteratively.
+/// x_1**2 + x_1 * (sum' - (A*n**n - 1) * D / (A * n**n)) = D ** (n + 1) / (n ** (2 * n) * prod' * A)
+/// x_1**2 + b*x_1 = c
+/// x_1 = (x_1**2 + c) / (2*x_1 + b)
+/// @param i Index of the coin whose balance is set
+/// @param j Index of the coin whose balance is solved for
+/// @param x New balance of coin i, scaled to 18 decimals
+/// @param xp Balances scaled to 18 decimals
+pub fn get_y(i: usize, j: usize, x: U256, xp: &[U256], amp: U256, a_precision: U256) -> Result<U256> {
+    // x in the input is converted to the same price/precision
+    if i == j {
+        return Err(eyre!("same coin"))
+    }
+    if i >= xp.len() || j >= xp.len() {
+        return Err(eyre!("coin index out of range"))
+    }
+
+    let n_coins = U256::from(xp.len());
+    let d = get_d(xp, amp, a_precision)?;
+    let ann = amp * n_coins;
+
+    let mut c = d;
+    let mut s = U256::ZERO;
+    for (k, &balance) in xp.iter().enumerate() {
+        let x_k = if k == i {
+            x
+        } else if k != j {
+            balance
+        } else {
+            continue
+        };
+        if x_k.is_zero() {
+            return Err(eyre!("Pool is missing a coin balance"))
+        }
+        s += x_k;
+        c = c * d / (x_k * n_coins);
+    }
+    c = c * d * a_precision / (ann * n_coins);
+    let b = s + d * a_precision / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        y = (y * y + c) / (U256::from(2) * y + b - d);
+        // Equality with the precision of 1
+        if y.abs_diff(y_prev) <= U256::from(1) {
+            return Ok(y)
+        }
+    }
+
+    Err(eyre!("y did not converge"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_d_test() {
+        // 3pool like balances of 170m DAI, 160m USDC and 60m USDT with A = 2000. D comes from scripts/curve_vectors.py,
+        // which runs the 3pool arithmetic in Python, and is also checked against the invariant.
+        let xp = [
+            U256::from(170000000000000000000000000u128),
+            U256::from(160000000000000000000000000u128),
+            U256::from(60000000000000000000000000u128)
+        ];
+        let amp = U256::from(2000) * A_PRECISION;
+        let d = get_d(&xp, amp, A_PRECISION).unwrap();
+        assert_eq!(d, U256::from(389977513367774707667534134u128));
+
+        // Ann * S + D = Ann * D + D^(n+1) / (n^n * prod(x))
+        let (ann, d_f) = (2000.0 * 3.0, f64::from(d));
+        let (sum, prod) = xp.iter().fold((0.0, 1.0), |(sum, prod), x| (sum + f64::from(*x), prod * f64::from(*x)));
+        let (lhs, rhs) = (ann * sum + d_f, ann * d_f + d_f.powi(4) / (27.0 * prod));
+        assert!((lhs - rhs).abs() / lhs < 1e-12);
+
+        // a pool without A_precise with the same A has the same invariant
+        assert!(get_d(&xp, U256::from(2000), U256::from(1)).unwrap().abs_diff(d) <= U256::from(1));
+
+        // balanced pools have D equal to the sum of balances
+        let balanced = [U256::from(1000000000000000000000u128); 2];
+        assert_eq!(get_d(&balanced, amp, A_PRECISION).unwrap(), U256::from(2000000000000000000000u128));
+
+        // moving coin 0 to its own balance solves back the balance of coin 1
+        let y = get_y(0, 1, xp[0], &xp, amp, A_PRECISION).unwrap();
+        assert!(y.abs_diff(xp[1]) <= U256::from(1));
+
+        // A ramps linearly between the two times
+        let (initial_a, future_a) = (U256::from(1000), U256::from(2000));
+        assert_eq!(get_a(initial_a, 100, future_a, 200, 150), U256::from(1500));
+        assert_eq!(get_a(future_a, 100, initial_a, 200, 175), U256::from(1250));
+        assert_eq!(get_a(initial_a, 100, future_a, 200, 300), future_a);
+    }
+}
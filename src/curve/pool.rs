@@ -0,0 +1,368 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use crate::{
+    amm::{resolve_block_number, Amm, LoadAmm},
+    uniswap_v3::{
+        multicall::multicall,
+        pool::{get_block_timestamp, load_tokens, Token}
+    }
+};
+use super::math::{self, A_PRECISION, FEE_DENOMINATOR, PRECISION};
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+// coins probed when loading a pool, plain pools hold 2 to 8 coins
+const MAX_COINS: usize = 8;
+
+sol! {
+    #[sol(rpc)]
+    interface ICurvePool {
+        function coins(uint256 i) external view returns (address);
+
+        function balances(uint256 i) external view returns (uint256);
+
+        function A_precise() external view returns (uint256);
+
+        function initial_A() external view returns (uint256);
+
+        function future_A() external view returns (uint256);
+
+        function initial_A_time() external view returns (uint256);
+
+        function future_A_time() external view returns (uint256);
+
+        function fee() external view returns (uint256);
+
+        function admin_fee() external view returns (uint256);
+    }
+}
+
+/// State of a Curve StableSwap plain pool. Balances are in the decimals of each coin, `rates` scale them to 18 decimals
+/// as the RATES / PRECISION_MUL constants of the pool do.
+#[derive(Clone)]
+pub struct CurvePoolState {
+    pub pool_address: Address,
+    // Multicall3 the pool is loaded and synced through
+    pub multicall_address: Address,
+    pub coins: Vec<Token>,
+    pub balances: Vec<U256>,
+    // 10 ** (36 - decimals) for every coin
+    pub rates: Vec<U256>,
+    // A ramp, the values are multiplied by a_precision
+    pub initial_a: U256,
+    pub initial_a_time: u64,
+    pub future_a: U256,
+    pub future_a_time: u64,
+    // A_PRECISION for pools exposing A_precise, 1 for older pools like 3pool
+    pub a_precision: U256,
+    // swap fee and share of it going to the admin, in 1e10
+    pub fee: U256,
+    pub admin_fee: U256,
+    pub block: BlockId,
+    pub block_timestamp: u64
+}
+
+impl CurvePoolState {
+    /// Loads coins, balances, the A ramp and fees of a plain pool with `uint256` indexed getters. The number of coins
+    /// is found by probing `coins`.
+    pub async fn load_at_block(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let coin_calls = (0..MAX_COINS).map(|i| ICurvePool::coinsCall{i: U256::from(i)}.abi_encode()).collect();
+        let coin_addresses = multicall(provider, multicall_address, pool_address, true, coin_calls, block).await?
+            .into_iter()
+            .take_while(|result| result.success)
+            .map(|result| Ok(ICurvePool::coinsCall::abi_decode_returns(&result.returnData, true)?._0))
+            .collect::<Result<Vec<Address>>>()?;
+        if coin_addresses.len() < 2 {
+            return Err(eyre!("{} is not a Curve plain pool", pool_address))
+        }
+
+        let mut encoded_calls: Vec<Vec<u8>> = (0..coin_addresses.len())
+            .map(|i| ICurvePool::balancesCall{i: U256::from(i)}.abi_encode())
+            .collect();
+        encoded_calls.extend([
+            ICurvePool::A_preciseCall{}.abi_encode(),
+            ICurvePool::initial_ACall{}.abi_encode(),
+            ICurvePool::future_ACall{}.abi_encode(),
+            ICurvePool::initial_A_timeCall{}.abi_encode(),
+            ICurvePool::future_A_timeCall{}.abi_encode(),
+            ICurvePool::feeCall{}.abi_encode(),
+            ICurvePool::admin_feeCall{}.abi_encode()
+        ]);
+        let return_data = multicall(provider, multicall_address, pool_address, true, encoded_calls, block).await?;
+        if return_data.iter().enumerate().any(|(i, result)| !result.success && i != coin_addresses.len()) {
+            return Err(eyre!("Failed to load the state of Curve pool {}", pool_address))
+        }
+
+        let (balance_data, parameter_data) = return_data.split_at(coin_addresses.len());
+        let balances = balance_data
+            .iter()
+            .map(|result| Ok(ICurvePool::balancesCall::abi_decode_returns(&result.returnData, true)?._0))
+            .collect::<Result<Vec<U256>>>()?;
+        // older pools have no A_precise and no A_PRECISION
+        let a_precision = if parameter_data[0].success {A_PRECISION} else {U256::from(1)};
+        let initial_a = ICurvePool::initial_ACall::abi_decode_returns(&parameter_data[1].returnData, true)?._0;
+        let future_a = ICurvePool::future_ACall::abi_decode_returns(&parameter_data[2].returnData, true)?._0;
+        let initial_a_time = ICurvePool::initial_A_timeCall::abi_decode_returns(&parameter_data[3].returnData, true)?._0;
+        let future_a_time = ICurvePool::future_A_timeCall::abi_decode_returns(&parameter_data[4].returnData, true)?._0;
+        let fee = ICurvePool::feeCall::abi_decode_returns(&parameter_data[5].returnData, true)?._0;
+        let admin_fee = ICurvePool::admin_feeCall::abi_decode_returns(&parameter_data[6].returnData, true)?._0;
+
+        let tokens = load_tokens(provider, multicall_address, coin_addresses.clone(), block).await?;
+        let coins: Vec<Token> = coin_addresses.iter().map(|coin| tokens[coin].clone()).collect();
+        let rates = coins.iter().map(|coin| U256::from(10).pow(U256::from(36 - coin.decimals as u64))).collect();
+
+        Ok(CurvePoolState {
+            pool_address,
+            multicall_address,
+            coins,
+            balances,
+            rates,
+            initial_a,
+            initial_a_time: initial_a_time.to::<u64>(),
+            future_a,
+            future_a_time: future_a_time.to::<u64>(),
+            a_precision,
+            fee,
+            admin_fee,
+            block,
+            block_timestamp: get_block_timestamp(provider, block).await?
+        })
+    }
+
+    /// Index of `coin` in the pool
+    pub fn coin_index(&self, coin: Address) -> Result<usize> {
+        self.coins
+            .iter()
+            .position(|token| token.address == coin)
+            .ok_or(eyre!("Curve pool {} does not hold {}", self.pool_address, coin))
+    }
+
+    /// A of the pool at the loaded block timestamp, multiplied by `a_precision`
+    pub fn a(&self) -> U256 {
+        math::get_a(self.initial_a, self.initial_a_time, self.future_a, self.future_a_time, self.block_timestamp)
+    }
+
+    /// Balances scaled to 18 decimals
+    pub fn xp(&self) -> Vec<U256> {
+        self.rates.iter().zip(&self.balances).map(|(rate, balance)| rate * balance / PRECISION).collect()
+    }
+
+    /// @notice Calculate the current output dy given input dx
+    /// @dev Rounds like `exchange` so the quote equals the amount sent, the `get_dy` view of older pools applies the
+    /// fee after scaling and can differ by a wei
+    /// @param i Index value for the coin to send
+    /// @param j Index value of the coin to receive
+    /// @param dx Amount of `i` being exchanged
+    /// @return Amount of `j` predicted
+    pub fn get_dy(&self, i: usize, j: usize, dx: U256) -> Result<U256> {
+        Ok(self.exchange_amounts(i, j, dx)?.0)
+    }
+
+    /// @notice Calculate the current input dx given output dy
+    /// @dev Index values can be found via the `coins` public getter method
+    /// @param i Index value for the coin to send
+    /// @param j Index value of the coin to receive
+    /// @param dy Amount of `j` being received after exchange
+    /// @return Amount of `i` predicted
+    pub fn get_dx(&self, i: usize, j: usize, dy: U256) -> Result<U256> {
+        let xp = self.xp();
+        if j >= xp.len() {
+            return Err(eyre!("coin index out of range"))
+        }
+
+        let dy_with_fee = (dy * self.rates[j] / PRECISION + U256::from(1)) * FEE_DENOMINATOR / (FEE_DENOMINATOR - self.fee);
+        let y = xp[j].checked_sub(dy_with_fee).ok_or(eyre!("Curve pool {} cannot pay out {}", self.pool_address, dy))?;
+        let x = math::get_y(j, i, y, &xp, self.a(), self.a_precision)?;
+        Ok((x - xp[i]) * PRECISION / self.rates[i])
+    }
+
+    /// @notice Perform an exchange between two coins
+    /// @dev Updates the balances, the admin fee leaves the pool balance of `j`
+    /// @param i Index value for the coin to send
+    /// @param j Index value of the coin to receive
+    /// @param dx Amount of `i` being exchanged
+    /// @return Actual amount of `j` received
+    pub fn exchange(&mut self, i: usize, j: usize, dx: U256) -> Result<U256> {
+        let (dy, dy_admin_fee) = self.exchange_amounts(i, j, dx)?;
+
+        self.balances[i] += dx;
+        // When rounding errors happen, we undercharge admin fee in favor of LP
+        self.balances[j] -= dy + dy_admin_fee;
+        Ok(dy)
+    }
+
+    // amount received and admin fee of an exchange, both in the decimals of j
+    fn exchange_amounts(&self, i: usize, j: usize, dx: U256) -> Result<(U256, U256)> {
+        let xp = self.xp();
+        if i >= xp.len() {
+            return Err(eyre!("coin index out of range"))
+        }
+
+        let x = xp[i] + dx * self.rates[i] / PRECISION;
+        let y = math::get_y(i, j, x, &xp, self.a(), self.a_precision)?;
+
+        // -1 just in case there were some rounding errors
+        let dy = xp[j].checked_sub(y + U256::from(1)).ok_or(eyre!("Curve pool {} cannot pay out", self.pool_address))?;
+        let dy_fee = dy * self.fee / FEE_DENOMINATOR;
+
+        // Convert all to real units
+        let dy_out = (dy - dy_fee) * PRECISION / self.rates[j];
+        let dy_admin_fee = dy_fee * self.admin_fee / FEE_DENOMINATOR * PRECISION / self.rates[j];
+        Ok((dy_out, dy_admin_fee))
+    }
+}
+
+impl Amm for CurvePoolState {
+    fn address(&self) -> Address {
+        self.pool_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        self.coins.iter().map(|coin| coin.address).collect()
+    }
+
+    /// Marginal price of the invariant at the current balances, before fees. It is the ratio of the partial derivatives
+    /// of A * n**n * sum(x_i) + D - A * D * n**n - D**(n+1) / (n**n * prod(x_i)) in the two coins.
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let (i, j) = (self.coin_index(base)?, self.coin_index(quote)?);
+        let xp = self.xp();
+        let n_coins = xp.len() as f64;
+
+        let d = f64::from(math::get_d(&xp, self.a(), self.a_precision)?);
+        let ann = f64::from(self.a()) / f64::from(self.a_precision) * n_coins;
+        // D**(n+1) / (n**n * prod(x_i))
+        let d_p = xp.iter().fold(d, |d_p, x| d_p * d / (f64::from(*x) * n_coins));
+
+        Ok((ann + d_p / f64::from(xp[i])) / (ann + d_p / f64::from(xp[j])))
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.get_dy(self.coin_index(token_in)?, self.coin_index(token_out)?, amount_in)
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.get_dx(self.coin_index(token_in)?, self.coin_index(token_out)?, amount_out)
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let (i, j) = (self.coin_index(token_in)?, self.coin_index(token_out)?);
+            self.exchange(i, j, amount_in)
+        })
+    }
+
+    /// Reloads the pool at `to_block`, balances change with liquidity events as well as exchanges
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            *self = CurvePoolState::load_at_block(provider, self.multicall_address, self.pool_address, BlockId::number(to_block)).await?;
+            Ok(())
+        })
+    }
+}
+
+impl LoadAmm for CurvePoolState {
+    async fn load(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let block = resolve_block_number(provider, block).await?;
+        CurvePoolState::load_at_block(provider, multicall_address, address, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use crate::{test_utils::{offline_provider, token}, uniswap_v3::multicall::MULTICALL3_ADDRESS};
+    use super::*;
+
+    #[tokio::test]
+    async fn curve_amm_test() {
+        let provider = offline_provider();
+        let (dai, usdc, usdt) = (
+            address!("0000000000000000000000000000000000000001"),
+            address!("0000000000000000000000000000000000000002"),
+            address!("0000000000000000000000000000000000000003")
+        );
+        // 3pool like balances with A = 2000 and a 0.01% fee, the amounts come from scripts/curve_vectors.py
+        let mut pool = CurvePoolState {
+            pool_address: Address::ZERO,
+            multicall_address: MULTICALL3_ADDRESS,
+            coins: vec![token(dai, 18), token(usdc, 6), token(usdt, 6)],
+            balances: vec![U256::from(170000000000000000000000000u128), U256::from(160000000000000u128), U256::from(60000000000000u128)],
+            rates: vec![U256::from(10).pow(U256::from(18)), U256::from(10).pow(U256::from(30)), U256::from(10).pow(U256::from(30))],
+            initial_a: U256::from(1000) * A_PRECISION,
+            initial_a_time: 0,
+            future_a: U256::from(2000) * A_PRECISION,
+            future_a_time: 100,
+            a_precision: A_PRECISION,
+            fee: U256::from(1000000),
+            admin_fee: U256::from(5000000000u64),
+            block: BlockId::number(1),
+            block_timestamp: 200
+        };
+
+        assert_eq!(pool.get_dy(0, 1, U256::from(1000000000000000000000000u128)).unwrap(), U256::from(999864629587u64));
+        assert_eq!(pool.get_dy(2, 0, U256::from(5000000000000u64)).unwrap(), U256::from(5003779792599263964079864u128));
+        assert_eq!(pool.get_dx(0, 1, U256::from(1000000000000u64)).unwrap(), U256::from(1000135389178132409648302u128));
+
+        // the price of USDT, the scarce coin, is above one and the quote goes through the trait
+        assert!(pool.spot_price(usdt, dai).unwrap() > 1.0);
+        assert!((pool.spot_price(usdt, dai).unwrap() * pool.spot_price(dai, usdt).unwrap() - 1.0).abs() < 1e-12);
+        let amount_in = U256::from(1000000000000000000000u128);
+        let quoted_out = pool.quote_exact_input(&provider, dai, usdc, amount_in).await.unwrap();
+        let amount_in_back = pool.quote_exact_output(&provider, dai, usdc, quoted_out).await.unwrap();
+        assert!(pool.get_dy(0, 1, amount_in_back).unwrap() >= quoted_out);
+
+        // half of the fee leaves the pool as admin fee
+        assert_eq!(pool.swap_exact_input(&provider, dai, usdc, amount_in).await.unwrap(), quoted_out);
+        assert_eq!(pool.balances[0], U256::from(170001000000000000000000000u128));
+        let lp_balance1 = U256::from(160000000000000u128) - quoted_out;
+        assert_eq!(quoted_out, U256::from(999867854u64));
+        assert_eq!(lp_balance1 - pool.balances[1], U256::from(49998));
+
+        // mid ramp A is halfway between the two values
+        pool.block_timestamp = 50;
+        assert_eq!(pool.a(), U256::from(1500) * A_PRECISION);
+        assert!(pool.quote_exact_input(&provider, dai, Address::ZERO, amount_in).await.is_err());
+    }
+}
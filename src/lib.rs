@@ -0,0 +1,13 @@
+pub mod algebra;
+pub mod amm;
+pub mod balancer;
+pub mod chains;
+pub mod curve;
+pub mod liquidity_book;
+pub mod logs;
+pub mod solidly;
+#[cfg(test)]
+mod test_utils;
+pub mod uniswap_v2;
+pub mod uniswap_v3;
+pub mod uniswap_v4;
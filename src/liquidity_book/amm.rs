@@ -0,0 +1,138 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    transports::http::{Client, Http}
+};
+use crate::amm::{resolve_block_number, Amm, LoadAmm};
+use super::{
+    math::price_helper::{get_price_from_id, price_to_f64},
+    pair::LBPairState
+};
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+impl LBPairState {
+    fn swap_for_y(&self, token_in: Address, token_out: Address) -> Result<bool> {
+        if token_in == self.token_x.address && token_out == self.token_y.address {
+            Ok(true)
+        } else if token_in == self.token_y.address && token_out == self.token_x.address {
+            Ok(false)
+        } else {
+            Err(eyre!("Pair {} does not trade {} for {}", self.pair_address, token_in, token_out))
+        }
+    }
+}
+
+impl Amm for LBPairState {
+    fn address(&self) -> Address {
+        self.pair_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.token_x.address, self.token_y.address]
+    }
+
+    /// Price of the active bin
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let price = price_to_f64(
+            get_price_from_id(self.parameters.active_id, self.bin_step)?,
+            self.token_x.decimals,
+            self.token_y.decimals
+        );
+        if self.swap_for_y(base, quote)? {Ok(price)} else {Ok(1.0 / price)}
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let (amount_in_left, amount_out, _) = self.get_swap_out(provider, u128::try_from(amount_in)?, self.swap_for_y(token_in, token_out)?).await?;
+            if amount_in_left != 0 {
+                return Err(eyre!("LBPair__OutOfLiquidity"))
+            }
+            Ok(U256::from(amount_out))
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let (amount_in, amount_out_left, _) = self.get_swap_in(provider, u128::try_from(amount_out)?, self.swap_for_y(token_in, token_out)?).await?;
+            if amount_out_left != 0 {
+                return Err(eyre!("LBPair__OutOfLiquidity"))
+            }
+            Ok(U256::from(amount_in))
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let swap_for_y = self.swap_for_y(token_in, token_out)?;
+            let (amount_out, _) = self.swap(provider, swap_for_y, u128::try_from(amount_in)?).await?;
+            Ok(U256::from(amount_out))
+        })
+    }
+
+    /// Reloads the pair at `to_block`, the bins loaded so far are replaced by the words around the new active id
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            *self = LBPairState::load_at_block(provider, self.multicall_address, self.pair_address, BlockId::number(to_block)).await?;
+            Ok(())
+        })
+    }
+}
+
+impl LoadAmm for LBPairState {
+    async fn load(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let block = resolve_block_number(provider, block).await?;
+        LBPairState::load_at_block(provider, multicall_address, address, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{liquidity_book::pair::tests::pair_at_price_one, test_utils::offline_provider};
+    use super::*;
+
+    #[tokio::test]
+    async fn lb_amm_test() {
+        let provider = offline_provider();
+        let mut pair = pair_at_price_one();
+        let (token_x, token_y) = (pair.token_x.address, pair.token_y.address);
+
+        assert_eq!(pair.spot_price(token_x, token_y).unwrap(), 1.0);
+
+        let amount_in = U256::from(100000000000000000000u128);
+        let quoted_out = pair.quote_exact_input(&provider, token_y, token_x, amount_in).await.unwrap();
+        assert!(quoted_out < amount_in);
+        assert!(pair.quote_exact_output(&provider, token_y, token_x, quoted_out).await.unwrap() <= amount_in);
+
+        assert_eq!(pair.swap_exact_input(&provider, token_y, token_x, amount_in).await.unwrap(), quoted_out);
+        assert!(pair.quote_exact_input(&provider, token_x, Address::ZERO, amount_in).await.is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use alloy::primitives::U256;
+use crate::uniswap_v3::math::full_math::{mul_div, mul_div_rounding_up};
+use super::{
+    constants::SCALE,
+    fee_helper::{get_fee_amount, get_fee_amount_from},
+    pair_parameter_helper::PairParameters,
+    price_helper::get_price_from_id
+};
+use eyre::{eyre, Result};
+
+/// Reserves of a bin, bins above the active id only hold X and bins below only hold Y
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bin {
+    pub reserve_x: u128,
+    pub reserve_y: u128
+}
+
+impl Bin {
+    /// Reserve of the token paid out by a swap in the given direction
+    pub fn reserve_out(&self, swap_for_y: bool) -> u128 {
+        if swap_for_y {self.reserve_y} else {self.reserve_x}
+    }
+}
+
+/// @dev Returns the amounts of tokens that will be added and removed from the bin during a swap
+/// along with the fees that will be charged
+/// @param bin The reserves of the bin
+/// @param parameters The parameters of the pair
+/// @param binStep The bin step of the pair
+/// @param swapForY Whether the swap is for Y (true) or for X (false)
+/// @param activeId The id of the active bin
+/// @param amountInLeft The amount of token in left to swap
+/// @return amountInWithFees The amount of token in that will be added to the bin, including fees
+/// @return amountOutOfBin The amount of token out that will be removed from the bin
+/// @return totalFees The fees that will be charged
+pub fn get_amounts(
+    bin: &Bin,
+    parameters: &PairParameters,
+    bin_step: u16,
+    swap_for_y: bool,
+    active_id: u32,
+    amount_in_left: u128
+) -> Result<(u128, u128, u128)> {
+    let price = get_price_from_id(active_id, bin_step)?;
+    let bin_reserve_out = bin.reserve_out(swap_for_y);
+
+    let max_amount_in = if swap_for_y {
+        shift_div(U256::from(bin_reserve_out), price, true)?
+    } else {
+        mul_shift(U256::from(bin_reserve_out), price, true)?
+    };
+
+    let total_fee = parameters.get_total_fee(bin_step);
+    let max_fee = get_fee_amount(max_amount_in, total_fee)?;
+    let max_amount_in = max_amount_in.checked_add(max_fee).ok_or(eyre!("SafeCast__Exceeds128Bits"))?;
+
+    if amount_in_left >= max_amount_in {
+        Ok((max_amount_in, bin_reserve_out, max_fee))
+    } else {
+        let fee = get_fee_amount_from(amount_in_left, total_fee)?;
+        let amount_in = U256::from(amount_in_left - fee);
+        let amount_out = if swap_for_y {
+            mul_shift(amount_in, price, false)?
+        } else {
+            shift_div(amount_in, price, false)?
+        };
+
+        Ok((amount_in_left, amount_out.min(bin_reserve_out), fee))
+    }
+}
+
+/// Amount of token in, before fees, paying exactly `amount_out` out of the bin at `price`, rounding up
+pub fn get_amount_in_without_fee(amount_out: u128, price: U256, swap_for_y: bool) -> Result<u128> {
+    if swap_for_y {
+        shift_div(U256::from(amount_out), price, true)
+    } else {
+        mul_shift(U256::from(amount_out), price, true)
+    }
+}
+
+// x * price >> 128, the amount of Y worth x of X
+fn mul_shift(x: U256, price: U256, round_up: bool) -> Result<u128> {
+    let result = if round_up {mul_div_rounding_up(x, price, SCALE)?} else {mul_div(x, price, SCALE)?};
+    u128::try_from(result).map_err(|_| eyre!("SafeCast__Exceeds128Bits"))
+}
+
+// (y << 128) / price, the amount of X worth y of Y
+fn shift_div(y: U256, price: U256, round_up: bool) -> Result<u128> {
+    let result = if round_up {mul_div_rounding_up(y, SCALE, price)?} else {mul_div(y, SCALE, price)?};
+    u128::try_from(result).map_err(|_| eyre!("SafeCast__Exceeds128Bits"))
+}
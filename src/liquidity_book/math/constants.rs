@@ -0,0 +1,15 @@
+use alloy::primitives::U256;
+
+pub const SCALE_OFFSET: usize = 128;
+// 1 as a 128.128-binary fixed-point number
+pub const SCALE: U256 = U256::from_limbs([0, 0, 1, 0]);
+
+// fees are 18 decimals, 1e16 is a 1% fee
+pub const PRECISION: u128 = 1000000000000000000;
+pub const MAX_FEE: u128 = 100000000000000000;
+pub const BASIS_POINT_MAX: u32 = 10000;
+
+// the id of the bin at price 1
+pub const REAL_ID_SHIFT: i32 = 1 << 23;
+// ids are uint24, the first and last ids are used by the tree as not found markers
+pub const MAX_ID: u32 = (1 << 24) - 1;
@@ -0,0 +1,40 @@
+use alloy::primitives::U256;
+use super::constants::{BASIS_POINT_MAX, MAX_FEE, PRECISION};
+use eyre::{eyre, Result};
+
+/// @dev Verify that the fee is not too large
+fn verify_fee(fee: u128) -> Result<()> {
+    if fee > MAX_FEE {
+        return Err(eyre!("FeeHelper__FeeTooLarge"))
+    }
+    Ok(())
+}
+
+/// @dev Calculates the fee amount from the amount with fees, rounding up
+/// @param amountWithFees The amount with fees
+/// @param totalFee The total fee
+/// @return feeAmount The fee amount
+pub fn get_fee_amount_from(amount_with_fees: u128, total_fee: u128) -> Result<u128> {
+    verify_fee(total_fee)?;
+    let fee_amount = (U256::from(amount_with_fees) * U256::from(total_fee) + U256::from(PRECISION - 1)) / U256::from(PRECISION);
+    Ok(fee_amount.to::<u128>())
+}
+
+/// @dev Calculates the fee amount that will be charged, rounding up
+/// @param amount The amount
+/// @param totalFee The total fee
+/// @return feeAmount The fee amount
+pub fn get_fee_amount(amount: u128, total_fee: u128) -> Result<u128> {
+    verify_fee(total_fee)?;
+    let denominator = U256::from(PRECISION - total_fee);
+    let fee_amount = (U256::from(amount) * U256::from(total_fee) + denominator - U256::from(1)) / denominator;
+    u128::try_from(fee_amount).map_err(|_| eyre!("SafeCast__Exceeds128Bits"))
+}
+
+/// @dev Calculates the protocol fee amount from the fee amount and the protocol share, rounding down
+/// @param feeAmount The fee amount
+/// @param protocolShare The protocol share
+/// @return protocolFeeAmount The protocol fee amount
+pub fn get_protocol_fee_amount(fee_amount: u128, protocol_share: u16) -> u128 {
+    (U256::from(fee_amount) * U256::from(protocol_share) / U256::from(BASIS_POINT_MAX)).to::<u128>()
+}
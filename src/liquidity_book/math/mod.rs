@@ -0,0 +1,5 @@
+pub mod constants;
+pub mod price_helper;
+pub mod fee_helper;
+pub mod pair_parameter_helper;
+pub mod bin_helper;
@@ -0,0 +1,87 @@
+use super::constants::BASIS_POINT_MAX;
+
+/// Fee parameters of a pair, the static parameters are set by the factory owner and the variable parameters move
+/// with every swap. The contract packs them into a single word along with the active id.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PairParameters {
+    pub base_factor: u16,
+    pub filter_period: u16,
+    pub decay_period: u16,
+    pub reduction_factor: u16,
+    pub variable_fee_control: u32,
+    pub protocol_share: u16,
+    pub max_volatility_accumulator: u32,
+    pub volatility_accumulator: u32,
+    pub volatility_reference: u32,
+    pub id_reference: u32,
+    pub time_of_last_update: u64,
+    pub active_id: u32
+}
+
+impl PairParameters {
+    /// @dev Calculates the base fee, with 18 decimals
+    /// @param binStep The bin step (in basis points)
+    /// @return baseFee The base fee
+    pub fn get_base_fee(&self, bin_step: u16) -> u128 {
+        // base factor is in basis points, binStep is in basis points, so we multiply by 1e10
+        self.base_factor as u128 * bin_step as u128 * 10000000000
+    }
+
+    /// @dev Calculates the variable fee
+    /// @param binStep The bin step (in basis points)
+    /// @return variableFee The variable fee, with 18 decimals
+    pub fn get_variable_fee(&self, bin_step: u16) -> u128 {
+        if self.variable_fee_control == 0 {
+            return 0
+        }
+
+        // The volatility accumulator is in basis points, binStep is in basis points,
+        // and the variable fee control is in basis points, so the result is in 100e18th
+        let prod = self.volatility_accumulator as u128 * bin_step as u128;
+        (prod * prod * self.variable_fee_control as u128).div_ceil(100)
+    }
+
+    /// @dev Calculates the total fee, which is the sum of the base fee and the variable fee
+    /// @param binStep The bin step (in basis points)
+    /// @return totalFee The total fee, with 18 decimals
+    pub fn get_total_fee(&self, bin_step: u16) -> u128 {
+        self.get_base_fee(bin_step) + self.get_variable_fee(bin_step)
+    }
+
+    /// @dev Updates the volatility reference: volatilityAccumulator * reductionFactor / BASIS_POINT_MAX
+    pub fn update_volatility_reference(&mut self) {
+        self.volatility_reference = (self.volatility_accumulator as u64 * self.reduction_factor as u64 / BASIS_POINT_MAX as u64) as u32;
+    }
+
+    /// @dev Updates the id reference to the active id
+    pub fn update_id_reference(&mut self) {
+        self.id_reference = self.active_id;
+    }
+
+    /// @dev Updates the volatility accumulator:
+    /// volatilityAccumulator = min(volatilityReference + |activeId - idReference| * BASIS_POINT_MAX, maxVolatilityAccumulator)
+    /// @param activeId The active id
+    pub fn update_volatility_accumulator(&mut self, active_id: u32) {
+        let delta_id = active_id.abs_diff(self.id_reference) as u64;
+        let volatility_accumulator = self.volatility_reference as u64 + delta_id * BASIS_POINT_MAX as u64;
+        self.volatility_accumulator = volatility_accumulator.min(self.max_volatility_accumulator as u64) as u32;
+    }
+
+    /// @dev Updates the volatility reference and the id reference when at least the filter period passed since the
+    /// last update, the volatility reference decays to zero after the decay period
+    /// @param timestamp The current timestamp
+    pub fn update_references(&mut self, timestamp: u64) {
+        let dt = timestamp.saturating_sub(self.time_of_last_update);
+
+        if dt >= self.filter_period as u64 {
+            self.update_id_reference();
+            if dt < self.decay_period as u64 {
+                self.update_volatility_reference();
+            } else {
+                self.volatility_reference = 0;
+            }
+        }
+
+        self.time_of_last_update = timestamp;
+    }
+}
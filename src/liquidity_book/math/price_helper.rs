@@ -0,0 +1,89 @@
+use alloy::primitives::U256;
+use super::constants::{BASIS_POINT_MAX, REAL_ID_SHIFT, SCALE, SCALE_OFFSET};
+use eyre::{eyre, Result};
+
+/// @notice Calculates the price from the id and the bin step
+/// @param id The id
+/// @param binStep The bin step
+/// @return price The price as a 128.128-binary fixed-point number
+pub fn get_price_from_id(id: u32, bin_step: u16) -> Result<U256> {
+    pow(get_base(bin_step), get_exponent(id))
+}
+
+/// @notice Calculates the base from the bin step, which is `1 + binStep / BASIS_POINT_MAX`
+/// @param binStep The bin step
+/// @return base The base
+pub fn get_base(bin_step: u16) -> U256 {
+    SCALE + (U256::from(bin_step) << SCALE_OFFSET) / U256::from(BASIS_POINT_MAX)
+}
+
+/// @notice Calculates the exponent from the id, which is `id - REAL_ID_SHIFT`
+/// @param id The id
+/// @return exponent The exponent
+pub fn get_exponent(id: u32) -> i32 {
+    id as i32 - REAL_ID_SHIFT
+}
+
+/// @notice Returns the value of x^y. It calculates `1 / x^abs(y)` if x is bigger than 2^128.
+/// At the end of the operations, we invert the result if needed.
+/// @param x The unsigned 128.128-binary fixed-point number for which to calculate the power
+/// @param y A relative number without any decimals, needs to be between ]-2^21; 2^21[
+pub fn pow(x: U256, y: i32) -> Result<U256> {
+    if y == 0 {
+        return Ok(SCALE)
+    }
+
+    let mut invert = y < 0;
+    let abs_y = y.unsigned_abs();
+
+    let mut result = U256::ZERO;
+    if abs_y < 0x100000 {
+        result = SCALE;
+        let mut squared = x;
+        if x > U256::from(u128::MAX) {
+            squared = U256::MAX / squared;
+            invert = !invert;
+        }
+
+        for bit in 0..20 {
+            if abs_y & (1 << bit) != 0 {
+                result = (result * squared) >> SCALE_OFFSET;
+            }
+            squared = (squared * squared) >> SCALE_OFFSET;
+        }
+    }
+
+    // revert if y is too big or if x^y underflowed
+    if result.is_zero() {
+        return Err(eyre!("Uint128x128Math__PowUnderflow"))
+    }
+
+    Ok(if invert {U256::MAX / result} else {result})
+}
+
+/// Converts a 128.128-binary fixed-point price of Y per X to a price of whole tokens
+pub fn price_to_f64(price: U256, decimals_x: u8, decimals_y: u8) -> f64 {
+    f64::from(price) / 2f64.powi(SCALE_OFFSET as i32) * 10f64.powi(decimals_x as i32 - decimals_y as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_price_from_id_test() {
+        // the prices come from scripts/lb_vectors.py, the last assertion compares them with 1.0025^100
+        assert_eq!(get_price_from_id(REAL_ID_SHIFT as u32, 25).unwrap(), SCALE);
+        assert_eq!(
+            get_price_from_id(REAL_ID_SHIFT as u32 + 100, 25).unwrap(),
+            U256::from_str_radix("436794915378552100798054128165989473614", 10).unwrap()
+        );
+        assert_eq!(
+            get_price_from_id(REAL_ID_SHIFT as u32 - 100, 25).unwrap(),
+            U256::from_str_radix("265094865257220261526334763469518204397", 10).unwrap()
+        );
+        assert!((price_to_f64(get_price_from_id(REAL_ID_SHIFT as u32 + 100, 25).unwrap(), 18, 18) - 1.0025f64.powi(100)).abs() < 1e-12);
+
+        assert!(pow(get_base(25), 1 << 20).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod math;
+pub mod pair;
+pub mod amm;
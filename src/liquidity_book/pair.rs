@@ -0,0 +1,513 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::{
+    multicall::multicall,
+    pool::{get_block_timestamp, load_tokens, Token}
+};
+use super::math::{
+    bin_helper::{get_amount_in_without_fee, get_amounts, Bin},
+    constants::MAX_ID,
+    fee_helper::{get_fee_amount, get_protocol_fee_amount},
+    pair_parameter_helper::PairParameters,
+    price_helper::{get_price_from_id, price_to_f64}
+};
+use eyre::{eyre, Result};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+sol! {
+    #[sol(rpc)]
+    interface ILBPair {
+        function getTokenX() external view returns (address tokenX);
+
+        function getTokenY() external view returns (address tokenY);
+
+        function getBinStep() external view returns (uint16 binStep);
+
+        function getActiveId() external view returns (uint24 activeId);
+
+        function getBin(uint24 id) external view returns (uint128 binReserveX, uint128 binReserveY);
+
+        function getNextNonEmptyBin(bool swapForY, uint24 id) external view returns (uint24 nextId);
+
+        function getProtocolFees() external view returns (uint128 protocolFeeX, uint128 protocolFeeY);
+
+        function getStaticFeeParameters()
+            external
+            view
+            returns (
+                uint16 baseFactor,
+                uint16 filterPeriod,
+                uint16 decayPeriod,
+                uint16 reductionFactor,
+                uint24 variableFeeControl,
+                uint16 protocolShare,
+                uint24 maxVolatilityAccumulator
+            );
+
+        function getVariableFeeParameters()
+            external
+            view
+            returns (
+                uint24 volatilityAccumulator,
+                uint24 volatilityReference,
+                uint24 idReference,
+                uint40 timeOfLastUpdate
+            );
+    }
+}
+
+// words of the bitmap loaded on each side of the active bin, every word holds 256 bins
+const BIN_WORDS_AROUND_ACTIVE: u32 = 1;
+
+/// State of a Liquidity Book pair (v2.1 and v2.2). Non-empty bins are tracked in a bitmap of 256 bin words like the V3
+/// tick bitmap, words are loaded lazily while swapping and a word missing from the map is not loaded yet.
+#[derive(Clone)]
+pub struct LBPairState {
+    pub pair_address: Address,
+    // Multicall3 the pair is loaded through, lazy bin loads use it too
+    pub multicall_address: Address,
+    pub token_x: Token,
+    pub token_y: Token,
+    pub bin_step: u16,
+    // fee parameters and the active id
+    pub parameters: PairParameters,
+    pub bins: HashMap<u32, Bin>,
+    pub bin_bitmap: HashMap<u32, U256>,
+    pub protocol_fee_x: u128,
+    pub protocol_fee_y: u128,
+    pub block: BlockId,
+    pub block_timestamp: u64
+}
+
+impl LBPairState {
+    /// Loads the pair parameters and the bins of the words around the active id
+    pub async fn load_at_block(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pair_address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let encoded_calls = vec![
+            ILBPair::getTokenXCall{}.abi_encode(),
+            ILBPair::getTokenYCall{}.abi_encode(),
+            ILBPair::getBinStepCall{}.abi_encode(),
+            ILBPair::getActiveIdCall{}.abi_encode(),
+            ILBPair::getProtocolFeesCall{}.abi_encode(),
+            ILBPair::getStaticFeeParametersCall{}.abi_encode(),
+            ILBPair::getVariableFeeParametersCall{}.abi_encode()
+        ];
+        let return_data = multicall(provider, multicall_address, pair_address, false, encoded_calls, block).await?;
+
+        let token_x = ILBPair::getTokenXCall::abi_decode_returns(&return_data[0].returnData, true)?.tokenX;
+        let token_y = ILBPair::getTokenYCall::abi_decode_returns(&return_data[1].returnData, true)?.tokenY;
+        let bin_step = ILBPair::getBinStepCall::abi_decode_returns(&return_data[2].returnData, true)?.binStep;
+        let active_id = ILBPair::getActiveIdCall::abi_decode_returns(&return_data[3].returnData, true)?.activeId;
+        let protocol_fees = ILBPair::getProtocolFeesCall::abi_decode_returns(&return_data[4].returnData, true)?;
+        let static_parameters = ILBPair::getStaticFeeParametersCall::abi_decode_returns(&return_data[5].returnData, true)?;
+        let variable_parameters = ILBPair::getVariableFeeParametersCall::abi_decode_returns(&return_data[6].returnData, true)?;
+
+        let tokens = load_tokens(provider, multicall_address, vec![token_x, token_y], block).await?;
+
+        let mut pair = LBPairState {
+            pair_address,
+            multicall_address,
+            token_x: tokens[&token_x].clone(),
+            token_y: tokens[&token_y].clone(),
+            bin_step,
+            parameters: PairParameters {
+                base_factor: static_parameters.baseFactor,
+                filter_period: static_parameters.filterPeriod,
+                decay_period: static_parameters.decayPeriod,
+                reduction_factor: static_parameters.reductionFactor,
+                variable_fee_control: static_parameters.variableFeeControl,
+                protocol_share: static_parameters.protocolShare,
+                max_volatility_accumulator: static_parameters.maxVolatilityAccumulator,
+                volatility_accumulator: variable_parameters.volatilityAccumulator,
+                volatility_reference: variable_parameters.volatilityReference,
+                id_reference: variable_parameters.idReference,
+                time_of_last_update: variable_parameters.timeOfLastUpdate,
+                active_id
+            },
+            bins: HashMap::new(),
+            bin_bitmap: HashMap::new(),
+            protocol_fee_x: protocol_fees.protocolFeeX,
+            protocol_fee_y: protocol_fees.protocolFeeY,
+            block,
+            block_timestamp: get_block_timestamp(provider, block).await?
+        };
+
+        let active_word = active_id >> 8;
+        let words = (active_word.saturating_sub(BIN_WORDS_AROUND_ACTIVE)..=(active_word + BIN_WORDS_AROUND_ACTIVE).min(MAX_ID >> 8)).collect();
+        pair.load_bin_words(provider, words).await?;
+
+        Ok(pair)
+    }
+
+    /// Loads every bin of the bitmap words at `word_positions`, bins already in memory are kept as they may carry
+    /// simulated swaps
+    pub async fn load_bin_words(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        word_positions: Vec<u32>
+    ) -> Result<()> {
+        let word_positions: Vec<u32> = word_positions.into_iter().filter(|word_pos| !self.bin_bitmap.contains_key(word_pos)).collect();
+        let ids: Vec<u32> = word_positions.iter().flat_map(|word_pos| (word_pos << 8)..=(word_pos << 8 | 0xff)).collect();
+        let bin_calls = ids.iter().map(|&id| ILBPair::getBinCall{id}.abi_encode()).collect();
+        let return_data = multicall(provider, self.multicall_address, self.pair_address, false, bin_calls, self.block).await?;
+
+        for word_pos in word_positions {
+            self.bin_bitmap.insert(word_pos, U256::ZERO);
+        }
+        for (id, data) in ids.into_iter().zip(return_data) {
+            let bin = ILBPair::getBinCall::abi_decode_returns(&data.returnData, true)?;
+            if bin.binReserveX != 0 || bin.binReserveY != 0 {
+                self.set_bin(id, Bin {reserve_x: bin.binReserveX, reserve_y: bin.binReserveY});
+            }
+        }
+        Ok(())
+    }
+
+    /// Reserves of the bin `id`, loading its word when missing
+    pub async fn get_bin(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        id: u32
+    ) -> Result<Bin> {
+        if !self.bin_bitmap.contains_key(&(id >> 8)) {
+            self.load_bin_words(provider, vec![id >> 8]).await?;
+        }
+        Ok(self.bins.get(&id).copied().unwrap_or_default())
+    }
+
+    /// Id of the first non-empty bin after `id`, below it when swapping for Y and above it otherwise. Unloaded stretches
+    /// are skipped with `getNextNonEmptyBin` and only the words around the next bin are loaded.
+    pub async fn next_non_empty_bin(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        swap_for_y: bool,
+        id: u32
+    ) -> Result<Option<u32>> {
+        let mut id = id;
+        loop {
+            let next = if swap_for_y {
+                if id == 0 {return Ok(None)}
+                id - 1
+            } else {
+                if id >= MAX_ID {return Ok(None)}
+                id + 1
+            };
+            let word_pos = next >> 8;
+
+            let word = match self.bin_bitmap.get(&word_pos) {
+                Some(word) => *word,
+                None => {
+                    let pair = ILBPair::new(self.pair_address, provider);
+                    let next_id = pair.getNextNonEmptyBin(swap_for_y, id).block(self.block).call().await?.nextId;
+                    if next_id == 0 || next_id == MAX_ID {
+                        return Ok(None)
+                    }
+
+                    // words strictly between the two are empty, loaded ones are kept as they are
+                    let next_word_pos = next_id >> 8;
+                    for empty_word_pos in word_pos.min(next_word_pos) + 1..word_pos.max(next_word_pos) {
+                        self.bin_bitmap.entry(empty_word_pos).or_insert(U256::ZERO);
+                    }
+                    self.load_bin_words(provider, vec![word_pos, next_word_pos]).await?;
+                    continue
+                }
+            };
+
+            // bits at or below `next` for swaps for Y, at or above it otherwise
+            let bit_pos = (next & 0xff) as usize;
+            let masked = if swap_for_y {
+                word & (U256::MAX >> (255 - bit_pos))
+            } else {
+                word & (U256::MAX << bit_pos)
+            };
+
+            if !masked.is_zero() {
+                let found = if swap_for_y {255 - masked.leading_zeros()} else {masked.trailing_zeros()};
+                let next_id = word_pos << 8 | found as u32;
+                return Ok(if next_id == 0 || next_id == MAX_ID {None} else {Some(next_id)})
+            }
+
+            id = if swap_for_y {word_pos << 8} else {word_pos << 8 | 0xff};
+        }
+    }
+
+    /// Simulates a swap of exactly `amount_in` and returns the amount out and the total fee paid. The swap fails with
+    /// `LBPair__OutOfLiquidity` when the bins can't absorb the whole input, leaving the state unchanged.
+    pub async fn swap(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        swap_for_y: bool,
+        amount_in: u128
+    ) -> Result<(u128, u128)> {
+        let mut pair = self.clone();
+        let (amount_in_left, amount_out, fee) = pair.swap_bins(provider, swap_for_y, amount_in).await?;
+        if amount_in_left != 0 {
+            return Err(eyre!("LBPair__OutOfLiquidity"))
+        }
+
+        *self = pair;
+        Ok((amount_out, fee))
+    }
+
+    /// @notice Simulates a swap in.
+    /// @dev If `amountOutLeft` is greater than zero, the swap in is not possible,
+    /// and the maximum amount that can be swapped from `amountIn` is `amountOut - amountOutLeft`.
+    /// @param amountOut The amount of token X or Y to swap in
+    /// @param swapForY Whether the swap is for token Y (true) or token X (false)
+    /// @return amountIn The amount of token X or Y that can be swapped in, including the fee
+    /// @return amountOutLeft The amount of token Y or X that cannot be swapped out
+    /// @return fee The fee of the swap
+    pub async fn get_swap_in(
+        &self,
+        provider: &RootProvider<Http<Client>>,
+        amount_out: u128,
+        swap_for_y: bool
+    ) -> Result<(u128, u128, u128)> {
+        // bins loaded while searching are only kept by the copy
+        let mut pair = self.clone();
+        let mut parameters = pair.parameters;
+        parameters.update_references(pair.block_timestamp);
+
+        let (mut amount_in, mut amount_out_left, mut fee) = (0u128, amount_out, 0u128);
+        let mut id = parameters.active_id;
+        loop {
+            let bin_reserves = pair.get_bin(provider, id).await?.reserve_out(swap_for_y);
+            if bin_reserves > 0 {
+                let price = get_price_from_id(id, pair.bin_step)?;
+                let amount_out_of_bin = bin_reserves.min(amount_out_left);
+
+                parameters.update_volatility_accumulator(id);
+
+                let amount_in_without_fee = get_amount_in_without_fee(amount_out_of_bin, price, swap_for_y)?;
+                let fee_amount = get_fee_amount(amount_in_without_fee, parameters.get_total_fee(pair.bin_step))?;
+
+                amount_in += amount_in_without_fee + fee_amount;
+                amount_out_left -= amount_out_of_bin;
+                fee += fee_amount;
+            }
+
+            if amount_out_left == 0 {
+                break
+            }
+            match pair.next_non_empty_bin(provider, swap_for_y, id).await? {
+                Some(next_id) => id = next_id,
+                None => break
+            }
+        }
+
+        Ok((amount_in, amount_out_left, fee))
+    }
+
+    /// @notice Simulates a swap out.
+    /// @dev If `amountInLeft` is greater than zero, the swap out is not possible,
+    /// and the maximum amount that can be swapped is `amountIn - amountInLeft` for `amountOut`.
+    /// @param amountIn The amount of token X or Y to swap in
+    /// @param swapForY Whether the swap is for token Y (true) or token X (false)
+    /// @return amountInLeft The amount of token X or Y that cannot be swapped in
+    /// @return amountOut The amount of token Y or X that can be swapped out
+    /// @return fee The fee of the swap
+    pub async fn get_swap_out(
+        &self,
+        provider: &RootProvider<Http<Client>>,
+        amount_in: u128,
+        swap_for_y: bool
+    ) -> Result<(u128, u128, u128)> {
+        self.clone().swap_bins(provider, swap_for_y, amount_in).await
+    }
+
+    /// Non-empty bins sorted by id with their price of X in Y and reserves, the Liquidity Book counterpart of the tick
+    /// export of V3 pools
+    pub fn export_to_df(&self) -> Result<DataFrame> {
+        let mut ids: Vec<u32> = self.bins.iter().filter(|(_, bin)| **bin != Bin::default()).map(|(id, _)| *id).collect();
+        ids.sort();
+
+        let mut price = Vec::<f64>::new();
+        let mut reserve_x = Vec::<String>::new();
+        let mut reserve_y = Vec::<String>::new();
+        for id in &ids {
+            let bin = self.bins[id];
+            price.push(price_to_f64(get_price_from_id(*id, self.bin_step)?, self.token_x.decimals, self.token_y.decimals));
+            reserve_x.push(bin.reserve_x.to_string());
+            reserve_y.push(bin.reserve_y.to_string());
+        }
+
+        let series_vector = vec![
+            Series::new("id", ids),
+            Series::new("price", price),
+            Series::new("reserve_x", reserve_x),
+            Series::new("reserve_y", reserve_y)
+        ];
+
+        Ok(DataFrame::new(series_vector)?)
+    }
+
+    // the swap loop of LBPair.swap, bins and parameters are updated but the swap does not revert when the input isn't
+    // fully used, the amount left is returned as by getSwapOut
+    async fn swap_bins(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        swap_for_y: bool,
+        amount_in: u128
+    ) -> Result<(u128, u128, u128)> {
+        let mut parameters = self.parameters;
+        parameters.update_references(self.block_timestamp);
+
+        let (mut amount_in_left, mut amount_out, mut fee) = (amount_in, 0u128, 0u128);
+        let mut id = parameters.active_id;
+        loop {
+            let bin = self.get_bin(provider, id).await?;
+            if bin.reserve_out(swap_for_y) != 0 {
+                parameters.update_volatility_accumulator(id);
+
+                let (amount_in_with_fees, amount_out_of_bin, total_fees) = get_amounts(&bin, &parameters, self.bin_step, swap_for_y, id, amount_in_left)?;
+
+                if amount_in_with_fees > 0 {
+                    amount_in_left -= amount_in_with_fees;
+                    amount_out += amount_out_of_bin;
+                    fee += total_fees;
+
+                    let protocol_fee = get_protocol_fee_amount(total_fees, parameters.protocol_share);
+                    let amount_in_to_bin = amount_in_with_fees - protocol_fee;
+                    let bin = if swap_for_y {
+                        self.protocol_fee_x += protocol_fee;
+                        Bin {reserve_x: bin.reserve_x + amount_in_to_bin, reserve_y: bin.reserve_y - amount_out_of_bin}
+                    } else {
+                        self.protocol_fee_y += protocol_fee;
+                        Bin {reserve_x: bin.reserve_x - amount_out_of_bin, reserve_y: bin.reserve_y + amount_in_to_bin}
+                    };
+                    self.set_bin(id, bin);
+                }
+            }
+
+            if amount_in_left == 0 {
+                break
+            }
+            match self.next_non_empty_bin(provider, swap_for_y, id).await? {
+                Some(next_id) => id = next_id,
+                None => break
+            }
+        }
+
+        parameters.active_id = id;
+        self.parameters = parameters;
+        Ok((amount_in_left, amount_out, fee))
+    }
+
+    fn set_bin(&mut self, id: u32, bin: Bin) {
+        let word = self.bin_bitmap.entry(id >> 8).or_default();
+        if bin == Bin::default() {
+            word.set_bit((id & 0xff) as usize, false);
+            self.bins.remove(&id);
+        } else {
+            word.set_bit((id & 0xff) as usize, true);
+            self.bins.insert(id, bin);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use alloy::primitives::address;
+    use crate::{
+        liquidity_book::math::constants::REAL_ID_SHIFT,
+        test_utils::{offline_provider, token},
+        uniswap_v3::multicall::MULTICALL3_ADDRESS
+    };
+    use super::*;
+
+    /// Pair at price one with bin step 25, 1000 X in each of the 5 bins above the active bin, 1000 Y in each of the 5
+    /// bins below and 500 of both in the active bin. The words around the active bin are loaded.
+    pub fn pair_at_price_one() -> LBPairState {
+        let active_id = REAL_ID_SHIFT as u32;
+        let mut pair = LBPairState {
+            pair_address: Address::ZERO,
+            multicall_address: MULTICALL3_ADDRESS,
+            token_x: token(address!("0000000000000000000000000000000000000001"), 18),
+            token_y: token(address!("0000000000000000000000000000000000000002"), 18),
+            bin_step: 25,
+            parameters: PairParameters {
+                base_factor: 5000,
+                filter_period: 30,
+                decay_period: 600,
+                reduction_factor: 5000,
+                variable_fee_control: 40000,
+                protocol_share: 1000,
+                max_volatility_accumulator: 350000,
+                volatility_accumulator: 0,
+                volatility_reference: 0,
+                id_reference: active_id,
+                time_of_last_update: 0,
+                active_id
+            },
+            bins: HashMap::new(),
+            bin_bitmap: HashMap::new(),
+            protocol_fee_x: 0,
+            protocol_fee_y: 0,
+            block: BlockId::number(1),
+            block_timestamp: 1000
+        };
+        for word_pos in (active_id >> 8) - 1..=(active_id >> 8) + 1 {
+            pair.bin_bitmap.insert(word_pos, U256::ZERO);
+        }
+
+        let amount = 1000000000000000000000u128;
+        pair.set_bin(active_id, Bin {reserve_x: amount / 2, reserve_y: amount / 2});
+        for offset in 1..=5 {
+            pair.set_bin(active_id + offset, Bin {reserve_x: amount, reserve_y: 0});
+            pair.set_bin(active_id - offset, Bin {reserve_x: 0, reserve_y: amount});
+        }
+        pair
+    }
+
+    #[tokio::test]
+    async fn swap_across_bins_test() {
+        let provider = offline_provider();
+        let mut pair = pair_at_price_one();
+        let active_id = pair.parameters.active_id;
+
+        // 2000 X in crosses the active bin and two bins below it with a growing variable fee. The amounts come from
+        // scripts/lb_vectors.py.
+        let amount_in = 2000000000000000000000u128;
+        let (amount_in_left, quoted_out, quoted_fee) = pair.get_swap_out(&provider, amount_in, true).await.unwrap();
+        assert_eq!(amount_in_left, 0);
+        assert_eq!((quoted_out, quoted_fee), (1992459968348125194368, 2574653935332003876));
+
+        let (amount_out, fee) = pair.swap(&provider, true, amount_in).await.unwrap();
+        assert_eq!((amount_out, fee), (quoted_out, quoted_fee));
+        assert_eq!(pair.parameters.active_id, active_id - 2);
+        assert_eq!(pair.parameters.volatility_accumulator, 20000);
+        assert_eq!(pair.protocol_fee_x, 257465393533200386);
+        assert!(pair.bins.get(&active_id).unwrap().reserve_y == 0 && pair.bins.get(&(active_id - 1)).unwrap().reserve_y == 0);
+
+        // the input for an output is enough to get it
+        let (amount_in, amount_out_left, _) = pair.get_swap_in(&provider, 1000000000000000000000, false).await.unwrap();
+        assert_eq!(amount_out_left, 0);
+        let (_, amount_out, _) = pair.get_swap_out(&provider, amount_in, false).await.unwrap();
+        assert!(amount_out >= 1000000000000000000000);
+
+        // with every word below loaded the bins run out without the provider, a failed swap leaves the pair unchanged
+        for word_pos in 0..(active_id >> 8) {
+            pair.bin_bitmap.entry(word_pos).or_insert(U256::ZERO);
+        }
+        let (parameters, bins, bin_bitmap) = (pair.parameters, pair.bins.clone(), pair.bin_bitmap.clone());
+        let err = pair.swap(&provider, true, 10000000000000000000000).await.unwrap_err();
+        assert_eq!(err.to_string(), "LBPair__OutOfLiquidity");
+        assert_eq!(pair.parameters, parameters);
+        assert!(pair.bins == bins && pair.bin_bitmap == bin_bitmap);
+        assert_eq!(pair.protocol_fee_x, 257465393533200386);
+
+        let df = pair.export_to_df().unwrap();
+        assert_eq!(df.height(), 11);
+    }
+}
@@ -0,0 +1,133 @@
+use alloy::{
+    primitives::{Address, B256},
+    providers::{Provider, RootProvider},
+    rpc::types::eth::{BlockId, BlockNumberOrTag, Filter, Log},
+    transports::http::{Client, Http}
+};
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+
+// number of blocks requested per eth_getLogs call
+pub const LOG_CHUNK_SIZE: u64 = 2000;
+
+/// Puts logs in the order they were emitted, `eth_getLogs` gives no guarantee when several addresses are queried
+pub fn sort_logs(logs: &mut [Log]) {
+    logs.sort_by_key(|log| (log.block_number, log.log_index));
+}
+
+/// Pool whose reserves are overwritten by every `Sync` log it emits, like Uniswap V2 pairs and Solidly pools
+pub trait SyncPool {
+    // topic of the pool's Sync event
+    const SYNC_SIGNATURE: B256;
+
+    fn pool_address(&self) -> Address;
+
+    /// Block the reserves were read at
+    fn block(&self) -> BlockId;
+
+    fn set_block(&mut self, block: BlockId);
+
+    /// Applies a `Sync` log of the pool, other logs are ignored
+    fn apply_log(&mut self, log: &Log) -> Result<()>;
+}
+
+/// Brings the reserves of `pools` forward to `to_block` (inclusive) from their `Sync` logs. All pools have to be pinned
+/// to the same block number, at most `to_block`.
+pub async fn sync_pools<P: SyncPool>(
+    provider: &RootProvider<Http<Client>>,
+    pools: &mut [P],
+    to_block: u64
+) -> Result<()> {
+    let start_block = match pools.first().map(|pool| pool.block()) {
+        Some(BlockId::Number(BlockNumberOrTag::Number(block))) => block,
+        Some(_) => return Err(eyre!("Pools must be pinned to a block number for syncing")),
+        None => return Ok(())
+    };
+    if pools.iter().any(|pool| pool.block() != BlockId::number(start_block)) {
+        return Err(eyre!("Pools are pinned to different blocks"))
+    }
+    if to_block < start_block {
+        return Err(eyre!("Pools are pinned to block {} which is past {}", start_block, to_block))
+    }
+
+    let mut from_block = start_block + 1;
+
+    while from_block <= to_block {
+        let chunk_end = std::cmp::min(from_block + LOG_CHUNK_SIZE - 1, to_block);
+
+        let filter = Filter::new()
+            .address(pools.iter().map(|pool| pool.pool_address()).collect::<Vec<Address>>())
+            .from_block(from_block)
+            .to_block(chunk_end)
+            .event_signature(P::SYNC_SIGNATURE);
+
+        apply_sync_logs(pools, provider.get_logs(&filter).await?)?;
+        from_block = chunk_end + 1;
+    }
+
+    for pool in pools.iter_mut() {
+        pool.set_block(BlockId::number(to_block));
+    }
+
+    Ok(())
+}
+
+// only the last Sync of a pool counts, so the logs are applied in the order they were emitted
+fn apply_sync_logs<P: SyncPool>(pools: &mut [P], mut logs: Vec<Log>) -> Result<()> {
+    let index: HashMap<Address, usize> = pools.iter().enumerate().map(|(i, pool)| (pool.pool_address(), i)).collect();
+    sort_logs(&mut logs);
+
+    for log in logs {
+        if let Some(&i) = index.get(&log.inner.address) {
+            pools[i].apply_log(&log)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use crate::{
+        test_utils::{log, offline_provider, token},
+        uniswap_v2::pair::{IPair, PairState}
+    };
+    use super::*;
+
+    #[tokio::test]
+    async fn sync_pools_test() {
+        let pair = |pair_address: Address| PairState {
+            pair_address,
+            token0: token(address!("0000000000000000000000000000000000000001"), 18),
+            token1: token(address!("0000000000000000000000000000000000000002"), 18),
+            reserve0: 1000,
+            reserve1: 1000,
+            block_timestamp_last: 0,
+            fee: 3000,
+            block: BlockId::number(100)
+        };
+        let (first, second) = (address!("00000000000000000000000000000000000000a1"), address!("00000000000000000000000000000000000000a2"));
+        let mut pairs = vec![pair(first), pair(second)];
+
+        // logs of both pairs as a node may return them, the latest Sync of each pair comes first
+        let sync = |reserve0: u128, reserve1: u128| IPair::Sync {reserve0, reserve1};
+        let logs = vec![
+            log(second, 102, 3, 0, &sync(7, 8)),
+            log(first, 102, 1, 0, &sync(5, 6)),
+            log(first, 101, 0, 0, &sync(1, 2)),
+            log(second, 101, 4, 0, &sync(3, 4)),
+            log(Address::ZERO, 103, 0, 0, &sync(0, 0))
+        ];
+        apply_sync_logs(&mut pairs, logs).unwrap();
+        assert_eq!((pairs[0].reserve0, pairs[0].reserve1), (5, 6));
+        assert_eq!((pairs[1].reserve0, pairs[1].reserve1), (7, 8));
+
+        // checked before any log is requested
+        let provider = offline_provider();
+        assert!(sync_pools(&provider, &mut pairs, 99).await.is_err());
+        pairs[1].block = BlockId::number(101);
+        assert!(sync_pools(&provider, &mut pairs, 200).await.is_err());
+        assert!(sync_pools::<PairState>(&provider, &mut [], 200).await.is_ok());
+    }
+}
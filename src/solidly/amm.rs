@@ -0,0 +1,146 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    transports::http::{Client, Http}
+};
+use crate::{amm::{resolve_block_number, Amm, LoadAmm}, logs::sync_pools};
+use super::pool::SolidlyPoolState;
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+impl SolidlyPoolState {
+    fn zero_for_one(&self, token_in: Address, token_out: Address) -> Result<bool> {
+        if token_in == self.token0.address && token_out == self.token1.address {
+            Ok(true)
+        } else if token_in == self.token1.address && token_out == self.token0.address {
+            Ok(false)
+        } else {
+            Err(eyre!("Pool {} does not trade {} for {}", self.pool_address, token_in, token_out))
+        }
+    }
+}
+
+impl Amm for SolidlyPoolState {
+    fn address(&self) -> Address {
+        self.pool_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.token0.address, self.token1.address]
+    }
+
+    /// Marginal price of the invariant, for stable pools the slope of x3y + y3x = k in whole tokens
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let x = f64::from(self.reserve0) / 10f64.powi(self.token0.decimals as i32);
+        let y = f64::from(self.reserve1) / 10f64.powi(self.token1.decimals as i32);
+        let price = if self.stable {
+            (3.0 * x * x * y + y * y * y) / (x * x * x + 3.0 * x * y * y)
+        } else {
+            y / x
+        };
+        if self.zero_for_one(base, quote)? {Ok(price)} else {Ok(1.0 / price)}
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.get_amount_out(self.zero_for_one(token_in, token_out)?, amount_in)
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.get_amount_in(self.zero_for_one(token_in, token_out)?, amount_out)
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            self.swap(zero_for_one, amount_in)
+        })
+    }
+
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            sync_pools(provider, std::slice::from_mut(self), to_block).await
+        })
+    }
+}
+
+impl LoadAmm for SolidlyPoolState {
+    async fn load(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let block = resolve_block_number(provider, block).await?;
+        SolidlyPoolState::load_at_block(provider, multicall_address, address, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use crate::test_utils::{offline_provider, token};
+    use super::*;
+
+    #[tokio::test]
+    async fn solidly_amm_test() {
+        let provider = offline_provider();
+        let (usdc, dai) = (address!("0000000000000000000000000000000000000001"), address!("0000000000000000000000000000000000000002"));
+        let mut pool = SolidlyPoolState {
+            pool_address: Address::ZERO,
+            factory_address: Address::ZERO,
+            token0: token(usdc, 6),
+            token1: token(dai, 18),
+            stable: true,
+            reserve0: U256::from(5000000000000u64),
+            reserve1: U256::from(5200000000000000000000000u128),
+            fee: 5,
+            block: BlockId::number(1)
+        };
+
+        // close to 1 around balanced reserves, unlike the 1.04 of a volatile pool
+        let price = pool.spot_price(usdc, dai).unwrap();
+        assert!(price > 1.0 && price < 1.01);
+
+        let amount_in = U256::from(10000000000u64);
+        let quoted_out = pool.quote_exact_input(&provider, usdc, dai, amount_in).await.unwrap();
+        assert_eq!(quoted_out, U256::from(9995129571614185597086u128));
+        // the smallest input paying the quote, one unit less than the input it came from
+        let amount_in_back = pool.quote_exact_output(&provider, usdc, dai, quoted_out).await.unwrap();
+        assert_eq!(amount_in_back, U256::from(9999999999u64));
+        assert!(pool.get_amount_out(true, amount_in_back).unwrap() >= quoted_out);
+        assert!(pool.get_amount_out(true, amount_in_back - U256::from(1)).unwrap() < quoted_out);
+
+        assert_eq!(pool.swap_exact_input(&provider, usdc, dai, amount_in).await.unwrap(), quoted_out);
+        // the fee leaves the pool
+        assert_eq!(pool.reserve0, U256::from(5000000000000u64 + 9995000000));
+        assert_eq!(pool.reserve1, U256::from(5200000000000000000000000u128) - quoted_out);
+        assert!(pool.quote_exact_input(&provider, dai, Address::ZERO, amount_in).await.is_err());
+    }
+}
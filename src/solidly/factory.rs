@@ -0,0 +1,105 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::multicall::multicall;
+use eyre::{eyre, Result};
+
+sol! {
+    #[sol(rpc)]
+    interface IPoolFactory {
+        function getPool(address tokenA, address tokenB, bool stable) external view returns (address pool);
+
+        function getFee(address pool, bool _stable) external view returns (uint256);
+
+        function allPools(uint256 index) external view returns (address pool);
+
+        function allPoolsLength() external view returns (uint256);
+    }
+}
+
+/// Address of the stable or volatile pool of `pair` created by the factory, errors if there is none
+pub async fn get_pool_address(
+    provider: &RootProvider<Http<Client>>,
+    factory_address: Address,
+    pair: (Address, Address),
+    stable: bool,
+    block: BlockId
+) -> Result<Address> {
+    let factory = IPoolFactory::new(factory_address, provider);
+
+    let pool_address = factory.getPool(pair.0, pair.1, stable).block(block).call().await?.pool;
+    if pool_address != Address::ZERO {Ok(pool_address)} else {Err(eyre!("Pool not found for tokens: {:?}, stable: {}", pair, stable))}
+}
+
+/// Addresses of the stable and volatile pools of `pair`, skipping the ones that were not created
+pub async fn get_pool_addresses(
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    factory_address: Address,
+    pair: (Address, Address),
+    block: BlockId
+) -> Result<Vec<Address>> {
+    let call_data = [true, false]
+        .into_iter()
+        .map(|stable| IPoolFactory::getPoolCall{tokenA: pair.0, tokenB: pair.1, stable}.abi_encode())
+        .collect();
+
+    let mut pools = Vec::new();
+    for data in multicall(provider, multicall_address, factory_address, false, call_data, block).await? {
+        let pool = IPoolFactory::getPoolCall::abi_decode_returns(&data.returnData, true)?.pool;
+        if pool != Address::ZERO {
+            pools.push(pool);
+        }
+    }
+
+    Ok(pools)
+}
+
+/// Swap fee of `pool` in basis points, the custom fee of the pool if one is set and the stable or volatile default
+/// otherwise
+pub async fn get_fee(
+    provider: &RootProvider<Http<Client>>,
+    factory_address: Address,
+    pool: Address,
+    stable: bool,
+    block: BlockId
+) -> Result<u32> {
+    let factory = IPoolFactory::new(factory_address, provider);
+    Ok(u32::try_from(factory.getFee(pool, stable).block(block).call().await?._0)?)
+}
+
+/// Number of pools created by the factory
+pub async fn all_pools_length(
+    provider: &RootProvider<Http<Client>>,
+    factory_address: Address,
+    block: BlockId
+) -> Result<u64> {
+    let factory = IPoolFactory::new(factory_address, provider);
+    Ok(u64::try_from(factory.allPoolsLength().block(block).call().await?._0)?)
+}
+
+/// Addresses of the pools created by the factory with index in `[from_index, to_index)`, in creation order
+pub async fn all_pools(
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    factory_address: Address,
+    from_index: u64,
+    to_index: u64,
+    block: BlockId
+) -> Result<Vec<Address>> {
+    let call_data: Vec<Vec<u8>> = (from_index..to_index)
+        .map(|index| IPoolFactory::allPoolsCall{index: U256::from(index)}.abi_encode())
+        .collect();
+
+    let mut pools = Vec::new();
+    for data in multicall(provider, multicall_address, factory_address, false, call_data, block).await? {
+        pools.push(IPoolFactory::allPoolsCall::abi_decode_returns(&data.returnData, true)?.pool);
+    }
+
+    Ok(pools)
+}
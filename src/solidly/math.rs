@@ -0,0 +1,154 @@
+use alloy::primitives::U256;
+use eyre::{eyre, Result};
+
+// reserves of stable pools are normalized to 18 decimals
+const ONE: U256 = U256::from_limbs([1000000000000000000, 0, 0, 0]);
+
+// swap fees are in basis points, the factory defaults are 5 for stable and 30 for volatile pools
+pub const FEE_DENOMINATOR: u32 = 10000;
+
+/// @notice The invariant of the pool, x3y+y3x for stable pools on reserves normalized to 18 decimals and xy for
+/// volatile pools
+/// @param x The reserve of token0
+/// @param y The reserve of token1
+/// @param decimals0 10 ** decimals of token0
+/// @param decimals1 10 ** decimals of token1
+pub fn k(x: U256, y: U256, decimals0: U256, decimals1: U256, stable: bool) -> U256 {
+    if stable {
+        let _x = x * ONE / decimals0;
+        let _y = y * ONE / decimals1;
+        let _a = _x * _y / ONE;
+        let _b = _x * _x / ONE + _y * _y / ONE;
+        _a * _b / ONE // x3y+y3x >= k
+    } else {
+        x * y // xy >= k
+    }
+}
+
+fn f(x0: U256, y: U256) -> U256 {
+    let _a = x0 * y / ONE;
+    let _b = x0 * x0 / ONE + y * y / ONE;
+    _a * _b / ONE
+}
+
+fn d(x0: U256, y: U256) -> U256 {
+    U256::from(3) * x0 * (y * y / ONE) / ONE + x0 * x0 / ONE * x0 / ONE
+}
+
+/// @notice Solves x0^3 y + x0 y^3 = xy for y with Newton's method, starting from `y`
+/// @dev Like the pool, the check for y + 1 goes through `k` and scales by the decimals again
+/// @param x0 The normalized reserve in after the swap
+/// @param xy The invariant to keep
+/// @param y The normalized reserve out before the swap
+pub fn get_y(x0: U256, xy: U256, mut y: U256, decimals0: U256, decimals1: U256) -> Result<U256> {
+    for _ in 0..255 {
+        let k_ = f(x0, y);
+        if k_ < xy {
+            // there are two cases where dy == 0
+            // case 1: The y is converged and we find the correct answer
+            // case 2: _d(x0, y) is too large compare to (xy - k) and the rounding error
+            //         screwed us.
+            //         In this case, we need to increase y by 1
+            let mut dy = (xy - k_) * ONE / d(x0, y);
+            if dy.is_zero() {
+                if k(x0, y + U256::from(1), decimals0, decimals1, true) > xy {
+                    // If _k(x0, y + 1) > xy, then we are close to the correct answer.
+                    // There's no closer answer than y + 1
+                    return Ok(y + U256::from(1))
+                }
+                dy = U256::from(1);
+            }
+            y += dy;
+        } else {
+            let mut dy = (k_ - xy) * ONE / d(x0, y);
+            if dy.is_zero() {
+                if k_ == xy || f(x0, y - U256::from(1)) < xy {
+                    // If k == xy, we found the correct answer.
+                    // If _f(x0, y - 1) < xy, then we are close to the correct answer.
+                    // There's no closer answer than "y"
+                    return Ok(y)
+                }
+                dy = U256::from(1);
+            }
+            y = y.checked_sub(dy).ok_or(eyre!("!y"))?;
+        }
+    }
+    Err(eyre!("!y"))
+}
+
+/// @notice Amount of the other token received for `amountIn` of token0 (`zero_for_one`) or token1, after the fee was
+/// taken off `amountIn`
+/// @param amountIn The amount in, net of the fee
+/// @param reserve0 The reserve of token0
+/// @param reserve1 The reserve of token1
+/// @param decimals0 10 ** decimals of token0
+/// @param decimals1 10 ** decimals of token1
+/// @param stable Whether the pool follows the stable invariant
+pub fn get_amount_out(
+    amount_in: U256,
+    zero_for_one: bool,
+    reserve0: U256,
+    reserve1: U256,
+    decimals0: U256,
+    decimals1: U256,
+    stable: bool
+) -> Result<U256> {
+    if stable {
+        let xy = k(reserve0, reserve1, decimals0, decimals1, true);
+        let reserve0 = reserve0 * ONE / decimals0;
+        let reserve1 = reserve1 * ONE / decimals1;
+        let (reserve_a, reserve_b) = if zero_for_one {(reserve0, reserve1)} else {(reserve1, reserve0)};
+        let amount_in = if zero_for_one {amount_in * ONE / decimals0} else {amount_in * ONE / decimals1};
+        let y = reserve_b.checked_sub(get_y(amount_in + reserve_a, xy, reserve_b, decimals0, decimals1)?).ok_or(eyre!("!y"))?;
+        Ok(y * (if zero_for_one {decimals1} else {decimals0}) / ONE)
+    } else {
+        let (reserve_a, reserve_b) = if zero_for_one {(reserve0, reserve1)} else {(reserve1, reserve0)};
+        if (reserve_a + amount_in).is_zero() {
+            return Err(eyre!("Pool: INSUFFICIENT_LIQUIDITY"))
+        }
+        Ok(amount_in * reserve_b / (reserve_a + amount_in))
+    }
+}
+
+/// Takes the swap fee in basis points off `amount_in`, like `getAmountOut` of the pool
+pub fn amount_after_fee(amount_in: U256, fee: u32) -> U256 {
+    amount_in - amount_in * U256::from(fee) / U256::from(FEE_DENOMINATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_amount_out_test() {
+        // the amounts come from scripts/solidly_vectors.py, the stable swap keeps x3y + y3x in whole tokens
+        let (usdc, dai) = (U256::from(1000000u64), ONE);
+        let (reserve0, reserve1) = (U256::from(5000000000000u64), U256::from(5200000000000000000000000u128));
+        assert_eq!(
+            get_amount_out(amount_after_fee(U256::from(10000000000u64), 5), true, reserve0, reserve1, usdc, dai, true).unwrap(),
+            U256::from(9995129571614185597086u128)
+        );
+        let k = |x: f64, y: f64| x * x * x * y + y * y * y * x;
+        let (x, y) = (5000000.0, 5200000.0);
+        let (x_after, y_after) = (x + 10000.0 * 0.9995, y - 9995.129571614186);
+        assert!((k(x_after, y_after) - k(x, y)).abs() / k(x, y) < 1e-12);
+        assert_eq!(
+            get_amount_out(amount_after_fee(U256::from(10000000000000000000000u128), 5), false, reserve0, reserve1, usdc, dai, true).unwrap(),
+            U256::from(9994825169u64)
+        );
+
+        let (weth, usdc) = (ONE, U256::from(1000000u64));
+        assert_eq!(
+            get_amount_out(
+                amount_after_fee(ONE, 30),
+                true,
+                U256::from(1000000000000000000000u128),
+                U256::from(2000000000000u64),
+                weth,
+                usdc,
+                false
+            ).unwrap(),
+            U256::from(1992013962u64)
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod math;
+pub mod utils;
+pub mod pool;
+pub mod factory;
+pub mod amm;
@@ -0,0 +1,215 @@
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::RootProvider,
+    rpc::types::eth::{BlockId, Log},
+    sol,
+    sol_types::{SolCall, SolEvent},
+    transports::http::{Client, Http}
+};
+use crate::{
+    logs::SyncPool,
+    uniswap_v3::{multicall::multicall_targets, pool::{load_tokens, SwapResult, Token}}
+};
+use super::{factory::{get_pool_address, IPoolFactory}, math};
+use eyre::{eyre, Result};
+
+sol! {
+    #[sol(rpc)]
+    interface IPool {
+        event Sync(uint256 reserve0, uint256 reserve1);
+
+        function factory() external view returns (address);
+
+        function metadata() external view returns (uint256 dec0, uint256 dec1, uint256 r0, uint256 r1, bool st, address t0, address t1);
+    }
+}
+
+/// Reserves of a Solidly style pool (Velodrome V2, Aerodrome), either volatile (xy = k) or stable (x3y + y3x = k)
+#[derive(Clone)]
+pub struct SolidlyPoolState {
+    pub pool_address: Address,
+    pub factory_address: Address,
+    pub token0: Token,
+    pub token1: Token,
+    pub stable: bool,
+    pub reserve0: U256,
+    pub reserve1: U256,
+    // swap fee in basis points as returned by the factory's getFee
+    pub fee: u32,
+    pub block: BlockId
+}
+
+impl SolidlyPoolState {
+    pub async fn load_at_block(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let mut pools = load_pools(provider, multicall_address, &[pool_address], block).await?;
+        pools.pop().ok_or(eyre!("Pool {} not loaded", pool_address))
+    }
+
+    /// @notice Amount of the other token received for exactly `amount_in` of token0 (`zero_for_one`) or token1
+    /// @dev Same as `getAmountOut` of the pool, the fee is taken off the amount in first
+    pub fn get_amount_out(&self, zero_for_one: bool, amount_in: U256) -> Result<U256> {
+        math::get_amount_out(
+            math::amount_after_fee(amount_in, self.fee),
+            zero_for_one,
+            self.reserve0,
+            self.reserve1,
+            self.decimals0(),
+            self.decimals1(),
+            self.stable
+        )
+    }
+
+    /// Smallest amount of token0 (`zero_for_one`) or token1 for which `get_amount_out` pays at least `amount_out`. The
+    /// pool has no inverse of `getAmountOut`, the amount is found by bisection over it.
+    pub fn get_amount_in(&self, zero_for_one: bool, amount_out: U256) -> Result<U256> {
+        let (reserve_in, reserve_out) = if zero_for_one {(self.reserve0, self.reserve1)} else {(self.reserve1, self.reserve0)};
+        if amount_out.is_zero() || amount_out >= reserve_out || reserve_in.is_zero() {
+            return Err(eyre!("Pool: INSUFFICIENT_LIQUIDITY"))
+        }
+
+        let mut high = U256::from(1);
+        while self.get_amount_out(zero_for_one, high)? < amount_out {
+            if high.bit_len() > 192 {
+                return Err(eyre!("Pool: INSUFFICIENT_LIQUIDITY"))
+            }
+            high <<= 1;
+        }
+
+        let mut low = high >> 1;
+        while high - low > U256::from(1) {
+            let mid = (low + high) >> 1;
+            if self.get_amount_out(zero_for_one, mid)? >= amount_out {high = mid} else {low = mid}
+        }
+        Ok(high)
+    }
+
+    /// Simulates a `swap` paid with exactly `amount_in` and returns the amount received. The fee is sent out of the
+    /// pool to the fee contract, so only the rest of the input is added to the reserves.
+    pub fn swap(&mut self, zero_for_one: bool, amount_in: U256) -> Result<U256> {
+        let amount_out = self.get_amount_out(zero_for_one, amount_in)?;
+        let amount_in_after_fee = math::amount_after_fee(amount_in, self.fee);
+
+        if zero_for_one {
+            (self.reserve0, self.reserve1) = (self.reserve0 + amount_in_after_fee, self.reserve1 - amount_out);
+        } else {
+            (self.reserve1, self.reserve0) = (self.reserve1 + amount_in_after_fee, self.reserve0 - amount_out);
+        }
+        Ok(amount_out)
+    }
+
+    /// 10 ** decimals of token0, the scale of `decimals0` in the pool
+    pub fn decimals0(&self) -> U256 {
+        U256::from(10).pow(U256::from(self.token0.decimals))
+    }
+
+    /// 10 ** decimals of token1
+    pub fn decimals1(&self) -> U256 {
+        U256::from(10).pow(U256::from(self.token1.decimals))
+    }
+}
+
+/// Loads reserves, tokens and fees of many pools with a few multicalls. The fee of every pool is read from the factory
+/// that created it.
+pub async fn load_pools(
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    pool_addresses: &[Address],
+    block: BlockId
+) -> Result<Vec<SolidlyPoolState>> {
+    let call_data: Vec<(Address, Vec<u8>)> = pool_addresses
+        .iter()
+        .flat_map(|&pool_address| [
+            (pool_address, IPool::factoryCall{}.abi_encode()),
+            (pool_address, IPool::metadataCall{}.abi_encode())
+        ])
+        .collect();
+    let return_data = multicall_targets(provider, multicall_address, false, call_data, block).await?;
+
+    let mut pool_data = Vec::new();
+    for data in return_data.chunks(2) {
+        let factory_address = IPool::factoryCall::abi_decode_returns(&data[0].returnData, true)?._0;
+        let metadata = IPool::metadataCall::abi_decode_returns(&data[1].returnData, true)?;
+        pool_data.push((factory_address, metadata));
+    }
+
+    let fee_calls: Vec<(Address, Vec<u8>)> = pool_addresses
+        .iter()
+        .zip(&pool_data)
+        .map(|(&pool, (factory_address, metadata))| (*factory_address, IPoolFactory::getFeeCall{pool, _stable: metadata.st}.abi_encode()))
+        .collect();
+    let fees = multicall_targets(provider, multicall_address, false, fee_calls, block).await?
+        .iter()
+        .map(|data| Ok(u32::try_from(IPoolFactory::getFeeCall::abi_decode_returns(&data.returnData, true)?._0)?))
+        .collect::<Result<Vec<u32>>>()?;
+
+    let tokens = load_tokens(provider, multicall_address, pool_data.iter().flat_map(|(_, metadata)| [metadata.t0, metadata.t1]).collect(), block).await?;
+
+    Ok(pool_addresses
+        .iter()
+        .zip(pool_data)
+        .zip(fees)
+        .map(|((&pool_address, (factory_address, metadata)), fee)| SolidlyPoolState {
+            pool_address,
+            factory_address,
+            token0: tokens[&metadata.t0].clone(),
+            token1: tokens[&metadata.t1].clone(),
+            stable: metadata.st,
+            reserve0: metadata.r0,
+            reserve1: metadata.r1,
+            fee,
+            block
+        })
+        .collect())
+}
+
+/// Fee changes made on the factory are not tracked by syncing
+impl SyncPool for SolidlyPoolState {
+    const SYNC_SIGNATURE: B256 = IPool::Sync::SIGNATURE_HASH;
+
+    fn pool_address(&self) -> Address {
+        self.pool_address
+    }
+
+    fn block(&self) -> BlockId {
+        self.block
+    }
+
+    fn set_block(&mut self, block: BlockId) {
+        self.block = block;
+    }
+
+    fn apply_log(&mut self, log: &Log) -> Result<()> {
+        if log.inner.address != self.pool_address || log.inner.topics().first() != Some(&IPool::Sync::SIGNATURE_HASH) {
+            return Ok(())
+        }
+
+        let sync = log.log_decode::<IPool::Sync>()?.inner.data;
+        (self.reserve0, self.reserve1) = (sync.reserve0, sync.reserve1);
+        Ok(())
+    }
+}
+
+/// Quotes an exact input swap of `amount_in` of `pair.0` (`one_for_two`) or `pair.1` through the stable or volatile
+/// pool of `pair`, loaded at the latest block
+pub async fn simulate_exact_input_single(
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    pool_factory_address: Address,
+    pair: (Address, Address),
+    stable: bool,
+    amount_in: U256,
+    one_for_two: bool
+) -> Result<SwapResult> {
+    let pool_address = get_pool_address(provider, pool_factory_address, pair, stable, BlockId::latest()).await?;
+    let pool_state = SolidlyPoolState::load_at_block(provider, multicall_address, pool_address, BlockId::latest()).await?;
+
+    let zero_for_one = if pair.0 == pool_state.token0.address {one_for_two} else {!one_for_two};
+    let amount_out = pool_state.get_amount_out(zero_for_one, amount_in)?;
+
+    Ok(SwapResult{amount_in, amount_out})
+}
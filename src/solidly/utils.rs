@@ -0,0 +1,4 @@
+use alloy::primitives::{address, Address};
+
+pub const VELODROME_V2_FACTORY_ADDRESS: Address = address!("F1046053aa5682b4F9a81b5481394DA16BE5FF5a");
+pub const AERODROME_FACTORY_ADDRESS: Address = address!("420DD381b31aEf6683db6B902084cB0FFECe40Da");
@@ -0,0 +1,31 @@
+use alloy::{
+    primitives::{Address, LogData},
+    providers::{ProviderBuilder, RootProvider},
+    rpc::types::eth::Log,
+    sol_types::SolEvent,
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::pool::Token;
+
+/// Provider for tests running on preloaded state, it points at a local node and is never called
+pub fn offline_provider() -> RootProvider<Http<Client>> {
+    ProviderBuilder::new().on_http("http://localhost:8545".parse().unwrap())
+}
+
+/// Token without a symbol
+pub fn token(address: Address, decimals: u8) -> Token {
+    Token {address, symbol: String::new(), decimals}
+}
+
+
+/// Log of `event` as returned by `eth_getLogs`, the block timestamp is included so it is never fetched
+pub fn log(address: Address, block: u64, log_index: u64, timestamp: u64, event: &impl SolEvent) -> Log {
+    let topics = event.encode_topics().into_iter().map(|topic| topic.0).collect();
+    Log {
+        inner: alloy::primitives::Log {address, data: LogData::new_unchecked(topics, event.encode_data().into())},
+        block_number: Some(block),
+        block_timestamp: Some(timestamp),
+        log_index: Some(log_index),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,135 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    transports::http::{Client, Http}
+};
+use crate::{amm::{resolve_block_number, Amm, LoadAmm}, logs::sync_pools};
+use super::{
+    pair::PairState,
+    utils::UNISWAP_V2_FEE
+};
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+impl PairState {
+    fn zero_for_one(&self, token_in: Address, token_out: Address) -> Result<bool> {
+        if token_in == self.token0.address && token_out == self.token1.address {
+            Ok(true)
+        } else if token_in == self.token1.address && token_out == self.token0.address {
+            Ok(false)
+        } else {
+            Err(eyre!("Pair {} does not trade {} for {}", self.pair_address, token_in, token_out))
+        }
+    }
+}
+
+impl Amm for PairState {
+    fn address(&self) -> Address {
+        self.pair_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.token0.address, self.token1.address]
+    }
+
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let price = self.reserve1 as f64 / self.reserve0 as f64
+            * 10f64.powi(self.token0.decimals as i32 - self.token1.decimals as i32);
+        if self.zero_for_one(base, quote)? {Ok(price)} else {Ok(1.0 / price)}
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.get_amount_out(self.zero_for_one(token_in, token_out)?, amount_in)
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            self.get_amount_in(self.zero_for_one(token_in, token_out)?, amount_out)
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        _provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            self.swap(zero_for_one, amount_in)
+        })
+    }
+
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            sync_pools(provider, std::slice::from_mut(self), to_block).await
+        })
+    }
+}
+
+impl LoadAmm for PairState {
+    /// Loads the pair with the Uniswap V2 fee, forks with another fee use `PairState::load_at_block`
+    async fn load(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let block = resolve_block_number(provider, block).await?;
+        PairState::load_at_block(provider, multicall_address, address, UNISWAP_V2_FEE, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use crate::test_utils::{offline_provider, token};
+    use super::*;
+
+    #[tokio::test]
+    async fn v2_amm_test() {
+        let provider = offline_provider();
+        let (token0, token1) = (address!("0000000000000000000000000000000000000001"), address!("0000000000000000000000000000000000000002"));
+        let mut pair = PairState {
+            pair_address: Address::ZERO,
+            token0: token(token0, 18),
+            token1: token(token1, 6),
+            reserve0: 1000000000000000000000,
+            reserve1: 2000000000000,
+            block_timestamp_last: 0,
+            fee: UNISWAP_V2_FEE,
+            block: BlockId::number(1)
+        };
+
+        assert!((pair.spot_price(token0, token1).unwrap() - 2000.0).abs() < 1e-9);
+
+        let amount_in = U256::from(1000000000000000000u128);
+        let quoted_out = pair.quote_exact_input(&provider, token0, token1, amount_in).await.unwrap();
+        assert!(pair.quote_exact_output(&provider, token0, token1, quoted_out).await.unwrap() <= amount_in);
+
+        assert_eq!(pair.swap_exact_input(&provider, token0, token1, amount_in).await.unwrap(), quoted_out);
+        assert_eq!(pair.reserve0, 1001000000000000000000);
+        assert_eq!(U256::from(pair.reserve1), U256::from(2000000000000u128) - quoted_out);
+        assert!(pair.quote_exact_input(&provider, token1, Address::ZERO, amount_in).await.is_err());
+    }
+}
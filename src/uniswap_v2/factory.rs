@@ -0,0 +1,65 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::multicall::multicall;
+use eyre::{eyre, Result};
+
+sol! {
+    #[sol(rpc)]
+    interface IPairFactory {
+        function getPair(address tokenA, address tokenB) external view returns (address pair);
+
+        function allPairs(uint256 index) external view returns (address pair);
+
+        function allPairsLength() external view returns (uint256);
+    }
+}
+
+/// Address of the pair of `pair` created by the factory, errors if there is none
+pub async fn get_pair_address(
+    provider: &RootProvider<Http<Client>>,
+    factory_address: Address,
+    pair: (Address, Address),
+    block: BlockId
+) -> Result<Address> {
+    let factory = IPairFactory::new(factory_address, provider);
+
+    let pair_address = factory.getPair(pair.0, pair.1).block(block).call().await?.pair;
+    if pair_address != Address::ZERO {Ok(pair_address)} else {Err(eyre!("Pair not found for tokens: {:?}", pair))}
+}
+
+/// Number of pairs created by the factory
+pub async fn all_pairs_length(
+    provider: &RootProvider<Http<Client>>,
+    factory_address: Address,
+    block: BlockId
+) -> Result<u64> {
+    let factory = IPairFactory::new(factory_address, provider);
+    Ok(u64::try_from(factory.allPairsLength().block(block).call().await?._0)?)
+}
+
+/// Addresses of the pairs created by the factory with index in `[from_index, to_index)`, in creation order
+pub async fn all_pairs(
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    factory_address: Address,
+    from_index: u64,
+    to_index: u64,
+    block: BlockId
+) -> Result<Vec<Address>> {
+    let call_data: Vec<Vec<u8>> = (from_index..to_index)
+        .map(|index| IPairFactory::allPairsCall{index: U256::from(index)}.abi_encode())
+        .collect();
+
+    let mut pairs = Vec::new();
+    for data in multicall(provider, multicall_address, factory_address, false, call_data, block).await? {
+        pairs.push(IPairFactory::allPairsCall::abi_decode_returns(&data.returnData, true)?.pair);
+    }
+
+    Ok(pairs)
+}
@@ -0,0 +1,70 @@
+use alloy::primitives::U256;
+use eyre::{eyre, Result};
+
+// fees are expressed in hundredths of a bip like Uniswap V3, 3000 is the 0.3% of Uniswap V2
+const FEE_DENOMINATOR: u32 = 1000000;
+
+/// @notice Given an input amount of an asset and pair reserves, returns the maximum output amount of the other asset
+/// @dev The fee is configurable for forks, the 997/1000 of Uniswap V2 is a fee of 3000
+/// @param amountIn The amount of the input asset
+/// @param reserveIn The reserve of the input asset
+/// @param reserveOut The reserve of the output asset
+/// @param fee The swap fee in hundredths of a bip
+/// @return amountOut The amount of the output asset received
+pub fn get_amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256, fee: u32) -> Result<U256> {
+    if amount_in.is_zero() {
+        return Err(eyre!("UniswapV2Library: INSUFFICIENT_INPUT_AMOUNT"))
+    }
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(eyre!("UniswapV2Library: INSUFFICIENT_LIQUIDITY"))
+    }
+
+    let amount_in_with_fee = amount_in * U256::from(FEE_DENOMINATOR - fee);
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * U256::from(FEE_DENOMINATOR) + amount_in_with_fee;
+    Ok(numerator / denominator)
+}
+
+/// @notice Given an output amount of an asset and pair reserves, returns a required input amount of the other asset
+/// @param amountOut The amount of the output asset
+/// @param reserveIn The reserve of the input asset
+/// @param reserveOut The reserve of the output asset
+/// @param fee The swap fee in hundredths of a bip
+/// @return amountIn The amount of the input asset required
+pub fn get_amount_in(amount_out: U256, reserve_in: U256, reserve_out: U256, fee: u32) -> Result<U256> {
+    if amount_out.is_zero() {
+        return Err(eyre!("UniswapV2Library: INSUFFICIENT_OUTPUT_AMOUNT"))
+    }
+    if reserve_in.is_zero() || reserve_out.is_zero() || amount_out >= reserve_out {
+        return Err(eyre!("UniswapV2Library: INSUFFICIENT_LIQUIDITY"))
+    }
+
+    let numerator = reserve_in * amount_out * U256::from(FEE_DENOMINATOR);
+    let denominator = (reserve_out - amount_out) * U256::from(FEE_DENOMINATOR - fee);
+    Ok(numerator / denominator + U256::from(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_amount_out_test() {
+        // matches the 997/1000 formula of UniswapV2Library
+        let (reserve_in, reserve_out) = (U256::from(5000000000000000000u128), U256::from(10000000000000000000u128));
+        let amount_in = U256::from(1000000000000000000u128);
+        let expected = amount_in * U256::from(997) * reserve_out / (reserve_in * U256::from(1000) + amount_in * U256::from(997));
+        assert_eq!(get_amount_out(amount_in, reserve_in, reserve_out, 3000).unwrap(), expected);
+        assert_eq!(expected, U256::from(1662497915624478906u128));
+
+        // the output is rounded down, so the input it takes is at most the original input and only a few wei below it
+        let amount_in_back = get_amount_in(expected, reserve_in, reserve_out, 3000).unwrap();
+        assert!(amount_in_back <= amount_in && amount_in - amount_in_back < U256::from(10));
+
+        // a lower fork fee pays out more
+        assert!(get_amount_out(amount_in, reserve_in, reserve_out, 2500).unwrap() > expected);
+
+        assert!(get_amount_out(U256::ZERO, reserve_in, reserve_out, 3000).is_err());
+        assert!(get_amount_in(reserve_out, reserve_in, reserve_out, 3000).is_err());
+    }
+}
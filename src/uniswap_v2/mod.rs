@@ -0,0 +1,5 @@
+pub mod math;
+pub mod utils;
+pub mod pair;
+pub mod factory;
+pub mod amm;
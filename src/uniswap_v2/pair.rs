@@ -0,0 +1,158 @@
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::RootProvider,
+    rpc::types::eth::{BlockId, Log},
+    sol,
+    sol_types::{SolCall, SolEvent},
+    transports::http::{Client, Http}
+};
+use crate::{
+    logs::SyncPool,
+    uniswap_v3::{multicall::multicall_targets, pool::{load_tokens, Token}}
+};
+use super::math;
+use eyre::{eyre, Result};
+
+sol! {
+    #[sol(rpc)]
+    interface IPair {
+        event Sync(uint112 reserve0, uint112 reserve1);
+
+        function token0() external view returns (address);
+
+        function token1() external view returns (address);
+
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    }
+}
+
+/// Reserves of a constant product pair
+#[derive(Clone)]
+pub struct PairState {
+    pub pair_address: Address,
+    pub token0: Token,
+    pub token1: Token,
+    pub reserve0: u128,
+    pub reserve1: u128,
+    pub block_timestamp_last: u32,
+    // swap fee in hundredths of a bip, 3000 for Uniswap V2
+    pub fee: u32,
+    pub block: BlockId
+}
+
+impl PairState {
+    pub async fn load_at_block(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pair_address: Address,
+        fee: u32,
+        block: BlockId
+    ) -> Result<Self> {
+        let mut pairs = load_pairs(provider, multicall_address, &[pair_address], fee, block).await?;
+        pairs.pop().ok_or(eyre!("Pair {} not loaded", pair_address))
+    }
+
+    /// Amount of the other token received for exactly `amount_in` of token0 (`zero_for_one`) or token1
+    pub fn get_amount_out(&self, zero_for_one: bool, amount_in: U256) -> Result<U256> {
+        let (reserve_in, reserve_out) = self.reserves(zero_for_one);
+        math::get_amount_out(amount_in, reserve_in, reserve_out, self.fee)
+    }
+
+    /// Amount of token0 (`zero_for_one`) or token1 needed to receive exactly `amount_out` of the other token
+    pub fn get_amount_in(&self, zero_for_one: bool, amount_out: U256) -> Result<U256> {
+        let (reserve_in, reserve_out) = self.reserves(zero_for_one);
+        math::get_amount_in(amount_out, reserve_in, reserve_out, self.fee)
+    }
+
+    /// Simulates a `swap` paid with exactly `amount_in` and returns the amount received
+    pub fn swap(&mut self, zero_for_one: bool, amount_in: U256) -> Result<U256> {
+        let amount_out = self.get_amount_out(zero_for_one, amount_in)?;
+        let (reserve_in, reserve_out) = self.reserves(zero_for_one);
+        let (reserve_in, reserve_out) = (u128::try_from(reserve_in + amount_in)?, u128::try_from(reserve_out - amount_out)?);
+
+        if zero_for_one {
+            (self.reserve0, self.reserve1) = (reserve_in, reserve_out);
+        } else {
+            (self.reserve1, self.reserve0) = (reserve_in, reserve_out);
+        }
+        Ok(amount_out)
+    }
+
+    fn reserves(&self, zero_for_one: bool) -> (U256, U256) {
+        if zero_for_one {
+            (U256::from(self.reserve0), U256::from(self.reserve1))
+        } else {
+            (U256::from(self.reserve1), U256::from(self.reserve0))
+        }
+    }
+}
+
+/// Loads reserves and tokens of many pairs with a couple of multicalls, every pair charging `fee`
+pub async fn load_pairs(
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    pair_addresses: &[Address],
+    fee: u32,
+    block: BlockId
+) -> Result<Vec<PairState>> {
+    let call_data: Vec<(Address, Vec<u8>)> = pair_addresses
+        .iter()
+        .flat_map(|&pair_address| [
+            (pair_address, IPair::token0Call{}.abi_encode()),
+            (pair_address, IPair::token1Call{}.abi_encode()),
+            (pair_address, IPair::getReservesCall{}.abi_encode())
+        ])
+        .collect();
+    let return_data = multicall_targets(provider, multicall_address, false, call_data, block).await?;
+
+    let mut pair_data = Vec::new();
+    for data in return_data.chunks(3) {
+        let token0 = IPair::token0Call::abi_decode_returns(&data[0].returnData, true)?._0;
+        let token1 = IPair::token1Call::abi_decode_returns(&data[1].returnData, true)?._0;
+        let reserves = IPair::getReservesCall::abi_decode_returns(&data[2].returnData, true)?;
+        pair_data.push((token0, token1, reserves));
+    }
+
+    let tokens = load_tokens(provider, multicall_address, pair_data.iter().flat_map(|(token0, token1, _)| [*token0, *token1]).collect(), block).await?;
+
+    Ok(pair_addresses
+        .iter()
+        .zip(pair_data)
+        .map(|(&pair_address, (token0, token1, reserves))| PairState {
+            pair_address,
+            token0: tokens[&token0].clone(),
+            token1: tokens[&token1].clone(),
+            reserve0: reserves.reserve0,
+            reserve1: reserves.reserve1,
+            block_timestamp_last: reserves.blockTimestampLast,
+            fee,
+            block
+        })
+        .collect())
+}
+
+impl SyncPool for PairState {
+    const SYNC_SIGNATURE: B256 = IPair::Sync::SIGNATURE_HASH;
+
+    fn pool_address(&self) -> Address {
+        self.pair_address
+    }
+
+    fn block(&self) -> BlockId {
+        self.block
+    }
+
+    fn set_block(&mut self, block: BlockId) {
+        self.block = block;
+    }
+
+    fn apply_log(&mut self, log: &Log) -> Result<()> {
+        if log.inner.address != self.pair_address || log.inner.topics().first() != Some(&IPair::Sync::SIGNATURE_HASH) {
+            return Ok(())
+        }
+
+        let sync = log.log_decode::<IPair::Sync>()?.inner.data;
+        (self.reserve0, self.reserve1) = (sync.reserve0, sync.reserve1);
+        Ok(())
+    }
+}
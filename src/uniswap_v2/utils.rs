@@ -0,0 +1,6 @@
+use alloy::primitives::{address, Address};
+
+pub const UNISWAP_V2_FACTORY_ADDRESS: Address = address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f");
+
+// 0.3% in hundredths of a bip
+pub const UNISWAP_V2_FEE: u32 = 3000;
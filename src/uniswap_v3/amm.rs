@@ -0,0 +1,153 @@
+use alloy::{
+    primitives::{Address, I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    transports::http::{Client, Http}
+};
+use crate::amm::{resolve_block_number, Amm, LoadAmm};
+use super::{
+    backfill::backfill,
+    math::{safe_cast, tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO}},
+    pool::{LoadingPattern, PoolState},
+    swap,
+    utils::sqrt_price_x96_to_price
+};
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+impl PoolState {
+    fn zero_for_one(&self, token_in: Address, token_out: Address) -> Result<bool> {
+        if token_in == self.token0.address && token_out == self.token1.address {
+            Ok(true)
+        } else if token_in == self.token1.address && token_out == self.token0.address {
+            Ok(false)
+        } else {
+            Err(eyre!("Pool {} does not trade {} for {}", self.pool_address, token_in, token_out))
+        }
+    }
+
+    async fn swap_exact(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        zero_for_one: bool,
+        amount_specified: I256
+    ) -> Result<(U256, U256)> {
+        let sqrt_price_limit_x96 = if zero_for_one {MIN_SQRT_RATIO + U256::from(1)} else {MAX_SQRT_RATIO - U256::from(1)};
+        let (amount0, amount1) = swap::swap(provider, self, zero_for_one, amount_specified, sqrt_price_limit_x96).await?;
+
+        let (amount_in, amount_out) = if zero_for_one {(amount0, amount1)} else {(amount1, amount0)};
+        Ok((amount_in.unsigned_abs(), amount_out.unsigned_abs()))
+    }
+}
+
+impl Amm for PoolState {
+    fn address(&self) -> Address {
+        self.pool_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.token0.address, self.token1.address]
+    }
+
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let price = sqrt_price_x96_to_price(self.slot0.sqrt_price_x96, self.token0.decimals, self.token1.decimals);
+        if self.zero_for_one(base, quote)? {Ok(price)} else {Ok(1.0 / price)}
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (swapped_in, amount_out) = self.clone().swap_exact(provider, zero_for_one, safe_cast::to_int256(amount_in)?).await?;
+            if swapped_in != amount_in {
+                return Err(eyre!("Not enough liquidity to swap {} of {}", amount_in, token_in))
+            }
+            Ok(amount_out)
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (amount_in, swapped_out) = self.clone().swap_exact(provider, zero_for_one, -safe_cast::to_int256(amount_out)?).await?;
+            if swapped_out != amount_out {
+                return Err(eyre!("Not enough liquidity to receive {} of {}", amount_out, token_out))
+            }
+            Ok(amount_in)
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (_, amount_out) = self.swap_exact(provider, zero_for_one, safe_cast::to_int256(amount_in)?).await?;
+            Ok(amount_out)
+        })
+    }
+
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            backfill(provider, self, to_block, None).await?;
+            Ok(())
+        })
+    }
+}
+
+impl LoadAmm for PoolState {
+    async fn load(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        address: Address,
+        block: BlockId
+    ) -> Result<Self> {
+        let block = resolve_block_number(provider, block).await?;
+        PoolState::load_from_address_at_block(provider, multicall_address, address, LoadingPattern::MID, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::offline_provider, uniswap_v3::position::tests::pool_state_with_liquidity};
+    use super::*;
+
+    #[tokio::test]
+    async fn v3_amm_test() {
+        let provider = offline_provider();
+        let mut pool_state = pool_state_with_liquidity().await;
+        let (token0, token1) = (pool_state.token0.address, pool_state.token1.address);
+
+        assert_eq!(pool_state.tokens(), vec![token0, token1]);
+        assert_eq!(pool_state.spot_price(token1, token0).unwrap(), 1.0);
+        assert!(pool_state.spot_price(token0, Address::ZERO).is_err());
+
+        let amount_in = U256::from(1000000000000000000u128);
+        let quoted_out = pool_state.quote_exact_input(&provider, token0, token1, amount_in).await.unwrap();
+        assert_eq!(pool_state.quote_exact_output(&provider, token0, token1, quoted_out).await.unwrap(), amount_in);
+        assert_eq!(pool_state.slot0.tick, 0);
+
+        // applying the swap gives the quoted amount and moves the price
+        assert_eq!(pool_state.swap_exact_input(&provider, token0, token1, amount_in).await.unwrap(), quoted_out);
+        assert!(pool_state.spot_price(token0, token1).unwrap() < 1.0);
+    }
+}
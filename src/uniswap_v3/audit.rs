@@ -0,0 +1,280 @@
+use alloy::{
+    primitives::{Address, I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::{BlockId, Log},
+    sol_types::SolEvent,
+    transports::http::{Client, Http}
+};
+use crate::chains::ChainDeployment;
+use super::{
+    backfill::{apply_log, get_pool_logs},
+    math::tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    pool::{IPool, LoadingPattern, PoolState},
+    swap
+};
+use eyre::{eyre, Result};
+use polars::prelude::*;
+
+/// How the pool state preceding every audited swap is obtained
+pub enum AuditMode {
+    // reload the pool at the previous block for every block containing swaps, replaying earlier logs of the same block
+    Reload,
+    // load the pool once before the range and replay every log forward
+    Replay
+}
+
+pub struct AuditReport {
+    pub swaps: usize,
+    pub mismatches: usize,
+    // swaps only reproduced as exact output, see `AuditStatus::ExactOutput`
+    pub exact_output: usize,
+    // one row per swap, `matches` is false when any simulated value differs from the event
+    pub df: DataFrame
+}
+
+/// Outcome of replaying a single swap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditStatus {
+    // reproduced as an exact input swap of the amount the pool received
+    Match,
+    // reproduced as an exact output swap of the amount the pool paid out, the log does not say which kind was
+    // submitted and the exact input replay rounds the fee differently, which moves the price by more than a wei
+    ExactOutput,
+    Mismatch,
+    // the simulation failed
+    Error
+}
+
+impl AuditStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditStatus::Match => "match",
+            AuditStatus::ExactOutput => "exact-output, ambiguous",
+            AuditStatus::Mismatch => "mismatch",
+            AuditStatus::Error => "error"
+        }
+    }
+}
+
+#[derive(Default)]
+struct AuditRows {
+    block: Vec<u64>,
+    log_index: Vec<u64>,
+    transaction_hash: Vec<String>,
+    zero_for_one: Vec<bool>,
+    amount0_expected: Vec<String>,
+    amount0_simulated: Vec<String>,
+    amount1_expected: Vec<String>,
+    amount1_simulated: Vec<String>,
+    sqrt_price_x96_expected: Vec<String>,
+    sqrt_price_x96_simulated: Vec<String>,
+    tick_expected: Vec<i32>,
+    tick_simulated: Vec<Option<i32>>,
+    lp_fee: Vec<String>,
+    protocol_fee: Vec<String>,
+    matches: Vec<bool>,
+    status: Vec<&'static str>,
+    error: Vec<String>
+}
+
+impl AuditRows {
+    fn to_df(&self) -> Result<DataFrame> {
+        let series_vector = vec![
+            Series::new("block", &self.block),
+            Series::new("log_index", &self.log_index),
+            Series::new("transaction_hash", &self.transaction_hash),
+            Series::new("zero_for_one", &self.zero_for_one),
+            Series::new("amount0_expected", &self.amount0_expected),
+            Series::new("amount0_simulated", &self.amount0_simulated),
+            Series::new("amount1_expected", &self.amount1_expected),
+            Series::new("amount1_simulated", &self.amount1_simulated),
+            Series::new("sqrt_price_x96_expected", &self.sqrt_price_x96_expected),
+            Series::new("sqrt_price_x96_simulated", &self.sqrt_price_x96_simulated),
+            Series::new("tick_expected", &self.tick_expected),
+            Series::new("tick_simulated", &self.tick_simulated),
+            Series::new("lp_fee", &self.lp_fee),
+            Series::new("protocol_fee", &self.protocol_fee),
+            Series::new("matches", &self.matches),
+            Series::new("status", &self.status),
+            Series::new("error", &self.error)
+        ];
+
+        Ok(DataFrame::new(series_vector)?)
+    }
+}
+
+/// Re-runs every historical `Swap` of the pool of the chain's deployment in `[from_block, to_block]` through `swap::swap`
+/// as an exact input swap of the recorded input amount and compares the simulated amounts, sqrt price and tick with the
+/// values emitted by the pool.
+/// Swaps that don't match are re-run as exact output of the recorded output amount, since the original kind isn't
+/// logged, and are reported as `AuditStatus::ExactOutput` instead of mismatches when that reproduces the event.
+pub async fn audit_swaps(
+    provider: &RootProvider<Http<Client>>,
+    deployment: &ChainDeployment,
+    pair: (Address, Address),
+    fee: u32,
+    from_block: u64,
+    to_block: u64,
+    mode: AuditMode
+) -> Result<AuditReport> {
+    if from_block == 0 || from_block > to_block {
+        return Err(eyre!("Invalid block range {} - {}", from_block, to_block))
+    }
+
+    let mut pool_state = PoolState::load_at_block(provider, deployment.multicall, deployment.factory, pair, fee, LoadingPattern::MID, BlockId::number(from_block - 1)).await?;
+    let logs = get_pool_logs(provider, &pool_state, from_block, to_block).await?;
+
+    let mut rows = AuditRows::default();
+
+    for block_logs in logs.chunk_by(|a, b| a.block_number == b.block_number) {
+        let block = block_logs[0].block_number.ok_or(eyre!("Log is missing block number"))?;
+
+        if let AuditMode::Reload = mode {
+            pool_state = PoolState::load_at_block(provider, deployment.multicall, deployment.factory, pair, fee, LoadingPattern::MID, BlockId::number(block - 1)).await?;
+        } else {
+            pool_state.block = BlockId::number(block - 1);
+        }
+
+        for log in block_logs {
+            if log.inner.topics().first() == Some(&IPool::Swap::SIGNATURE_HASH) {
+                audit_swap(provider, &mut pool_state, log, block, &mut rows).await?;
+            }
+            apply_log(provider, &mut pool_state, log).await?;
+        }
+    }
+
+    let swaps = rows.block.len();
+    let count = |status: AuditStatus| rows.status.iter().filter(|row| **row == status.as_str()).count();
+    let mismatches = count(AuditStatus::Mismatch) + count(AuditStatus::Error);
+    let exact_output = count(AuditStatus::ExactOutput);
+
+    Ok(AuditReport{swaps, mismatches, exact_output, df: rows.to_df()?})
+}
+
+async fn audit_swap(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &mut PoolState,
+    log: &Log,
+    block: u64,
+    rows: &mut AuditRows
+) -> Result<()> {
+    let event = log.log_decode::<IPool::Swap>()?.inner.data;
+
+    // the positive side of the event is the amount the pool received
+    let zero_for_one = event.amount0 > I256::ZERO;
+    let (amount_in, amount_out) = if zero_for_one {(event.amount0, event.amount1)} else {(event.amount1, event.amount0)};
+    let sqrt_price_limit_x96 = if zero_for_one {MIN_SQRT_RATIO + U256::from(1)} else {MAX_SQRT_RATIO - U256::from(1)};
+    let reproduces = |state: &PoolState, amounts: (I256, I256)| amounts == (event.amount0, event.amount1)
+        && state.slot0.sqrt_price_x96 == event.sqrtPriceX96
+        && state.slot0.tick == event.tick;
+
+    let before = pool_state.clone();
+    let mut simulated = swap::swap_with_fees(provider, pool_state, zero_for_one, amount_in, sqrt_price_limit_x96).await
+        .map(|(amounts, fees)| {
+            let status = if reproduces(pool_state, amounts) {AuditStatus::Match} else {AuditStatus::Mismatch};
+            (amounts, fees, status)
+        });
+
+    if matches!(simulated, Ok((_, _, AuditStatus::Mismatch))) {
+        let mut exact_output_state = before;
+        if let Ok((amounts, fees)) = swap::swap_with_fees(provider, &mut exact_output_state, zero_for_one, amount_out, sqrt_price_limit_x96).await {
+            if reproduces(&exact_output_state, amounts) {
+                *pool_state = exact_output_state;
+                simulated = Ok((amounts, fees, AuditStatus::ExactOutput));
+            }
+        }
+    }
+
+    rows.block.push(block);
+    rows.log_index.push(log.log_index.unwrap_or_default());
+    rows.transaction_hash.push(log.transaction_hash.map(|hash| hash.to_string()).unwrap_or_default());
+    rows.zero_for_one.push(zero_for_one);
+    rows.amount0_expected.push(event.amount0.to_string());
+    rows.amount1_expected.push(event.amount1.to_string());
+    rows.sqrt_price_x96_expected.push(event.sqrtPriceX96.to_string());
+    rows.tick_expected.push(event.tick);
+
+    match simulated {
+        Ok(((amount0, amount1), fees, status)) => {
+            rows.amount0_simulated.push(amount0.to_string());
+            rows.amount1_simulated.push(amount1.to_string());
+            rows.sqrt_price_x96_simulated.push(pool_state.slot0.sqrt_price_x96.to_string());
+            rows.tick_simulated.push(Some(pool_state.slot0.tick));
+            rows.lp_fee.push(fees.lp_fee.to_string());
+            rows.protocol_fee.push(fees.protocol_fee.to_string());
+            rows.matches.push(status == AuditStatus::Match);
+            rows.status.push(status.as_str());
+            rows.error.push(String::new());
+        },
+        Err(err) => {
+            rows.amount0_simulated.push(String::new());
+            rows.amount1_simulated.push(String::new());
+            rows.sqrt_price_x96_simulated.push(String::new());
+            rows.tick_simulated.push(None);
+            rows.lp_fee.push(String::new());
+            rows.protocol_fee.push(String::new());
+            rows.matches.push(false);
+            rows.status.push(AuditStatus::Error.as_str());
+            rows.error.push(err.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::{log, offline_provider}, uniswap_v3::position::tests::pool_state_with_liquidity};
+    use super::*;
+
+    // Swap log of a swap simulated on the shared test pool, with `amount1_offset` added to the logged amount1
+    async fn simulated_swap_log(zero_for_one: bool, amount_specified: i128, amount1_offset: i128) -> Log {
+        let mut pool_state = pool_state_with_liquidity().await;
+        let sqrt_price_limit_x96 = if zero_for_one {MIN_SQRT_RATIO + U256::from(1)} else {MAX_SQRT_RATIO - U256::from(1)};
+        let amount_specified = I256::try_from(amount_specified).unwrap();
+        let ((amount0, amount1), _) = swap::swap_with_fees(&offline_provider(), &mut pool_state, zero_for_one, amount_specified, sqrt_price_limit_x96).await.unwrap();
+
+        let swap = IPool::Swap {
+            sender: Address::ZERO,
+            recipient: Address::ZERO,
+            amount0,
+            amount1: amount1 + I256::try_from(amount1_offset).unwrap(),
+            sqrtPriceX96: pool_state.slot0.sqrt_price_x96,
+            liquidity: pool_state.liquidity,
+            tick: pool_state.slot0.tick
+        };
+        log(Address::ZERO, 10, 0, 1000, &swap)
+    }
+
+    async fn audit(log: &Log) -> (AuditStatus, PoolState) {
+        let mut pool_state = pool_state_with_liquidity().await;
+        let mut rows = AuditRows::default();
+        audit_swap(&offline_provider(), &mut pool_state, log, 10, &mut rows).await.unwrap();
+
+        assert_eq!(rows.block, vec![10]);
+        let status = [AuditStatus::Match, AuditStatus::ExactOutput, AuditStatus::Mismatch, AuditStatus::Error].into_iter()
+            .find(|status| status.as_str() == rows.status[0])
+            .unwrap();
+        assert_eq!(rows.matches[0], status == AuditStatus::Match);
+        (status, pool_state)
+    }
+
+    #[tokio::test]
+    async fn audit_swap_test() {
+        let (status, pool_state) = audit(&simulated_swap_log(true, 1000000000000000000, 0).await).await;
+        assert_eq!(status, AuditStatus::Match);
+        assert_eq!(pool_state.slot0.tick, -20);
+
+        assert_eq!(audit(&simulated_swap_log(true, 1000000000000000000, 1).await).await.0, AuditStatus::Mismatch);
+        assert_eq!(audit(&simulated_swap_log(false, 1000000000000000000, -1).await).await.0, AuditStatus::Mismatch);
+
+        // replaying these as exact input charges the fee on a rounded amount and lands on another price
+        for (zero_for_one, amount_out) in [(true, 500000000000000000), (false, 333333333333333333)] {
+            let log = simulated_swap_log(zero_for_one, -amount_out, 0).await;
+            let (status, pool_state) = audit(&log).await;
+            assert_eq!(status, AuditStatus::ExactOutput);
+            // the pool is left as the exact output swap left it
+            assert_eq!(pool_state.slot0.sqrt_price_x96, log.log_decode::<IPool::Swap>().unwrap().inner.data.sqrtPriceX96);
+        }
+    }
+}
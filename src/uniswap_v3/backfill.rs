@@ -0,0 +1,474 @@
+use alloy::{
+    primitives::{Address, I256, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::eth::{BlockId, BlockNumberOrTag, Filter, Log},
+    sol_types::SolEvent,
+    transports::http::{Client, Http}
+};
+use crate::logs::{sort_logs, LOG_CHUNK_SIZE};
+use super::{
+    math::full_math,
+    pool::{IPool, LoadingPattern, PoolState},
+    swap::SwapFees,
+    utils::sqrt_price_x96_to_price
+};
+use eyre::{eyre, Result};
+use polars::{prelude::*, io::prelude::CsvWriter};
+use std::{fs::{self, OpenOptions}, path::Path};
+
+// Column buffers for the pool time series, one row per block with pool activity
+#[derive(Default)]
+struct TimeSeries {
+    block: Vec<u64>,
+    timestamp: Vec<u64>,
+    sqrt_price_x96: Vec<String>,
+    price: Vec<f64>,
+    liquidity: Vec<String>,
+    tick: Vec<i32>,
+    volume0: Vec<String>,
+    volume1: Vec<String>,
+    fees0: Vec<String>,
+    fees1: Vec<String>,
+    protocol_fees0: Vec<String>,
+    protocol_fees1: Vec<String>
+}
+
+impl TimeSeries {
+    fn is_empty(&self) -> bool {
+        self.block.is_empty()
+    }
+
+    fn extend(&mut self, other: TimeSeries) {
+        self.block.extend(other.block);
+        self.timestamp.extend(other.timestamp);
+        self.sqrt_price_x96.extend(other.sqrt_price_x96);
+        self.price.extend(other.price);
+        self.liquidity.extend(other.liquidity);
+        self.tick.extend(other.tick);
+        self.volume0.extend(other.volume0);
+        self.volume1.extend(other.volume1);
+        self.fees0.extend(other.fees0);
+        self.fees1.extend(other.fees1);
+        self.protocol_fees0.extend(other.protocol_fees0);
+        self.protocol_fees1.extend(other.protocol_fees1);
+    }
+
+    fn to_df(&self) -> Result<DataFrame> {
+        let series_vector = vec![
+            Series::new("block", &self.block),
+            Series::new("timestamp", &self.timestamp),
+            Series::new("sqrt_price_x96", &self.sqrt_price_x96),
+            Series::new("price", &self.price),
+            Series::new("liquidity", &self.liquidity),
+            Series::new("tick", &self.tick),
+            Series::new("volume0", &self.volume0),
+            Series::new("volume1", &self.volume1),
+            Series::new("fees0", &self.fees0),
+            Series::new("fees1", &self.fees1),
+            Series::new("protocol_fees0", &self.protocol_fees0),
+            Series::new("protocol_fees1", &self.protocol_fees1)
+        ];
+
+        Ok(DataFrame::new(series_vector)?)
+    }
+}
+
+// Volume and fees accumulated over the swaps of a single block
+#[derive(Default)]
+struct BlockActivity {
+    volume0: U256,
+    volume1: U256,
+    fees0: U256,
+    fees1: U256,
+    protocol_fees0: U256,
+    protocol_fees1: U256
+}
+
+/// Last block written to a backfill checkpoint file
+pub struct BackfillCheckpoint {
+    pub block: u64
+}
+
+impl BackfillCheckpoint {
+    /// Reads the checkpoint from the csv written by `backfill`, returns None if nothing was written yet
+    pub fn read(path: &str) -> Result<Option<Self>> {
+        if !Path::new(path).exists() {
+            return Ok(None)
+        }
+
+        let contents = fs::read_to_string(path)?;
+        match contents.lines().skip(1).filter(|line| !line.is_empty()).last() {
+            Some(line) => {
+                let block = line.split(',').next().ok_or(eyre!("Malformed checkpoint row"))?.parse::<u64>()?;
+                Ok(Some(BackfillCheckpoint{block}))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// Loads the pool state pinned at the checkpoint block so `backfill` can continue from the next block
+    pub async fn load_pool_state(
+        &self,
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_factory_address: Address,
+        pair: (Address, Address),
+        fee: u32,
+        loading_pattern: LoadingPattern
+    ) -> Result<PoolState> {
+        PoolState::load_at_block(provider, multicall_address, pool_factory_address, pair, fee, loading_pattern, BlockId::number(self.block)).await
+    }
+}
+
+/// Replays `Swap`, `Mint` and `Burn` logs block by block on top of a block-pinned pool state up to `to_block` (inclusive)
+/// and returns the resulting time series. When a checkpoint path is given, rows are appended to that csv after every log chunk
+/// so an interrupted run can be resumed with `BackfillCheckpoint`.
+/// Fee growth globals are left untouched, they can not be recovered exactly from the emitted logs.
+pub async fn backfill(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &mut PoolState,
+    to_block: u64,
+    checkpoint_path: Option<&str>
+) -> Result<DataFrame> {
+    let start_block = match pool_state.block {
+        BlockId::Number(BlockNumberOrTag::Number(block)) => block,
+        _ => return Err(eyre!("Pool state must be pinned to a block number for backfill"))
+    };
+
+    let mut series = TimeSeries::default();
+    let mut from_block = start_block + 1;
+
+    while from_block <= to_block {
+        let chunk_end = std::cmp::min(from_block + LOG_CHUNK_SIZE - 1, to_block);
+
+        let logs = get_pool_logs(provider, pool_state, from_block, chunk_end).await?;
+        let chunk_series = replay_logs(provider, pool_state, &logs).await?;
+
+        pool_state.block = BlockId::number(chunk_end);
+
+        if let Some(path) = checkpoint_path {
+            if !chunk_series.is_empty() {
+                append_to_checkpoint(path, &mut chunk_series.to_df()?)?;
+            }
+        }
+
+        series.extend(chunk_series);
+        from_block = chunk_end + 1;
+    }
+
+    series.to_df()
+}
+
+pub async fn get_pool_logs(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &PoolState,
+    from_block: u64,
+    to_block: u64
+) -> Result<Vec<Log>> {
+    let filter = Filter::new()
+        .address(pool_state.pool_address)
+        .from_block(from_block)
+        .to_block(to_block)
+        .event_signature(vec![
+            IPool::Swap::SIGNATURE_HASH,
+            IPool::Mint::SIGNATURE_HASH,
+            IPool::Burn::SIGNATURE_HASH
+        ]);
+
+    let mut logs = provider.get_logs(&filter).await?;
+    sort_logs(&mut logs);
+
+    Ok(logs)
+}
+
+async fn replay_logs(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &mut PoolState,
+    logs: &[Log]
+) -> Result<TimeSeries> {
+    let mut series = TimeSeries::default();
+
+    for block_logs in logs.chunk_by(|a, b| a.block_number == b.block_number) {
+        let block = block_logs[0].block_number.ok_or(eyre!("Log is missing block number"))?;
+        let mut activity = BlockActivity::default();
+        let timestamp = log_timestamp(provider, &block_logs[0]).await?;
+        pool_state.block_timestamp = timestamp as u32;
+
+        for log in block_logs {
+            record_activity(pool_state, log, &mut activity)?;
+            apply_log(provider, pool_state, log).await?;
+        }
+
+        series.block.push(block);
+        series.timestamp.push(timestamp);
+        series.sqrt_price_x96.push(pool_state.slot0.sqrt_price_x96.to_string());
+        series.price.push(sqrt_price_x96_to_price(pool_state.slot0.sqrt_price_x96, pool_state.token0.decimals, pool_state.token1.decimals));
+        series.liquidity.push(pool_state.liquidity.to_string());
+        series.tick.push(pool_state.slot0.tick);
+        series.volume0.push(activity.volume0.to_string());
+        series.volume1.push(activity.volume1.to_string());
+        series.fees0.push(activity.fees0.to_string());
+        series.fees1.push(activity.fees1.to_string());
+        series.protocol_fees0.push(activity.protocol_fees0.to_string());
+        series.protocol_fees1.push(activity.protocol_fees1.to_string());
+    }
+
+    Ok(series)
+}
+
+/// Timestamp of the block a log was emitted in, fetched from the block header when the node does not include it in the log
+pub async fn log_timestamp(
+    provider: &RootProvider<Http<Client>>,
+    log: &Log
+) -> Result<u64> {
+    if let Some(timestamp) = log.block_timestamp {
+        return Ok(timestamp)
+    }
+
+    let block = log.block_number.ok_or(eyre!("Log is missing block number"))?;
+    Ok(provider
+        .get_block_by_number(BlockNumberOrTag::Number(block), false)
+        .await?
+        .ok_or(eyre!("Block {} not found", block))?
+        .header
+        .timestamp)
+}
+
+/// Applies a decoded `Swap`, `Mint` or `Burn` log to the pool state, other logs are ignored
+pub async fn apply_log(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &mut PoolState,
+    log: &Log
+) -> Result<()> {
+    match log.inner.topics().first() {
+        Some(&IPool::Swap::SIGNATURE_HASH) => {
+            let swap = log.log_decode::<IPool::Swap>()?.inner.data;
+            if swap.tick != pool_state.slot0.tick {
+                pool_state.write_observation(pool_state.slot0.tick, pool_state.liquidity);
+            }
+            pool_state.slot0.sqrt_price_x96 = swap.sqrtPriceX96;
+            pool_state.slot0.tick = swap.tick;
+            pool_state.liquidity = swap.liquidity;
+        },
+        Some(&IPool::Mint::SIGNATURE_HASH) => {
+            let mint = log.log_decode::<IPool::Mint>()?.inner.data;
+            pool_state.modify_position(provider, mint.tickLower, mint.tickUpper, i128::try_from(mint.amount)?).await?;
+        },
+        Some(&IPool::Burn::SIGNATURE_HASH) => {
+            let burn = log.log_decode::<IPool::Burn>()?.inner.data;
+            pool_state.modify_position(provider, burn.tickLower, burn.tickUpper, -i128::try_from(burn.amount)?).await?;
+        },
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn record_activity(
+    pool_state: &PoolState,
+    log: &Log,
+    activity: &mut BlockActivity
+) -> Result<()> {
+    if log.inner.topics().first() != Some(&IPool::Swap::SIGNATURE_HASH) {
+        return Ok(())
+    }
+
+    let swap = log.log_decode::<IPool::Swap>()?.inner.data;
+
+    // the fee is taken from the input token only
+    if swap.amount0 > I256::ZERO {
+        let fees = estimate_swap_fees(pool_state, true, swap.amount0.unsigned_abs())?;
+        activity.fees0 += fees.lp_fee;
+        activity.protocol_fees0 += fees.protocol_fee;
+    }
+    if swap.amount1 > I256::ZERO {
+        let fees = estimate_swap_fees(pool_state, false, swap.amount1.unsigned_abs())?;
+        activity.fees1 += fees.lp_fee;
+        activity.protocol_fees1 += fees.protocol_fee;
+    }
+    activity.volume0 += swap.amount0.unsigned_abs();
+    activity.volume1 += swap.amount1.unsigned_abs();
+
+    Ok(())
+}
+
+/// Fee split of a logged swap from its input amount. The pool charges and splits the fee per step so the result can be
+/// off by rounding against `swap::swap_with_fees`.
+pub fn estimate_swap_fees(
+    pool_state: &PoolState,
+    zero_for_one: bool,
+    amount_in: U256
+) -> Result<SwapFees> {
+    let fee = full_math::mul_div_rounding_up(amount_in, U256::from(pool_state.fee), U256::from(1000000))?;
+    let protocol_fee = match pool_state.slot0.protocol_fee_denominator(zero_for_one) {
+        0 => U256::ZERO,
+        fee_protocol => fee / U256::from(fee_protocol)
+    };
+
+    Ok(SwapFees {lp_fee: fee - protocol_fee, protocol_fee})
+}
+
+fn append_to_checkpoint(path: &str, df: &mut DataFrame) -> Result<()> {
+    let include_header = !Path::new(path).exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    CsvWriter::new(&mut file).include_header(include_header).with_separator(b',').finish(df)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_utils::{log, offline_provider},
+        uniswap_v3::{math::{constants::Q96, oracle, tick_math}, position::tests::{pool_state_at_price_one, pool_state_with_liquidity}}
+    };
+    use super::*;
+
+    fn swap_log(block: u64, log_index: u64, timestamp: u64, amounts: (i128, i128), tick: i32, liquidity: u128) -> Log {
+        let swap = IPool::Swap {
+            sender: Address::ZERO,
+            recipient: Address::ZERO,
+            amount0: I256::try_from(amounts.0).unwrap(),
+            amount1: I256::try_from(amounts.1).unwrap(),
+            sqrtPriceX96: tick_math::get_sqrt_ratio_at_tick(tick).unwrap(),
+            liquidity,
+            tick
+        };
+        log(Address::ZERO, block, log_index, timestamp, &swap)
+    }
+
+    #[tokio::test]
+    async fn replay_logs_test() {
+        // ticks and bitmap words of the logged positions are preloaded
+        let provider = offline_provider();
+        let mut pool_state = pool_state_with_liquidity().await;
+        (pool_state.slot0.observation_cardinality, pool_state.slot0.observation_cardinality_next) = oracle::initialize(&mut pool_state.observations, 0);
+        pool_state.increase_observation_cardinality_next(10).unwrap();
+        // a quarter of the fee of token0 inputs and a fifth of token1 inputs goes to the protocol
+        pool_state.slot0.fee_protocol = 4 | (5 << 4);
+
+        let liquidity: u128 = 1000000000000000000000;
+        let mint = IPool::Mint {
+            sender: Address::ZERO,
+            owner: Address::ZERO,
+            tickLower: -600,
+            tickUpper: 600,
+            amount: 1000000000000000000,
+            amount0: U256::ZERO,
+            amount1: U256::ZERO
+        };
+        let burn = IPool::Burn {
+            owner: Address::ZERO,
+            tickLower: -600,
+            tickUpper: 600,
+            amount: 1000000000000000000,
+            amount0: U256::ZERO,
+            amount1: U256::ZERO
+        };
+        // logs without a known signature are skipped
+        let other = Log {block_number: Some(12), block_timestamp: Some(1024), log_index: Some(0), ..Default::default()};
+        let logs = vec![
+            swap_log(10, 0, 1000, (1000000000000000000, -996006981039903216), -1, liquidity),
+            log(Address::ZERO, 10, 1, 1000, &mint),
+            other,
+            log(Address::ZERO, 12, 1, 1024, &burn),
+            swap_log(12, 2, 1024, (-996006981039903216, 1000000000000000000), 0, liquidity)
+        ];
+
+        let series = replay_logs(&provider, &mut pool_state, &logs).await.unwrap();
+        assert_eq!(series.block, vec![10, 12]);
+        assert_eq!(series.timestamp, vec![1000, 1024]);
+        assert_eq!(series.tick, vec![-1, 0]);
+        assert_eq!(series.sqrt_price_x96, vec![tick_math::get_sqrt_ratio_at_tick(-1).unwrap().to_string(), Q96.to_string()]);
+        assert_eq!(series.liquidity, vec!["1001000000000000000000", "1000000000000000000000"]);
+        assert_eq!(series.volume0, vec!["1000000000000000000", "996006981039903216"]);
+        assert_eq!(series.volume1, vec!["996006981039903216", "1000000000000000000"]);
+        // 0.3% of the 1e18 input, split 3 to 1 and 4 to 1 between liquidity providers and the protocol
+        assert_eq!(series.fees0, vec!["2250000000000000", "0"]);
+        assert_eq!(series.protocol_fees0, vec!["750000000000000", "0"]);
+        assert_eq!(series.fees1, vec!["0", "2400000000000000"]);
+        assert_eq!(series.protocol_fees1, vec!["0", "600000000000000"]);
+
+        assert_eq!(pool_state.slot0.sqrt_price_x96, Q96);
+        assert_eq!(pool_state.liquidity, liquidity);
+        assert_eq!(pool_state.ticks[&-600].liquidity_gross, liquidity);
+        // one observation per block, written before the first change of the block: tick 0 until 1000, tick -1 until 1024
+        assert_eq!(pool_state.slot0.observation_index, 2);
+        assert_eq!(pool_state.observations[&1].block_timestamp, 1000);
+        assert_eq!(pool_state.observations[&1].tick_cumulative, 0);
+        assert_eq!(pool_state.observations[&2].block_timestamp, 1024);
+        assert_eq!(pool_state.observations[&2].tick_cumulative, -24);
+    }
+
+    #[tokio::test]
+    async fn apply_swap_log_test() {
+        let provider = offline_provider();
+        let mut pool_state = pool_state_with_liquidity().await;
+        (pool_state.slot0.observation_cardinality, pool_state.slot0.observation_cardinality_next) = oracle::initialize(&mut pool_state.observations, 0);
+        pool_state.block_timestamp = 1000;
+
+        // a swap that stays at the current tick does not write an observation
+        apply_log(&provider, &mut pool_state, &swap_log(10, 0, 1000, (1000, -999), 0, 5)).await.unwrap();
+        assert_eq!((pool_state.slot0.tick, pool_state.liquidity), (0, 5));
+        assert_eq!(pool_state.observations[&0].block_timestamp, 0);
+
+        apply_log(&provider, &mut pool_state, &swap_log(10, 1, 1000, (1000, -999), -120, 7)).await.unwrap();
+        assert_eq!(pool_state.slot0.sqrt_price_x96, tick_math::get_sqrt_ratio_at_tick(-120).unwrap());
+        assert_eq!((pool_state.slot0.tick, pool_state.liquidity), (-120, 7));
+        assert_eq!(pool_state.observations[&0].block_timestamp, 1000);
+    }
+
+    #[test]
+    fn estimate_swap_fees_test() {
+        let mut pool_state = pool_state_at_price_one();
+        let amount_in = U256::from(1000001);
+
+        // the fee rounds up
+        let fees = estimate_swap_fees(&pool_state, true, amount_in).unwrap();
+        assert_eq!((fees.lp_fee, fees.protocol_fee), (U256::from(3001), U256::ZERO));
+
+        pool_state.slot0.fee_protocol = 4 | (6 << 4);
+        let fees = estimate_swap_fees(&pool_state, true, amount_in).unwrap();
+        assert_eq!((fees.lp_fee, fees.protocol_fee), (U256::from(2251), U256::from(750)));
+        let fees = estimate_swap_fees(&pool_state, false, amount_in).unwrap();
+        assert_eq!((fees.lp_fee, fees.protocol_fee), (U256::from(2501), U256::from(500)));
+    }
+
+    #[test]
+    fn checkpoint_test() {
+        let path = std::env::temp_dir().join("backfill_checkpoint_test.csv");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        assert!(BackfillCheckpoint::read(path).unwrap().is_none());
+
+        let chunk = |blocks: Vec<u64>| {
+            let rows = blocks.len();
+            TimeSeries {
+                block: blocks,
+                timestamp: vec![0; rows],
+                sqrt_price_x96: vec![Q96.to_string(); rows],
+                price: vec![1.0; rows],
+                liquidity: vec!["0".to_string(); rows],
+                tick: vec![0; rows],
+                volume0: vec!["0".to_string(); rows],
+                volume1: vec!["0".to_string(); rows],
+                fees0: vec!["0".to_string(); rows],
+                fees1: vec!["0".to_string(); rows],
+                protocol_fees0: vec!["0".to_string(); rows],
+                protocol_fees1: vec!["0".to_string(); rows]
+            }.to_df().unwrap()
+        };
+
+        // a file with the header only has no checkpoint yet
+        append_to_checkpoint(path, &mut chunk(vec![])).unwrap();
+        assert!(BackfillCheckpoint::read(path).unwrap().is_none());
+
+        append_to_checkpoint(path, &mut chunk(vec![10, 12])).unwrap();
+        append_to_checkpoint(path, &mut chunk(vec![15])).unwrap();
+        let contents = fs::read_to_string(path).unwrap();
+        assert_eq!(BackfillCheckpoint::read(path).unwrap().unwrap().block, 15);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(contents.lines().filter(|line| line.starts_with("block,")).count(), 1);
+        assert_eq!(contents.lines().count(), 4);
+    }
+}
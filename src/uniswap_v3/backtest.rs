@@ -0,0 +1,495 @@
+use alloy::{
+    primitives::{I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::{BlockId, BlockNumberOrTag, Log},
+    sol_types::SolEvent,
+    transports::http::{Client, Http}
+};
+use crate::logs::LOG_CHUNK_SIZE;
+use super::{
+    backfill::{apply_log, get_pool_logs, log_timestamp},
+    math::{constants::Q96, liquidity_amounts, safe_cast, tick_math::{self, MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK}},
+    pool::{IPool, PoolState},
+    position::Position,
+    swap,
+    utils::{i256_to_f64, sqrt_price_x96_to_price}
+};
+use eyre::{eyre, Result};
+use polars::prelude::*;
+use std::collections::VecDeque;
+
+// liquidity used to derive the token ratio of a range, large enough to keep the rounding negligible
+const RATIO_LIQUIDITY: u128 = 1 << 96;
+// maximum number of swaps used to reach the token ratio of a new range
+const RATIO_SWAP_ITERATIONS: usize = 4;
+
+/// Decides where the simulated position provides liquidity
+pub trait Strategy {
+    /// Called before the replay starts and after every replayed block with the current range of the position.
+    /// Returns the range the position should be moved to, or None to keep the current one.
+    fn rebalance(&mut self, pool_state: &PoolState, range: Option<(i32, i32)>) -> Option<(i32, i32)>;
+}
+
+/// Range spanning at least `half_width` ticks on each side of `tick`, aligned to the tick spacing
+pub fn centered_range(tick: i32, half_width: i32, tick_spacing: i32) -> (i32, i32) {
+    let min_tick = MIN_TICK / tick_spacing * tick_spacing;
+    let max_tick = MAX_TICK / tick_spacing * tick_spacing;
+
+    let tick_lower = (tick - half_width).div_euclid(tick_spacing) * tick_spacing;
+    let tick_upper = ((tick + half_width).div_euclid(tick_spacing) + 1) * tick_spacing;
+
+    (tick_lower.max(min_tick), tick_upper.min(max_tick))
+}
+
+/// Provides liquidity in a fixed range and never rebalances
+pub struct FixedRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32
+}
+
+impl Strategy for FixedRange {
+    fn rebalance(&mut self, _pool_state: &PoolState, range: Option<(i32, i32)>) -> Option<(i32, i32)> {
+        match range {
+            Some(_) => None,
+            None => Some((self.tick_lower, self.tick_upper))
+        }
+    }
+}
+
+/// Recenters a range of `half_width` ticks around the price once the price comes within `trigger` ticks of either bound,
+/// a trigger of 0 recenters only when the price leaves the range
+pub struct RecenteringBand {
+    pub half_width: i32,
+    pub trigger: i32
+}
+
+impl Strategy for RecenteringBand {
+    fn rebalance(&mut self, pool_state: &PoolState, range: Option<(i32, i32)>) -> Option<(i32, i32)> {
+        let tick = pool_state.slot0.tick;
+        let target = centered_range(tick, self.half_width, pool_state.tick_spacing);
+
+        match range {
+            None => Some(target),
+            Some((tick_lower, tick_upper)) => {
+                if (tick < tick_lower + self.trigger || tick >= tick_upper - self.trigger) && target != (tick_lower, tick_upper) {
+                    Some(target)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Recenters when the price leaves the range, with a width of `multiplier` standard deviations of the tick moves over
+/// the last `window` blocks, scaled to the window length
+pub struct VolatilityScaled {
+    pub window: usize,
+    pub multiplier: f64,
+    pub min_half_width: i32,
+    ticks: VecDeque<i32>
+}
+
+impl VolatilityScaled {
+    pub fn new(window: usize, multiplier: f64, min_half_width: i32) -> Self {
+        VolatilityScaled {window, multiplier, min_half_width, ticks: VecDeque::new()}
+    }
+
+    fn half_width(&self) -> i32 {
+        if self.ticks.len() < 2 {
+            return self.min_half_width
+        }
+
+        let moves: Vec<f64> = self.ticks.iter().zip(self.ticks.iter().skip(1)).map(|(a, b)| (b - a) as f64).collect();
+        let mean = moves.iter().sum::<f64>() / moves.len() as f64;
+        let variance = moves.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / moves.len() as f64;
+        let half_width = self.multiplier * variance.sqrt() * (self.window as f64).sqrt();
+
+        (half_width as i32).max(self.min_half_width)
+    }
+}
+
+impl Strategy for VolatilityScaled {
+    fn rebalance(&mut self, pool_state: &PoolState, range: Option<(i32, i32)>) -> Option<(i32, i32)> {
+        let tick = pool_state.slot0.tick;
+
+        self.ticks.push_back(tick);
+        if self.ticks.len() > self.window + 1 {
+            self.ticks.pop_front();
+        }
+
+        match range {
+            Some((tick_lower, tick_upper)) if tick_lower <= tick && tick < tick_upper => None,
+            _ => Some(centered_range(tick, self.half_width(), pool_state.tick_spacing))
+        }
+    }
+}
+
+pub struct BacktestConfig {
+    // initial token balances of the simulated LP
+    pub amount0: U256,
+    pub amount1: U256,
+    // cost of burning, swapping and minting on a rebalance, in token1
+    pub gas_cost_per_rebalance: f64
+}
+
+// Balances and running costs of the simulated LP
+#[derive(Default)]
+struct LpAccount {
+    position: Option<Position>,
+    balance0: U256,
+    balance1: U256,
+    // values in token1 at the time they were incurred
+    fees_collected: f64,
+    swap_costs: f64,
+    gas_costs: f64,
+    rebalances: u64
+}
+
+// Column buffers for the equity curve, one row per block with pool activity
+#[derive(Default)]
+struct EquityCurve {
+    block: Vec<u64>,
+    timestamp: Vec<u64>,
+    tick: Vec<i32>,
+    price: Vec<f64>,
+    tick_lower: Vec<Option<i32>>,
+    tick_upper: Vec<Option<i32>>,
+    in_range: Vec<bool>,
+    position_value: Vec<f64>,
+    uncollected_fees: Vec<f64>,
+    idle_value: Vec<f64>,
+    fees_collected: Vec<f64>,
+    swap_costs: Vec<f64>,
+    gas_costs: Vec<f64>,
+    equity: Vec<f64>,
+    hodl_value: Vec<f64>,
+    rebalances: Vec<u64>
+}
+
+impl EquityCurve {
+    fn to_df(&self) -> Result<DataFrame> {
+        let series_vector = vec![
+            Series::new("block", &self.block),
+            Series::new("timestamp", &self.timestamp),
+            Series::new("tick", &self.tick),
+            Series::new("price", &self.price),
+            Series::new("tick_lower", &self.tick_lower),
+            Series::new("tick_upper", &self.tick_upper),
+            Series::new("in_range", &self.in_range),
+            Series::new("position_value", &self.position_value),
+            Series::new("uncollected_fees", &self.uncollected_fees),
+            Series::new("idle_value", &self.idle_value),
+            Series::new("fees_collected", &self.fees_collected),
+            Series::new("swap_costs", &self.swap_costs),
+            Series::new("gas_costs", &self.gas_costs),
+            Series::new("equity", &self.equity),
+            Series::new("hodl_value", &self.hodl_value),
+            Series::new("rebalances", &self.rebalances)
+        ];
+
+        Ok(DataFrame::new(series_vector)?)
+    }
+}
+
+/// Replays the pool's historical swaps, mints and burns from a block-pinned pool state up to `to_block` (inclusive) with a
+/// simulated LP position managed by `strategy`, and returns the equity curve of the LP valued in token1.
+/// Historical swaps are replayed as exact input swaps through the swap engine so the simulated position earns its share
+/// of the fees through fee growth, the price path drifts from history in proportion to the size of the position.
+pub async fn backtest<S: Strategy>(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &mut PoolState,
+    to_block: u64,
+    strategy: &mut S,
+    config: &BacktestConfig
+) -> Result<DataFrame> {
+    let start_block = match pool_state.block {
+        BlockId::Number(BlockNumberOrTag::Number(block)) => block,
+        _ => return Err(eyre!("Pool state must be pinned to a block number for backtest"))
+    };
+
+    let mut account = LpAccount {balance0: config.amount0, balance1: config.amount1, ..Default::default()};
+    let mut curve = EquityCurve::default();
+
+    if let Some(range) = strategy.rebalance(pool_state, None) {
+        account.rebalance(provider, pool_state, range, config).await?;
+    }
+
+    let mut from_block = start_block + 1;
+    while from_block <= to_block {
+        let chunk_end = std::cmp::min(from_block + LOG_CHUNK_SIZE - 1, to_block);
+        let logs = get_pool_logs(provider, pool_state, from_block, chunk_end).await?;
+
+        for block_logs in logs.chunk_by(|a, b| a.block_number == b.block_number) {
+            let block = block_logs[0].block_number.ok_or(eyre!("Log is missing block number"))?;
+            let timestamp = log_timestamp(provider, &block_logs[0]).await?;
+            pool_state.block_timestamp = timestamp as u32;
+
+            for log in block_logs {
+                replay_log(provider, pool_state, log).await?;
+            }
+
+            let range = account.position.as_ref().map(|position| (position.tick_lower, position.tick_upper));
+            if let Some(range) = strategy.rebalance(pool_state, range) {
+                account.rebalance(provider, pool_state, range, config).await?;
+            }
+
+            account.record(provider, pool_state, block, timestamp, config, &mut curve).await?;
+        }
+
+        pool_state.block = BlockId::number(chunk_end);
+        from_block = chunk_end + 1;
+    }
+
+    curve.to_df()
+}
+
+async fn replay_log(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &mut PoolState,
+    log: &Log
+) -> Result<()> {
+    if log.inner.topics().first() != Some(&IPool::Swap::SIGNATURE_HASH) {
+        return apply_log(provider, pool_state, log).await
+    }
+
+    let event = log.log_decode::<IPool::Swap>()?.inner.data;
+    let zero_for_one = event.amount0 > I256::ZERO;
+    let amount_specified = if zero_for_one {event.amount0} else {event.amount1};
+    if amount_specified <= I256::ZERO {
+        return Ok(())
+    }
+
+    swap::swap(provider, pool_state, zero_for_one, amount_specified, swap_limit(zero_for_one)).await?;
+    Ok(())
+}
+
+fn swap_limit(zero_for_one: bool) -> U256 {
+    if zero_for_one {MIN_SQRT_RATIO + U256::from(1)} else {MAX_SQRT_RATIO - U256::from(1)}
+}
+
+// token1 per token0 in raw token units
+fn raw_price(sqrt_price_x96: U256) -> f64 {
+    (f64::from(sqrt_price_x96) / f64::from(Q96)).powi(2)
+}
+
+// applies a pool-side token delta (positive when paid to the pool) to a balance
+fn apply_delta(balance: U256, delta: I256) -> Result<U256> {
+    if delta > I256::ZERO {
+        balance.checked_sub(delta.unsigned_abs()).ok_or(eyre!("Insufficient balance"))
+    } else {
+        Ok(balance + delta.unsigned_abs())
+    }
+}
+
+impl LpAccount {
+    // value of raw token amounts in token1
+    fn value(&self, pool_state: &PoolState, amount0: U256, amount1: U256) -> f64 {
+        let price = sqrt_price_x96_to_price(pool_state.slot0.sqrt_price_x96, pool_state.token0.decimals, pool_state.token1.decimals);
+        f64::from(amount0) / 10f64.powi(pool_state.token0.decimals as i32) * price
+            + f64::from(amount1) / 10f64.powi(pool_state.token1.decimals as i32)
+    }
+
+    /// Burns the current position and collects its fees, swaps the balances to the token ratio of the new range through
+    /// the pool and mints the new position
+    async fn rebalance(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        pool_state: &mut PoolState,
+        (tick_lower, tick_upper): (i32, i32),
+        config: &BacktestConfig
+    ) -> Result<()> {
+        if let Some(position) = self.position.take() {
+            let (fee_growth_inside0_x128, fee_growth_inside1_x128) = pool_state.fee_growth_inside(provider, position.tick_lower, position.tick_upper).await?;
+            let (fees0, fees1) = position.fees_owed(fee_growth_inside0_x128, fee_growth_inside1_x128)?;
+            let (amount0, amount1) = if position.liquidity > 0 {
+                pool_state.burn(provider, position.tick_lower, position.tick_upper, position.liquidity).await?
+            } else {
+                (U256::ZERO, U256::ZERO)
+            };
+
+            self.balance0 += amount0 + U256::from(fees0);
+            self.balance1 += amount1 + U256::from(fees1);
+            self.fees_collected += self.value(pool_state, U256::from(fees0), U256::from(fees1));
+        }
+
+        let sqrt_ratio_lower_x96 = tick_math::get_sqrt_ratio_at_tick(tick_lower)?;
+        let sqrt_ratio_upper_x96 = tick_math::get_sqrt_ratio_at_tick(tick_upper)?;
+
+        self.swap_to_range_ratio(provider, pool_state, sqrt_ratio_lower_x96, sqrt_ratio_upper_x96).await?;
+
+        let liquidity = liquidity_amounts::get_liquidity_for_amounts(
+            pool_state.slot0.sqrt_price_x96, sqrt_ratio_lower_x96, sqrt_ratio_upper_x96, self.balance0, self.balance1
+        )?;
+
+        if liquidity > 0 {
+            let (amount0, amount1) = pool_state.mint(provider, tick_lower, tick_upper, liquidity).await?;
+            // amounts are rounded up by the pool, the liquidity is rounded down so at most a wei is missing
+            self.balance0 = self.balance0.saturating_sub(amount0);
+            self.balance1 = self.balance1.saturating_sub(amount1);
+
+            let (fee_growth_inside0_last_x128, fee_growth_inside1_last_x128) = pool_state.fee_growth_inside(provider, tick_lower, tick_upper).await?;
+            self.position = Some(Position {
+                tick_lower,
+                tick_upper,
+                liquidity,
+                fee_growth_inside0_last_x128,
+                fee_growth_inside1_last_x128,
+                ..Default::default()
+            });
+        }
+
+        self.gas_costs += config.gas_cost_per_rebalance;
+        self.rebalances += 1;
+
+        Ok(())
+    }
+
+    /// Swaps the balances through the pool towards the token ratio of a range, the price impact of a swap shifts the ratio
+    /// so the remainder is swapped again a bounded number of times
+    async fn swap_to_range_ratio(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        pool_state: &mut PoolState,
+        sqrt_ratio_lower_x96: U256,
+        sqrt_ratio_upper_x96: U256
+    ) -> Result<()> {
+        for _ in 0..RATIO_SWAP_ITERATIONS {
+            // token ratio of the range at the current price
+            let (ratio0, ratio1) = liquidity_amounts::get_amounts_for_liquidity(
+                pool_state.slot0.sqrt_price_x96, sqrt_ratio_lower_x96, sqrt_ratio_upper_x96, RATIO_LIQUIDITY
+            )?;
+            let (ratio0, ratio1) = (f64::from(ratio0), f64::from(ratio1));
+
+            let price = raw_price(pool_state.slot0.sqrt_price_x96);
+            let value0 = f64::from(self.balance0) * price;
+            let total_value = value0 + f64::from(self.balance1);
+            let target_value0 = total_value * ratio0 * price / (ratio0 * price + ratio1);
+
+            let (zero_for_one, amount_in) = if value0 > target_value0 {
+                (true, (value0 - target_value0) / price)
+            } else {
+                (false, target_value0 - value0)
+            };
+
+            // stop once the remainder is negligible against the balances
+            let balance_in = f64::from(if zero_for_one {self.balance0} else {self.balance1});
+            if amount_in < 1.0 || amount_in < balance_in * 1e-6 {
+                break
+            }
+
+            let amount_specified = safe_cast::to_int256(U256::from(amount_in as u128))?;
+            let (amount0, amount1) = swap::swap(provider, pool_state, zero_for_one, amount_specified, swap_limit(zero_for_one)).await?;
+
+            self.balance0 = apply_delta(self.balance0, amount0)?;
+            self.balance1 = apply_delta(self.balance1, amount1)?;
+            // what was paid to the pool net of what was received, at the price before the swap
+            self.swap_costs += (i256_to_f64(amount0) * price + i256_to_f64(amount1)) / 10f64.powi(pool_state.token1.decimals as i32);
+        }
+
+        Ok(())
+    }
+
+    async fn record(
+        &self,
+        provider: &RootProvider<Http<Client>>,
+        pool_state: &mut PoolState,
+        block: u64,
+        timestamp: u64,
+        config: &BacktestConfig,
+        curve: &mut EquityCurve
+    ) -> Result<()> {
+        let (position_value, uncollected_fees) = match &self.position {
+            Some(position) => {
+                let (amount0, amount1) = liquidity_amounts::get_amounts_for_liquidity(
+                    pool_state.slot0.sqrt_price_x96,
+                    tick_math::get_sqrt_ratio_at_tick(position.tick_lower)?,
+                    tick_math::get_sqrt_ratio_at_tick(position.tick_upper)?,
+                    position.liquidity
+                )?;
+                let (fees0, fees1) = pool_state.uncollected_fees(provider, position).await?;
+
+                (self.value(pool_state, amount0, amount1), self.value(pool_state, U256::from(fees0), U256::from(fees1)))
+            },
+            None => (0.0, 0.0)
+        };
+        let idle_value = self.value(pool_state, self.balance0, self.balance1);
+        let tick = pool_state.slot0.tick;
+
+        curve.block.push(block);
+        curve.timestamp.push(timestamp);
+        curve.tick.push(tick);
+        curve.price.push(sqrt_price_x96_to_price(pool_state.slot0.sqrt_price_x96, pool_state.token0.decimals, pool_state.token1.decimals));
+        curve.tick_lower.push(self.position.as_ref().map(|position| position.tick_lower));
+        curve.tick_upper.push(self.position.as_ref().map(|position| position.tick_upper));
+        curve.in_range.push(self.position.as_ref().is_some_and(|position| position.tick_lower <= tick && tick < position.tick_upper));
+        curve.position_value.push(position_value);
+        curve.uncollected_fees.push(uncollected_fees);
+        curve.idle_value.push(idle_value);
+        curve.fees_collected.push(self.fees_collected);
+        curve.swap_costs.push(self.swap_costs);
+        curve.gas_costs.push(self.gas_costs);
+        curve.equity.push(position_value + uncollected_fees + idle_value - self.gas_costs);
+        curve.hodl_value.push(self.value(pool_state, config.amount0, config.amount1));
+        curve.rebalances.push(self.rebalances);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::offline_provider, uniswap_v3::position::tests::pool_state_at_price_one};
+    use super::*;
+
+    #[test]
+    fn centered_range_test() {
+        assert_eq!(centered_range(0, 100, 60), (-120, 120));
+        assert_eq!(centered_range(-1, 60, 60), (-120, 60));
+        assert_eq!(centered_range(887000, 600, 60), (886380, 887220));
+    }
+
+    #[test]
+    fn recentering_band_test() {
+        let mut pool_state = pool_state_at_price_one();
+        let mut strategy = RecenteringBand {half_width: 600, trigger: 60};
+
+        assert_eq!(strategy.rebalance(&pool_state, None), Some((-600, 660)));
+        assert_eq!(strategy.rebalance(&pool_state, Some((-600, 660))), None);
+
+        pool_state.slot0.tick = 610;
+        assert_eq!(strategy.rebalance(&pool_state, Some((-600, 660))), Some((0, 1260)));
+    }
+
+    #[tokio::test]
+    async fn rebalance_earns_swap_fees_test() {
+        // ticks and bitmap words around the price are preloaded
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        let other_liquidity: u128 = 10000000000000000000000;
+        pool_state.mint(&provider, -600, 600, other_liquidity).await.unwrap();
+        let config = BacktestConfig {
+            amount0: U256::from(1000000000000000000u128),
+            amount1: U256::from(3000000000000000000u128),
+            gas_cost_per_rebalance: 0.01
+        };
+
+        let mut account = LpAccount {balance0: config.amount0, balance1: config.amount1, ..Default::default()};
+        account.rebalance(&provider, &mut pool_state, (-600, 600), &config).await.unwrap();
+
+        // the surplus token1 was swapped through the pool before minting a symmetric range
+        assert!(account.swap_costs > 0.0);
+        assert!(account.value(&pool_state, account.balance0, account.balance1) < 0.0001);
+        let position = account.position.clone().unwrap();
+        assert_eq!(pool_state.liquidity, position.liquidity + other_liquidity);
+
+        let amount_in = U256::from(10000000000000000u128);
+        swap::swap(&provider, &mut pool_state, true, safe_cast::to_int256(amount_in).unwrap(), swap_limit(true)).await.unwrap();
+
+        // the position earns its pro rata share of the fee
+        let (fees0, fees1) = pool_state.uncollected_fees(&provider, &position).await.unwrap();
+        let expected0 = 30000000000000.0 * position.liquidity as f64 / pool_state.liquidity as f64;
+        assert_eq!(fees1, 0);
+        assert!((fees0 as f64 - expected0).abs() < 10.0);
+    }
+}
@@ -0,0 +1,163 @@
+use alloy::{
+    primitives::{address, b256, keccak256, Address, B256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol_types::{SolCall, SolValue},
+    transports::http::{Client, Http}
+};
+use crate::chains::ChainDeployment;
+use super::{
+    multicall::multicall_targets,
+    pool::{IPool, LoadingPattern, PoolState},
+    utils::{UNISWAP_V3_POOL_FACTORY_ADDRESS, UNISWAP_V3_POOL_INIT_CODE_HASH}
+};
+use eyre::Result;
+
+/// Deployment of Uniswap V3 or one of its forks
+#[derive(Debug, Clone, Copy)]
+pub struct Dex {
+    pub name: &'static str,
+    pub factory: Address,
+    // address pools are CREATE2 deployed from, the factory itself except for forks with a separate pool deployer
+    pub pool_deployer: Address,
+    pub pool_init_code_hash: B256,
+    // QuoterV2 of the deployment
+    pub quoter: Address,
+    // enabled fee tiers with their tick spacing
+    pub fee_tiers: &'static [(u32, i32)]
+}
+
+pub const UNISWAP_V3: Dex = Dex {
+    name: "Uniswap V3",
+    factory: UNISWAP_V3_POOL_FACTORY_ADDRESS,
+    pool_deployer: UNISWAP_V3_POOL_FACTORY_ADDRESS,
+    pool_init_code_hash: UNISWAP_V3_POOL_INIT_CODE_HASH,
+    quoter: address!("61fFE014bA17989E743c5F6cB21bF9697530B21e"),
+    fee_tiers: &[(100, 1), (500, 10), (3000, 60), (10000, 200)]
+};
+
+pub const SUSHISWAP_V3: Dex = Dex {
+    name: "SushiSwap V3",
+    factory: address!("bACEB8eC6b9355Dfc0269C18bac9d6E2Bdc29C4F"),
+    pool_deployer: address!("bACEB8eC6b9355Dfc0269C18bac9d6E2Bdc29C4F"),
+    pool_init_code_hash: UNISWAP_V3_POOL_INIT_CODE_HASH,
+    quoter: address!("64e8802FE490fa7cc61d3463958199161Bb608A7"),
+    fee_tiers: &[(100, 1), (500, 10), (3000, 60), (10000, 200)]
+};
+
+pub const PANCAKESWAP_V3: Dex = Dex {
+    name: "PancakeSwap V3",
+    factory: address!("0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865"),
+    pool_deployer: address!("41ff9AA7e16B8B1a8a8dc4f0eFacd93D02d071c9"),
+    pool_init_code_hash: b256!("6ce8eb472fa82df5469c6ab6d485f17c3ad13c8cd7af59b3d4a8026c5ce0f7e2"),
+    quoter: address!("B048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997"),
+    fee_tiers: &[(100, 1), (500, 10), (2500, 50), (10000, 200)]
+};
+
+/// Pool of a deployment derived offline, it only exists once someone created it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolKey {
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub pool_address: Address
+}
+
+impl Dex {
+    /// @notice Deterministically computes the pool address given the deployer and PoolKey
+    /// @dev Mirrors PoolAddress.computeAddress, the tokens are sorted first
+    /// @param tokenA The first token of a pool, unsorted
+    /// @param tokenB The second token of a pool, unsorted
+    /// @param fee The fee level of the pool
+    /// @return pool The contract address of the pool
+    pub fn compute_pool_address(&self, token_a: Address, token_b: Address, fee: u32) -> Address {
+        let (token0, token1) = if token_a < token_b {(token_a, token_b)} else {(token_b, token_a)};
+        let salt = keccak256((token0, token1, U256::from(fee)).abi_encode());
+        self.pool_deployer.create2(salt, self.pool_init_code_hash)
+    }
+
+    /// Every pool the deployment can have between any two of `tokens` over all of its fee tiers, without RPC calls
+    pub fn pool_keys(&self, tokens: &[Address]) -> Vec<PoolKey> {
+        let mut keys = Vec::new();
+        for (i, &token_a) in tokens.iter().enumerate() {
+            for &token_b in &tokens[i + 1..] {
+                if token_a == token_b {
+                    continue;
+                }
+                let (token0, token1) = if token_a < token_b {(token_a, token_b)} else {(token_b, token_a)};
+                for &(fee, _) in self.fee_tiers {
+                    keys.push(PoolKey {token0, token1, fee, pool_address: self.compute_pool_address(token0, token1, fee)});
+                }
+            }
+        }
+        keys
+    }
+
+    /// Tick spacing of a fee tier of the deployment
+    pub fn tick_spacing(&self, fee: u32) -> Option<i32> {
+        self.fee_tiers.iter().find(|(tier, _)| *tier == fee).map(|(_, tick_spacing)| *tick_spacing)
+    }
+
+    /// Loads the pool of `pair` and `fee` without asking the factory for its address
+    pub async fn load_pool(
+        &self,
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pair: (Address, Address),
+        fee: u32,
+        loading_pattern: LoadingPattern,
+        block: BlockId
+    ) -> Result<PoolState> {
+        PoolState::load_from_address_at_block(provider, multicall_address, self.compute_pool_address(pair.0, pair.1, fee), loading_pattern, block).await
+    }
+}
+
+/// Keeps the keys of pools that are deployed at `block`, checked through the Multicall3 of the chain's deployment in a
+/// single multicall per 200 pools
+pub async fn deployed_pools(
+    provider: &RootProvider<Http<Client>>,
+    deployment: &ChainDeployment,
+    keys: Vec<PoolKey>,
+    block: BlockId
+) -> Result<Vec<PoolKey>> {
+    let call_data: Vec<(Address, Vec<u8>)> = keys
+        .iter()
+        .map(|key| (key.pool_address, IPool::liquidityCall{}.abi_encode()))
+        .collect();
+
+    // calls to an address without code succeed with empty return data
+    let return_data = multicall_targets(provider, deployment.multicall, true, call_data, block).await?;
+
+    Ok(keys
+        .into_iter()
+        .zip(return_data)
+        .filter(|(_, result)| result.success && !result.returnData.is_empty())
+        .map(|(key, _)| key)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_pool_address_test() {
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+        assert_eq!(UNISWAP_V3.compute_pool_address(weth, usdc, 500), address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"));
+        assert_eq!(UNISWAP_V3.compute_pool_address(usdc, weth, 3000), address!("8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8"));
+
+        // WBNB/USDT 0.05% and 0.01% on BNB Chain, deployed from the separate PancakeSwap pool deployer
+        let wbnb = address!("bb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c");
+        let usdt = address!("55d398326f99059fF775485246999027B3197955");
+        assert_eq!(PANCAKESWAP_V3.compute_pool_address(wbnb, usdt, 500), address!("36696169C63e42cd08ce11f5deeBbCeBae652050"));
+        assert_eq!(PANCAKESWAP_V3.compute_pool_address(usdt, wbnb, 100), address!("172fcD41E0913e95784454622d1c3724f546f849"));
+
+        let keys = PANCAKESWAP_V3.pool_keys(&[weth, usdc]);
+        assert_eq!(keys.len(), 4);
+        assert_eq!((keys[0].token0, keys[0].token1), (usdc, weth));
+        assert_eq!(PANCAKESWAP_V3.tick_spacing(2500), Some(50));
+        assert_eq!(UNISWAP_V3.tick_spacing(2500), None);
+    }
+}
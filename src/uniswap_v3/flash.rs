@@ -0,0 +1,169 @@
+use alloy::{
+    primitives::{Address, I256, U256},
+    providers::RootProvider,
+    transports::http::{Client, Http}
+};
+use super::{
+    math::{constants::Q128, full_math, tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO}},
+    pool::PoolState,
+    swap
+};
+use eyre::{eyre, Result};
+
+/// Result of a flash swap funded arbitrage. The first leg's output is borrowed, repayment and profit are in the first
+/// leg's input token, which is the token the last leg outputs.
+#[derive(Debug)]
+pub struct FlashArbitrage {
+    // input owed to the first pool, repaid from the output of the last leg
+    pub repayment: U256,
+    // output of every leg of the route, in the output token of the leg
+    pub amounts_out: Vec<U256>,
+    // output of the last leg minus the repayment, negative if the route does not cover it
+    pub profit: I256
+}
+
+impl PoolState {
+    /// Fees owed for borrowing `amount0` and `amount1` with `UniswapV3Pool.flash`
+    pub fn flash_fees(&self, amount0: U256, amount1: U256) -> Result<(U256, U256)> {
+        if self.liquidity == 0 {
+            return Err(eyre!("L"))
+        }
+
+        let fee0 = full_math::mul_div_rounding_up(amount0, U256::from(self.fee), U256::from(1000000))?;
+        let fee1 = full_math::mul_div_rounding_up(amount1, U256::from(self.fee), U256::from(1000000))?;
+
+        Ok((fee0, fee1))
+    }
+
+    /// Simulates `UniswapV3Pool.flash` repaid with exactly the borrowed amounts plus fees and returns the fees paid.
+    /// The fees go to the in range liquidity and the protocol the way the pool books them.
+    pub fn flash(&mut self, amount0: U256, amount1: U256) -> Result<(U256, U256)> {
+        let (fee0, fee1) = self.flash_fees(amount0, amount1)?;
+        self.pay_flash_fees(fee0, fee1)?;
+
+        Ok((fee0, fee1))
+    }
+
+    /// Books `paid0` and `paid1` paid on top of a flash loan, the part of `UniswapV3Pool.flash` after the callback
+    pub fn pay_flash_fees(&mut self, paid0: U256, paid1: U256) -> Result<()> {
+        if self.liquidity == 0 {
+            return Err(eyre!("L"))
+        }
+
+        if paid0 > U256::ZERO {
+            let fee_protocol0 = self.slot0.protocol_fee_denominator(true);
+            let fees0 = if fee_protocol0 == 0 {U256::ZERO} else {paid0 / U256::from(fee_protocol0)};
+            let fees0: u128 = fees0.wrapping_to();
+            if fees0 > 0 {
+                self.protocol_fees.token0 = self.protocol_fees.token0.wrapping_add(fees0);
+            }
+            self.fee_growth_global0_x128 = self.fee_growth_global0_x128
+                .wrapping_add(full_math::mul_div(paid0 - U256::from(fees0), Q128, U256::from(self.liquidity))?);
+        }
+        if paid1 > U256::ZERO {
+            let fee_protocol1 = self.slot0.protocol_fee_denominator(false);
+            let fees1 = if fee_protocol1 == 0 {U256::ZERO} else {paid1 / U256::from(fee_protocol1)};
+            let fees1: u128 = fees1.wrapping_to();
+            if fees1 > 0 {
+                self.protocol_fees.token1 = self.protocol_fees.token1.wrapping_add(fees1);
+            }
+            self.fee_growth_global1_x128 = self.fee_growth_global1_x128
+                .wrapping_add(full_math::mul_div(paid1 - U256::from(fees1), Q128, U256::from(self.liquidity))?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Evaluates a cyclic arbitrage funded by a flash swap. The first pool of the route swaps `amount_in` exactly and pays
+/// out before being paid, its output is swapped through the remaining pools and the last output repays the first pool.
+/// `zero_for_one` gives the direction of every leg. The route runs on copies of the pools, a pool used twice in the
+/// route does not see its earlier leg.
+pub async fn simulate_flash_swap_arbitrage(
+    provider: &RootProvider<Http<Client>>,
+    pools: &[PoolState],
+    zero_for_one: &[bool],
+    amount_in: U256
+) -> Result<FlashArbitrage> {
+    if pools.is_empty() || pools.len() != zero_for_one.len() {
+        return Err(eyre!("Route needs one direction per pool"))
+    }
+
+    let token_in = |i: usize| if zero_for_one[i] {pools[i].token0.address} else {pools[i].token1.address};
+    let token_out = |i: usize| if zero_for_one[i] {pools[i].token1.address} else {pools[i].token0.address};
+    for i in 0..pools.len() {
+        let next: Address = token_in((i + 1) % pools.len());
+        if token_out(i) != next {
+            return Err(eyre!("Leg {} of the route outputs {} but the next leg takes {}", i, token_out(i), next))
+        }
+    }
+
+    let mut amounts_out = Vec::with_capacity(pools.len());
+    let mut repayment = U256::ZERO;
+    let mut amount = amount_in;
+    for (i, pool) in pools.iter().enumerate() {
+        let mut scratch = pool.clone();
+        let sqrt_price_limit_x96 = if zero_for_one[i] {MIN_SQRT_RATIO + U256::from(1)} else {MAX_SQRT_RATIO - U256::from(1)};
+        let (amount0, amount1) = swap::swap(provider, &mut scratch, zero_for_one[i], I256::from_raw(amount), sqrt_price_limit_x96).await?;
+        let (paid, received) = if zero_for_one[i] {(amount0, amount1)} else {(amount1, amount0)};
+
+        if i == 0 {
+            // a partial fill when the pool runs out of liquidity is owed in full
+            repayment = paid.unsigned_abs();
+        }
+        amount = received.unsigned_abs();
+        amounts_out.push(amount);
+    }
+
+    let profit = I256::from_raw(amount) - I256::from_raw(repayment);
+
+    Ok(FlashArbitrage {repayment, amounts_out, profit})
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::offline_provider, uniswap_v3::position::tests::pool_state_at_price_one};
+    use super::*;
+
+    #[test]
+    fn flash_fees_test() {
+        let mut pool_state = pool_state_at_price_one();
+        assert!(pool_state.flash(U256::from(1000), U256::ZERO).is_err());
+
+        pool_state.liquidity = 1000000;
+        pool_state.slot0.fee_protocol = 4 + (4 << 4);
+
+        // 0.3% of 1001 rounds up to 4, a quarter of it goes to the protocol
+        let (fee0, fee1) = pool_state.flash(U256::from(1001), U256::ZERO).unwrap();
+        assert_eq!((fee0, fee1), (U256::from(4), U256::ZERO));
+        assert_eq!(pool_state.protocol_fees.token0, 1);
+        assert_eq!(pool_state.fee_growth_global0_x128, Q128 * U256::from(3) / U256::from(1000000));
+        assert_eq!(pool_state.fee_growth_global1_x128, U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn flash_swap_arbitrage_test() {
+        // ticks and bitmap words around the price are preloaded
+        let provider = offline_provider();
+        let mut pool_a = pool_state_at_price_one();
+        pool_a.mint(&provider, -600, 600, 1000000000000000000000).await.unwrap();
+
+        // token0 is cheaper in pool b
+        let mut pool_b = pool_a.clone();
+        pool_b.pool_address = Address::with_last_byte(1);
+        swap::swap(&provider, &mut pool_b, true, I256::from_raw(U256::from(10000000000000000000u128)), MIN_SQRT_RATIO + U256::from(1)).await.unwrap();
+
+        // borrow token0 from pool b against token1, sell it in pool a and repay pool b in token1
+        let pools = vec![pool_b, pool_a];
+        let arbitrage = simulate_flash_swap_arbitrage(&provider, &pools, &[false, true], U256::from(1000000000000000000u128)).await.unwrap();
+        assert!(arbitrage.profit > I256::ZERO);
+        assert_eq!(arbitrage.amounts_out.len(), 2);
+
+        // overshooting moves both prices past each other
+        let arbitrage = simulate_flash_swap_arbitrage(&provider, &pools, &[false, true], U256::from(20000000000000000000u128)).await.unwrap();
+        assert!(arbitrage.profit < I256::ZERO);
+
+        // the route has to close the cycle
+        assert!(simulate_flash_swap_arbitrage(&provider, &pools, &[false, false], U256::from(1)).await.is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use alloy::{
+    primitives::{I256, U256},
+    providers::RootProvider,
+    transports::http::{Client, Http}
+};
+use super::{
+    math::tick_math::{self, MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK},
+    pool::PoolState,
+    swap,
+    utils::sqrt_price_x96_to_price
+};
+use eyre::{eyre, Report, Result};
+use polars::prelude::*;
+
+/// Cost of moving a pool's TWAP by a target deviation
+#[derive(Debug)]
+pub struct ManipulationCost {
+    pub window: u32,
+    pub hold_seconds: u32,
+    // relative move of the TWAP price, e.g. 0.05 for 5%
+    pub target_deviation: f64,
+    // true when the TWAP is pushed down by selling token0
+    pub zero_for_one: bool,
+    pub twap_tick_before: i32,
+    pub twap_tick_after: i32,
+    // tick the spot price has to be held at for `hold_seconds`
+    pub manipulated_tick: i32,
+    // required swap size, in the input token
+    pub amount_in: U256,
+    pub amount_out: U256,
+    // input token recovered by swapping `amount_out` back after the hold
+    pub amount_returned: U256,
+    // fees plus slippage of the round trip, in the input token
+    pub round_trip_loss: U256,
+    // round trip loss valued in token1 at the price before the manipulation
+    pub round_trip_loss_value: f64
+}
+
+/// Estimates the swap needed to move the `window`-second TWAP by `target_deviation` when the manipulated spot price is held
+/// for the last `hold_seconds` of the window (12 for a single mainnet block), and the loss of swapping back afterwards.
+/// The observations of the pool have to be loaded. The estimate runs on a copy of the pool state and assumes nobody
+/// arbitrages the price back during the hold, so it is a lower bound of the real cost.
+pub async fn estimate_manipulation_cost(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &PoolState,
+    window: u32,
+    hold_seconds: u32,
+    target_deviation: f64,
+    zero_for_one: bool
+) -> Result<ManipulationCost> {
+    if hold_seconds == 0 || hold_seconds > window {
+        return Err(eyre!("Hold time must be within the TWAP window"))
+    }
+    if target_deviation <= 0.0 || (zero_for_one && target_deviation >= 1.0) {
+        return Err(eyre!("Target deviation out of range"))
+    }
+
+    let twap_tick_before = pool_state.consult(window)?.arithmetic_mean_tick;
+
+    // tick accumulated over the part of the window that is already history when the manipulation lands
+    let (tick_cumulatives, _) = pool_state.observe(&[window - hold_seconds, 0])?;
+    let history = (tick_cumulatives[1] - tick_cumulatives[0]) as f64;
+
+    let deviation = if zero_for_one {1.0 - target_deviation} else {1.0 + target_deviation};
+    let target_twap_tick = twap_tick_before as f64 + deviation.ln() / 1.0001f64.ln();
+    let manipulated_tick = (target_twap_tick * window as f64 - history) / hold_seconds as f64;
+    let manipulated_tick = if zero_for_one {manipulated_tick.floor()} else {manipulated_tick.ceil()} as i64;
+
+    if manipulated_tick <= MIN_TICK as i64 || manipulated_tick >= MAX_TICK as i64 {
+        return Err(eyre!("Deviation can not be reached within the hold time"))
+    }
+    let manipulated_tick = manipulated_tick as i32;
+
+    let mut scratch = pool_state.clone();
+    let price_before = sqrt_price_x96_to_price(scratch.slot0.sqrt_price_x96, scratch.token0.decimals, scratch.token1.decimals);
+
+    // push the spot price to the manipulated tick
+    let sqrt_price_limit_x96 = tick_math::get_sqrt_ratio_at_tick(manipulated_tick)?;
+    if (zero_for_one && sqrt_price_limit_x96 >= scratch.slot0.sqrt_price_x96) || (!zero_for_one && sqrt_price_limit_x96 <= scratch.slot0.sqrt_price_x96) {
+        return Err(eyre!("Spot price is already past the manipulated tick"))
+    }
+    let (amount0, amount1) = swap::swap(provider, &mut scratch, zero_for_one, I256::MAX, sqrt_price_limit_x96).await?;
+    let (amount_in, amount_out) = if zero_for_one {
+        (amount0.unsigned_abs(), amount1.unsigned_abs())
+    } else {
+        (amount1.unsigned_abs(), amount0.unsigned_abs())
+    };
+
+    // hold the price, then swap the proceeds back
+    scratch.block_timestamp += hold_seconds;
+    let twap_tick_after = scratch.consult(window)?.arithmetic_mean_tick;
+
+    let sqrt_price_limit_x96 = if zero_for_one {MAX_SQRT_RATIO - U256::from(1)} else {MIN_SQRT_RATIO + U256::from(1)};
+    let (amount0, amount1) = swap::swap(provider, &mut scratch, !zero_for_one, I256::from_raw(amount_out), sqrt_price_limit_x96).await?;
+    let amount_returned = if zero_for_one {amount0.unsigned_abs()} else {amount1.unsigned_abs()};
+
+    let round_trip_loss = amount_in.saturating_sub(amount_returned);
+    let round_trip_loss_value = if zero_for_one {
+        f64::from(round_trip_loss) / 10f64.powi(pool_state.token0.decimals as i32) * price_before
+    } else {
+        f64::from(round_trip_loss) / 10f64.powi(pool_state.token1.decimals as i32)
+    };
+
+    Ok(ManipulationCost {
+        window,
+        hold_seconds,
+        target_deviation,
+        zero_for_one,
+        twap_tick_before,
+        twap_tick_after,
+        manipulated_tick,
+        amount_in,
+        amount_out,
+        amount_returned,
+        round_trip_loss,
+        round_trip_loss_value
+    })
+}
+
+/// Manipulation cost in both directions for every combination of TWAP window and target deviation, one row per estimate.
+/// Combinations that can not be reached are left out of the table and returned as (window, target deviation,
+/// zero_for_one) with the reason.
+pub async fn manipulation_cost_table(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &PoolState,
+    windows: &[u32],
+    target_deviations: &[f64],
+    hold_seconds: u32
+) -> Result<(DataFrame, Vec<((u32, f64, bool), Report)>)> {
+    let mut window = Vec::<u32>::new();
+    let mut target_deviation = Vec::<f64>::new();
+    let mut zero_for_one = Vec::<bool>::new();
+    let mut twap_tick_before = Vec::<i32>::new();
+    let mut twap_tick_after = Vec::<i32>::new();
+    let mut manipulated_tick = Vec::<i32>::new();
+    let mut amount_in = Vec::<String>::new();
+    let mut round_trip_loss = Vec::<String>::new();
+    let mut round_trip_loss_value = Vec::<f64>::new();
+    let mut skipped = Vec::new();
+
+    for &seconds in windows {
+        for &deviation in target_deviations {
+            for direction in [true, false] {
+                let cost = match estimate_manipulation_cost(provider, pool_state, seconds, hold_seconds.min(seconds), deviation, direction).await {
+                    Ok(cost) => cost,
+                    Err(e) => {
+                        skipped.push(((seconds, deviation, direction), e));
+                        continue;
+                    }
+                };
+
+                window.push(cost.window);
+                target_deviation.push(cost.target_deviation);
+                zero_for_one.push(cost.zero_for_one);
+                twap_tick_before.push(cost.twap_tick_before);
+                twap_tick_after.push(cost.twap_tick_after);
+                manipulated_tick.push(cost.manipulated_tick);
+                amount_in.push(cost.amount_in.to_string());
+                round_trip_loss.push(cost.round_trip_loss.to_string());
+                round_trip_loss_value.push(cost.round_trip_loss_value);
+            }
+        }
+    }
+
+    let series_vector = vec![
+        Series::new("window", window),
+        Series::new("target_deviation", target_deviation),
+        Series::new("zero_for_one", zero_for_one),
+        Series::new("twap_tick_before", twap_tick_before),
+        Series::new("twap_tick_after", twap_tick_after),
+        Series::new("manipulated_tick", manipulated_tick),
+        Series::new("amount_in", amount_in),
+        Series::new("round_trip_loss", round_trip_loss),
+        Series::new("round_trip_loss_value", round_trip_loss_value)
+    ];
+
+    Ok((DataFrame::new(series_vector)?, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::offline_provider, uniswap_v3::{math::oracle, position::tests::pool_state_at_price_one}};
+    use super::*;
+
+    #[tokio::test]
+    async fn single_block_manipulation_test() {
+        // ticks and bitmap words around the price are preloaded
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.mint(&provider, -600, 600, 1000000000000000000000).await.unwrap();
+
+        // the price sat at tick 0 for the last 1800 seconds
+        (pool_state.slot0.observation_cardinality, pool_state.slot0.observation_cardinality_next) = oracle::initialize(&mut pool_state.observations, 0);
+        pool_state.increase_observation_cardinality_next(10).unwrap();
+        pool_state.block_timestamp = 1800;
+
+        // 1% over 60 seconds held for one 12 second block needs the spot price 5% up
+        let cost = estimate_manipulation_cost(&provider, &pool_state, 60, 12, 0.01, false).await.unwrap();
+        assert_eq!(cost.manipulated_tick, 498);
+        assert!(cost.twap_tick_after >= 99);
+        assert!(cost.round_trip_loss > U256::ZERO && cost.round_trip_loss < cost.amount_in);
+
+        // the pool state itself is untouched
+        assert_eq!(pool_state.slot0.tick, 0);
+
+        // the hold can not outlast the window
+        assert!(estimate_manipulation_cost(&provider, &pool_state, 60, 61, 0.01, false).await.is_err());
+
+        // the observations do not reach an hour back
+        let (df, skipped) = manipulation_cost_table(&provider, &pool_state, &[60, 3600], &[0.01], 12).await.unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!(skipped.iter().map(|(estimate, _)| *estimate).collect::<Vec<_>>(), vec![(3600, 0.01, true), (3600, 0.01, false)]);
+    }
+}
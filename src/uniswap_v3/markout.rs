@@ -0,0 +1,182 @@
+use alloy::{
+    primitives::I256,
+    providers::RootProvider,
+    sol_types::SolEvent,
+    transports::http::{Client, Http}
+};
+use super::{
+    backfill::{estimate_swap_fees, get_pool_logs, log_timestamp},
+    pool::{IPool, PoolState},
+    utils::{i256_to_f64, sqrt_price_x96_to_price}
+};
+use eyre::{eyre, Result};
+use polars::prelude::*;
+use std::fs;
+
+/// Reference price series of token0 in token1 (decimal adjusted), sorted by timestamp
+pub struct ReferencePrices {
+    pub timestamps: Vec<u64>,
+    pub prices: Vec<f64>
+}
+
+impl ReferencePrices {
+    /// Reads a csv with a header containing `timestamp` (unix seconds) and `price` columns, other columns are ignored
+    pub fn read_csv(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines().filter(|line| !line.is_empty());
+
+        let header: Vec<&str> = lines.next().ok_or(eyre!("Reference price csv is empty"))?.split(',').map(str::trim).collect();
+        let timestamp_column = header.iter().position(|&name| name == "timestamp").ok_or(eyre!("Missing timestamp column"))?;
+        let price_column = header.iter().position(|&name| name == "price").ok_or(eyre!("Missing price column"))?;
+
+        let mut rows = Vec::new();
+        for line in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let timestamp = fields.get(timestamp_column).ok_or(eyre!("Malformed reference price row"))?.parse::<u64>()?;
+            let price = fields.get(price_column).ok_or(eyre!("Malformed reference price row"))?.parse::<f64>()?;
+            rows.push((timestamp, price));
+        }
+        rows.sort_by_key(|&(timestamp, _)| timestamp);
+
+        Ok(ReferencePrices {
+            timestamps: rows.iter().map(|&(timestamp, _)| timestamp).collect(),
+            prices: rows.iter().map(|&(_, price)| price).collect()
+        })
+    }
+
+    /// Last reference price observed at or before `timestamp`
+    pub fn price_at(&self, timestamp: u64) -> Option<f64> {
+        match self.timestamps.partition_point(|&t| t <= timestamp) {
+            0 => None,
+            index => Some(self.prices[index - 1])
+        }
+    }
+}
+
+pub struct MarkoutReport {
+    pub swaps: usize,
+    // loss versus rebalancing of the LPs over the period, in token1
+    pub lvr: f64,
+    // fees paid by the swaps to the LPs net of protocol fees, in token1
+    pub fees: f64,
+    // one row per swap
+    pub df: DataFrame
+}
+
+// Column buffers for the per-swap markouts
+#[derive(Default)]
+struct MarkoutRows {
+    block: Vec<u64>,
+    log_index: Vec<u64>,
+    timestamp: Vec<u64>,
+    amount0: Vec<f64>,
+    amount1: Vec<f64>,
+    execution_price: Vec<f64>,
+    pool_price: Vec<f64>,
+    reference_price: Vec<Option<f64>>,
+    lvr: Vec<Option<f64>>,
+    fees: Vec<Option<f64>>,
+    markouts: Vec<Vec<Option<f64>>>
+}
+
+impl MarkoutRows {
+    fn to_df(&self, horizons: &[u64]) -> Result<DataFrame> {
+        let mut series_vector = vec![
+            Series::new("block", &self.block),
+            Series::new("log_index", &self.log_index),
+            Series::new("timestamp", &self.timestamp),
+            Series::new("amount0", &self.amount0),
+            Series::new("amount1", &self.amount1),
+            Series::new("execution_price", &self.execution_price),
+            Series::new("pool_price", &self.pool_price),
+            Series::new("reference_price", &self.reference_price),
+            Series::new("lvr", &self.lvr),
+            Series::new("fees", &self.fees)
+        ];
+        for (horizon, markouts) in horizons.iter().zip(self.markouts.iter()) {
+            series_vector.push(Series::new(&format!("markout_{}s", horizon), markouts));
+        }
+
+        Ok(DataFrame::new(series_vector)?)
+    }
+}
+
+/// Marks every swap of the pool between `from_block` and `to_block` (inclusive) out against the reference price series.
+/// Amounts are taken from the pool's side (positive when paid in to the pool) and valued in token1:
+/// - the markout at horizon h is the LP's gain on the swap valued at the reference price h seconds after the swap, fees included
+/// - LVR is the LP's loss on the swap excluding fees valued at the reference price at the time of the swap, fees are the LP's share
+///
+/// Swaps without a reference price at or before the time they are valued at are left null and excluded from the aggregates.
+pub async fn markouts(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &PoolState,
+    from_block: u64,
+    to_block: u64,
+    reference_prices: &ReferencePrices,
+    horizons: &[u64]
+) -> Result<MarkoutReport> {
+    let logs = get_pool_logs(provider, pool_state, from_block, to_block).await?;
+    let to_token0 = |amount: I256| i256_to_f64(amount) / 10f64.powi(pool_state.token0.decimals as i32);
+    let to_token1 = |amount: I256| i256_to_f64(amount) / 10f64.powi(pool_state.token1.decimals as i32);
+
+    let mut rows = MarkoutRows {markouts: vec![Vec::new(); horizons.len()], ..Default::default()};
+    let (mut lvr, mut fees) = (0.0, 0.0);
+
+    for log in logs.iter().filter(|log| log.inner.topics().first() == Some(&IPool::Swap::SIGNATURE_HASH)) {
+        let swap = log.log_decode::<IPool::Swap>()?.inner.data;
+        let timestamp = log_timestamp(provider, log).await?;
+
+        // the fee is taken from the input token only
+        let zero_for_one = swap.amount0 > I256::ZERO;
+        let amount_in = if zero_for_one {swap.amount0} else {swap.amount1};
+        let split = estimate_swap_fees(pool_state, zero_for_one, amount_in.unsigned_abs())?;
+        let total_fee = I256::from_raw(split.lp_fee + split.protocol_fee);
+        let lp_fee = I256::from_raw(split.lp_fee);
+        let (fee0, fee1, lp_fee0, lp_fee1) = if zero_for_one {
+            (total_fee, I256::ZERO, lp_fee, I256::ZERO)
+        } else {
+            (I256::ZERO, total_fee, I256::ZERO, lp_fee)
+        };
+
+        let (amount0, amount1) = (to_token0(swap.amount0), to_token1(swap.amount1));
+        let reference_price = reference_prices.price_at(timestamp);
+        let swap_lvr = reference_price.map(|price| -(to_token0(swap.amount0 - fee0) * price + to_token1(swap.amount1 - fee1)));
+        let swap_fees = reference_price.map(|price| to_token0(lp_fee0) * price + to_token1(lp_fee1));
+
+        if let (Some(swap_lvr), Some(swap_fees)) = (swap_lvr, swap_fees) {
+            lvr += swap_lvr;
+            fees += swap_fees;
+        }
+
+        rows.block.push(log.block_number.ok_or(eyre!("Log is missing block number"))?);
+        rows.log_index.push(log.log_index.ok_or(eyre!("Log is missing log index"))?);
+        rows.timestamp.push(timestamp);
+        rows.amount0.push(amount0);
+        rows.amount1.push(amount1);
+        rows.execution_price.push(if amount0 == 0.0 {0.0} else {(amount1 / amount0).abs()});
+        rows.pool_price.push(sqrt_price_x96_to_price(swap.sqrtPriceX96, pool_state.token0.decimals, pool_state.token1.decimals));
+        rows.reference_price.push(reference_price);
+        rows.lvr.push(swap_lvr);
+        rows.fees.push(swap_fees);
+        for (horizon, markouts) in horizons.iter().zip(rows.markouts.iter_mut()) {
+            markouts.push(reference_prices.price_at(timestamp + horizon).map(|price| amount0 * price + amount1));
+        }
+    }
+
+    Ok(MarkoutReport {swaps: rows.block.len(), lvr, fees, df: rows.to_df(horizons)?})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_at_test() {
+        let reference_prices = ReferencePrices {timestamps: vec![100, 200, 300], prices: vec![1.0, 2.0, 3.0]};
+
+        assert_eq!(reference_prices.price_at(99), None);
+        assert_eq!(reference_prices.price_at(100), Some(1.0));
+        assert_eq!(reference_prices.price_at(250), Some(2.0));
+        assert_eq!(reference_prices.price_at(1000), Some(3.0));
+    }
+}
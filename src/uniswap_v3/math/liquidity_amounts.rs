@@ -0,0 +1,204 @@
+use alloy::primitives::U256;
+use super::{constants::{FIXED_POINT96_RESOLUTION, Q96}, full_math};
+use eyre::{eyre, Result};
+
+/// @notice Downcasts uint256 to uint128
+/// @param x The uint256 to be downcasted
+/// @return y The passed value, downcasted to uint128
+fn to_uint128(x: U256) -> Result<u128> {
+    if x > U256::from(u128::MAX) {
+        return Err(eyre!("Liquidity overflows uint128"))
+    }
+
+    Ok(x.to::<u128>())
+}
+
+/// @notice Computes the amount of liquidity received for a given amount of token0 and price range
+/// @dev Calculates amount0 * (sqrt(upper) * sqrt(lower)) / (sqrt(upper) - sqrt(lower))
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param amount0 The amount0 being sent in
+/// @return liquidity The amount of returned liquidity
+pub fn get_liquidity_for_amount0(
+    mut sqrt_ratio_ax96: U256,
+    mut sqrt_ratio_bx96: U256,
+    amount0: U256
+) -> Result<u128> {
+    if sqrt_ratio_ax96 > sqrt_ratio_bx96 {
+        (sqrt_ratio_ax96, sqrt_ratio_bx96) = (sqrt_ratio_bx96, sqrt_ratio_ax96);
+    }
+
+    let intermediate = full_math::mul_div(sqrt_ratio_ax96, sqrt_ratio_bx96, Q96)?;
+    to_uint128(full_math::mul_div(amount0, intermediate, sqrt_ratio_bx96 - sqrt_ratio_ax96)?)
+}
+
+/// @notice Computes the amount of liquidity received for a given amount of token1 and price range
+/// @dev Calculates amount1 / (sqrt(upper) - sqrt(lower)).
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param amount1 The amount1 being sent in
+/// @return liquidity The amount of returned liquidity
+pub fn get_liquidity_for_amount1(
+    mut sqrt_ratio_ax96: U256,
+    mut sqrt_ratio_bx96: U256,
+    amount1: U256
+) -> Result<u128> {
+    if sqrt_ratio_ax96 > sqrt_ratio_bx96 {
+        (sqrt_ratio_ax96, sqrt_ratio_bx96) = (sqrt_ratio_bx96, sqrt_ratio_ax96);
+    }
+
+    to_uint128(full_math::mul_div(amount1, Q96, sqrt_ratio_bx96 - sqrt_ratio_ax96)?)
+}
+
+/// @notice Computes the maximum amount of liquidity received for a given amount of token0, token1, the current
+/// pool prices and the prices at the tick boundaries
+/// @param sqrtRatioX96 A sqrt price representing the current pool prices
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param amount0 The amount of token0 being sent in
+/// @param amount1 The amount of token1 being sent in
+/// @return liquidity The maximum amount of liquidity received
+pub fn get_liquidity_for_amounts(
+    sqrt_ratio_x96: U256,
+    mut sqrt_ratio_ax96: U256,
+    mut sqrt_ratio_bx96: U256,
+    amount0: U256,
+    amount1: U256
+) -> Result<u128> {
+    if sqrt_ratio_ax96 > sqrt_ratio_bx96 {
+        (sqrt_ratio_ax96, sqrt_ratio_bx96) = (sqrt_ratio_bx96, sqrt_ratio_ax96);
+    }
+
+    if sqrt_ratio_x96 <= sqrt_ratio_ax96 {
+        get_liquidity_for_amount0(sqrt_ratio_ax96, sqrt_ratio_bx96, amount0)
+    } else if sqrt_ratio_x96 < sqrt_ratio_bx96 {
+        let liquidity0 = get_liquidity_for_amount0(sqrt_ratio_x96, sqrt_ratio_bx96, amount0)?;
+        let liquidity1 = get_liquidity_for_amount1(sqrt_ratio_ax96, sqrt_ratio_x96, amount1)?;
+
+        Ok(if liquidity0 < liquidity1 {liquidity0} else {liquidity1})
+    } else {
+        get_liquidity_for_amount1(sqrt_ratio_ax96, sqrt_ratio_bx96, amount1)
+    }
+}
+
+/// @notice Computes the amount of token0 for a given amount of liquidity and a price range
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param liquidity The liquidity being valued
+/// @return amount0 The amount of token0
+pub fn get_amount0_for_liquidity(
+    mut sqrt_ratio_ax96: U256,
+    mut sqrt_ratio_bx96: U256,
+    liquidity: u128
+) -> Result<U256> {
+    if sqrt_ratio_ax96 > sqrt_ratio_bx96 {
+        (sqrt_ratio_ax96, sqrt_ratio_bx96) = (sqrt_ratio_bx96, sqrt_ratio_ax96);
+    }
+
+    if sqrt_ratio_ax96.is_zero() {
+        return Err(eyre!("Sqrt ratio ax 96 can not be 0"))
+    }
+
+    Ok(full_math::mul_div(
+        U256::from(liquidity) << FIXED_POINT96_RESOLUTION,
+        sqrt_ratio_bx96 - sqrt_ratio_ax96,
+        sqrt_ratio_bx96
+    )? / sqrt_ratio_ax96)
+}
+
+/// @notice Computes the amount of token1 for a given amount of liquidity and a price range
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param liquidity The liquidity being valued
+/// @return amount1 The amount of token1
+pub fn get_amount1_for_liquidity(
+    mut sqrt_ratio_ax96: U256,
+    mut sqrt_ratio_bx96: U256,
+    liquidity: u128
+) -> Result<U256> {
+    if sqrt_ratio_ax96 > sqrt_ratio_bx96 {
+        (sqrt_ratio_ax96, sqrt_ratio_bx96) = (sqrt_ratio_bx96, sqrt_ratio_ax96);
+    }
+
+    full_math::mul_div(U256::from(liquidity), sqrt_ratio_bx96 - sqrt_ratio_ax96, Q96)
+}
+
+/// @notice Computes the token0 and token1 value for a given amount of liquidity, the current
+/// pool prices and the prices at the tick boundaries
+/// @param sqrtRatioX96 A sqrt price representing the current pool prices
+/// @param sqrtRatioAX96 A sqrt price representing the first tick boundary
+/// @param sqrtRatioBX96 A sqrt price representing the second tick boundary
+/// @param liquidity The liquidity being valued
+/// @return amount0 The amount of token0
+/// @return amount1 The amount of token1
+pub fn get_amounts_for_liquidity(
+    sqrt_ratio_x96: U256,
+    mut sqrt_ratio_ax96: U256,
+    mut sqrt_ratio_bx96: U256,
+    liquidity: u128
+) -> Result<(U256, U256)> {
+    if sqrt_ratio_ax96 > sqrt_ratio_bx96 {
+        (sqrt_ratio_ax96, sqrt_ratio_bx96) = (sqrt_ratio_bx96, sqrt_ratio_ax96);
+    }
+
+    if sqrt_ratio_x96 <= sqrt_ratio_ax96 {
+        Ok((get_amount0_for_liquidity(sqrt_ratio_ax96, sqrt_ratio_bx96, liquidity)?, U256::ZERO))
+    } else if sqrt_ratio_x96 < sqrt_ratio_bx96 {
+        Ok((
+            get_amount0_for_liquidity(sqrt_ratio_x96, sqrt_ratio_bx96, liquidity)?,
+            get_amount1_for_liquidity(sqrt_ratio_ax96, sqrt_ratio_x96, liquidity)?
+        ))
+    } else {
+        Ok((U256::ZERO, get_amount1_for_liquidity(sqrt_ratio_ax96, sqrt_ratio_bx96, liquidity)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // encodePriceSqrt vectors from the periphery LiquidityAmounts spec
+    const PRICE_1_1: u128 = 79228162514264337593543950336;
+    const PRICE_100_110: u128 = 75541088972021052632782079082;
+    const PRICE_110_100: u128 = 83095197869223157896060286990;
+    const PRICE_99_110: u128 = 75162434512514379355924140470;
+    const PRICE_111_100: u128 = 83472048772503575395058907992;
+
+    #[test]
+    fn get_liquidity_for_amounts_test() {
+        let (sqrt_ratio_ax96, sqrt_ratio_bx96) = (U256::from(PRICE_100_110), U256::from(PRICE_110_100));
+        let (amount0, amount1) = (U256::from(100), U256::from(200));
+
+        // price inside
+        assert_eq!(get_liquidity_for_amounts(U256::from(PRICE_1_1), sqrt_ratio_ax96, sqrt_ratio_bx96, amount0, amount1).unwrap(), 2148);
+        // price below
+        assert_eq!(get_liquidity_for_amounts(U256::from(PRICE_99_110), sqrt_ratio_ax96, sqrt_ratio_bx96, amount0, amount1).unwrap(), 1048);
+        // price above
+        assert_eq!(get_liquidity_for_amounts(U256::from(PRICE_111_100), sqrt_ratio_ax96, sqrt_ratio_bx96, amount0, amount1).unwrap(), 2097);
+        // price on lower boundary
+        assert_eq!(get_liquidity_for_amounts(sqrt_ratio_ax96, sqrt_ratio_ax96, sqrt_ratio_bx96, amount0, amount1).unwrap(), 1048);
+        // price on upper boundary
+        assert_eq!(get_liquidity_for_amounts(sqrt_ratio_bx96, sqrt_ratio_ax96, sqrt_ratio_bx96, amount0, amount1).unwrap(), 2097);
+    }
+
+    #[test]
+    fn get_amounts_for_liquidity_test() {
+        let (sqrt_ratio_ax96, sqrt_ratio_bx96) = (U256::from(PRICE_100_110), U256::from(PRICE_110_100));
+
+        // price inside
+        assert_eq!(get_amounts_for_liquidity(U256::from(PRICE_1_1), sqrt_ratio_ax96, sqrt_ratio_bx96, 2148).unwrap(), (U256::from(99), U256::from(99)));
+        // price below
+        assert_eq!(get_amounts_for_liquidity(U256::from(PRICE_99_110), sqrt_ratio_ax96, sqrt_ratio_bx96, 1048).unwrap(), (U256::from(99), U256::ZERO));
+        // price above
+        assert_eq!(get_amounts_for_liquidity(U256::from(PRICE_111_100), sqrt_ratio_ax96, sqrt_ratio_bx96, 2097).unwrap(), (U256::ZERO, U256::from(199)));
+        // price on lower boundary
+        assert_eq!(get_amounts_for_liquidity(sqrt_ratio_ax96, sqrt_ratio_ax96, sqrt_ratio_bx96, 1048).unwrap(), (U256::from(99), U256::ZERO));
+        // price on upper boundary
+        assert_eq!(get_amounts_for_liquidity(sqrt_ratio_bx96, sqrt_ratio_ax96, sqrt_ratio_bx96, 2097).unwrap(), (U256::ZERO, U256::from(199)));
+    }
+
+    #[test]
+    fn get_liquidity_for_amount0_overflow_test() {
+        assert!(get_liquidity_for_amount0(U256::from(PRICE_100_110), U256::from(PRICE_110_100), U256::MAX >> 1).is_err());
+    }
+}
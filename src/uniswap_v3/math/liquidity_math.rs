@@ -3,7 +3,7 @@ use eyre::{eyre, Result};
 pub fn add_delta (x : u128, y : i128) -> Result<u128> {
     match y < 0 {
         true => {
-            let z = x - y.unsigned_abs(); 
+            let z = x.wrapping_sub(y.unsigned_abs()); 
             if z < x {
                 Ok(z)
             } else {
@@ -11,7 +11,7 @@ pub fn add_delta (x : u128, y : i128) -> Result<u128> {
             }
         }, 
         false => {
-            let z = x + y.unsigned_abs(); 
+            let z = x.wrapping_add(y.unsigned_abs()); 
             if z >= x {
                 Ok(z)
             } else {
@@ -1,8 +1,10 @@
 pub mod bit_math;
 pub mod constants; 
 pub mod full_math;
+pub mod liquidity_amounts;
 pub mod liquidity_math;
 pub mod low_gas_safe_math;
+pub mod oracle;
 pub mod safe_cast;
 pub mod sqrt_price_math;
 pub mod swap_math;
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use alloy::primitives::U256;
+use eyre::{eyre, Result};
+
+// uint160 accumulators wrap at 2**160
+pub const UINT160_MASK: U256 = U256::from_limbs([u64::MAX, u64::MAX, u32::MAX as u64, 0]);
+
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+    // the block timestamp of the observation
+    pub block_timestamp: u32,
+    // the tick accumulator, i.e. tick * time elapsed since the pool was first initialized
+    pub tick_cumulative: i64,
+    // the seconds per liquidity, i.e. seconds elapsed / max(1, liquidity) since the pool was first initialized
+    pub seconds_per_liquidity_cumulative_x128: U256,
+    // whether or not the observation is initialized
+    pub initialized: bool
+}
+
+fn get(observations: &HashMap<u16, Observation>, index: u32) -> Observation {
+    observations.get(&(index as u16)).copied().unwrap_or_default()
+}
+
+/// @notice Transforms a previous observation into a new observation, given the passage of time and the current tick and liquidity values
+/// @dev blockTimestamp _must_ be chronologically equal to or greater than last.blockTimestamp, safe for 0 or 1 overflows
+/// @param last The specified observation to be transformed
+/// @param blockTimestamp The timestamp of the new observation
+/// @param tick The active tick at the time of the new observation
+/// @param liquidity The total in-range liquidity at the time of the new observation
+/// @return Observation The newly populated observation
+pub fn transform(
+    last: &Observation,
+    block_timestamp: u32,
+    tick: i32,
+    liquidity: u128
+) -> Observation {
+    let delta = block_timestamp.wrapping_sub(last.block_timestamp);
+    let liquidity = if liquidity > 0 {liquidity} else {1};
+
+    Observation {
+        block_timestamp,
+        tick_cumulative: last.tick_cumulative.wrapping_add(tick as i64 * delta as i64),
+        seconds_per_liquidity_cumulative_x128: last.seconds_per_liquidity_cumulative_x128
+            .wrapping_add((U256::from(delta) << 128) / U256::from(liquidity)) & UINT160_MASK,
+        initialized: true
+    }
+}
+
+/// @notice Initialize the oracle array by writing the first slot. Called once for the lifecycle of the observations array
+/// @param self The stored oracle array
+/// @param time The time of the oracle initialization, via block.timestamp truncated to uint32
+/// @return cardinality The number of populated elements in the oracle array
+/// @return cardinalityNext The new length of the oracle array, independent of population
+pub fn initialize(
+    observations: &mut HashMap<u16, Observation>,
+    time: u32
+) -> (u16, u16) {
+    observations.insert(0, Observation {block_timestamp: time, initialized: true, ..Default::default()});
+    (1, 1)
+}
+
+/// @notice Writes an oracle observation to the array
+/// @dev Writable at most once per block. Index represents the most recently written element. cardinality and index must be tracked externally.
+/// If the index is at the end of the allowable array length (according to cardinality), and the next cardinality
+/// is greater than the current one, cardinality may be increased. This restriction is created to preserve ordering.
+/// @param self The stored oracle array
+/// @param index The index of the observation that was most recently written to the observations array
+/// @param blockTimestamp The timestamp of the new observation
+/// @param tick The active tick at the time of the new observation
+/// @param liquidity The total in-range liquidity at the time of the new observation
+/// @param cardinality The number of populated elements in the oracle array
+/// @param cardinalityNext The new length of the oracle array, independent of population
+/// @return indexUpdated The new index of the most recently written element in the oracle array
+/// @return cardinalityUpdated The new cardinality of the oracle array
+pub fn write(
+    observations: &mut HashMap<u16, Observation>,
+    index: u16,
+    block_timestamp: u32,
+    tick: i32,
+    liquidity: u128,
+    cardinality: u16,
+    cardinality_next: u16
+) -> (u16, u16) {
+    let last = get(observations, index as u32);
+
+    // early return if we've already written an observation this block
+    if last.block_timestamp == block_timestamp {
+        return (index, cardinality)
+    }
+
+    // if the conditions are right, we can bump the cardinality
+    let cardinality_updated = if cardinality_next > cardinality && index == cardinality - 1 {
+        cardinality_next
+    } else {
+        cardinality
+    };
+
+    let index_updated = ((index as u32 + 1) % cardinality_updated as u32) as u16;
+    observations.insert(index_updated, transform(&last, block_timestamp, tick, liquidity));
+
+    (index_updated, cardinality_updated)
+}
+
+/// @notice Prepares the oracle array to store up to `next` observations
+/// @param self The stored oracle array
+/// @param current The current next cardinality of the oracle array
+/// @param next The proposed next cardinality which will be populated in the oracle array
+/// @return next The next cardinality which will be populated in the oracle array
+pub fn grow(
+    observations: &mut HashMap<u16, Observation>,
+    current: u16,
+    next: u16
+) -> Result<u16> {
+    if current == 0 {
+        return Err(eyre!("I"))
+    }
+    // no-op if the passed next value isn't greater than the current next value
+    if next <= current {
+        return Ok(current)
+    }
+    // store in each slot to prevent fresh SSTOREs in swaps
+    // this data will not be used because the initialized boolean is still false
+    for i in current..next {
+        observations.entry(i).or_default().block_timestamp = 1;
+    }
+
+    Ok(next)
+}
+
+/// @notice comparator for 32-bit timestamps
+/// @dev safe for 0 or 1 overflows, a and b _must_ be chronologically before or equal to time
+/// @param time A timestamp truncated to 32 bits
+/// @param a A comparison timestamp from which to determine the relative position of `time`
+/// @param b From which to determine the relative position of `time`
+/// @return bool Whether `a` is chronologically <= `b`
+pub fn lte(time: u32, a: u32, b: u32) -> bool {
+    // if there hasn't been overflow, no need to adjust
+    if a <= time && b <= time {
+        return a <= b
+    }
+
+    let a_adjusted = if a > time {a as u64} else {a as u64 + (1 << 32)};
+    let b_adjusted = if b > time {b as u64} else {b as u64 + (1 << 32)};
+
+    a_adjusted <= b_adjusted
+}
+
+/// @notice Fetches the observations beforeOrAt and atOrAfter a target, i.e. where [beforeOrAt, atOrAfter] is satisfied.
+/// The result may be the same observation, or adjacent observations.
+/// @dev The answer must be contained in the array, used when the target is located within the stored observation
+/// boundaries: older than the most recent observation and younger, or the same age as, the oldest observation
+/// @param self The stored oracle array
+/// @param time The current block.timestamp
+/// @param target The timestamp at which the reserved observation should be for
+/// @param index The index of the observation that was most recently written to the observations array
+/// @param cardinality The number of populated elements in the oracle array
+/// @return beforeOrAt The observation recorded before, or at, the target
+/// @return atOrAfter The observation recorded at, or after, the target
+pub fn binary_search(
+    observations: &HashMap<u16, Observation>,
+    time: u32,
+    target: u32,
+    index: u16,
+    cardinality: u16
+) -> Result<(Observation, Observation)> {
+    let cardinality = cardinality as u32;
+    // oldest observation
+    let mut l = (index as u32 + 1) % cardinality;
+    // newest observation
+    let mut r = l + cardinality - 1;
+
+    loop {
+        if l > r {
+            return Err(eyre!("Target not contained in the observations"))
+        }
+        let i = (l + r) / 2;
+
+        let before_or_at = get(observations, i % cardinality);
+
+        // we've landed on an uninitialized tick, keep searching higher (more recently)
+        if !before_or_at.initialized {
+            l = i + 1;
+            continue;
+        }
+
+        let at_or_after = get(observations, (i + 1) % cardinality);
+
+        let target_at_or_after = lte(time, before_or_at.block_timestamp, target);
+
+        // check if we've found the answer!
+        if target_at_or_after && lte(time, target, at_or_after.block_timestamp) {
+            return Ok((before_or_at, at_or_after))
+        }
+
+        if !target_at_or_after {
+            r = i.checked_sub(1).ok_or(eyre!("Target not contained in the observations"))?;
+        } else {
+            l = i + 1;
+        }
+    }
+}
+
+/// @notice Fetches the observations beforeOrAt and atOrAfter a given target, i.e. where [beforeOrAt, atOrAfter] is satisfied
+/// @dev Assumes there is at least 1 initialized observation.
+/// Used by observeSingle() to compute the counterfactual accumulator values as of a given block timestamp.
+/// @param self The stored oracle array
+/// @param time The current block.timestamp
+/// @param target The timestamp at which the reserved observation should be for
+/// @param tick The active tick at the time of the returned or simulated observation
+/// @param index The index of the observation that was most recently written to the observations array
+/// @param liquidity The total pool liquidity at the time of the call
+/// @param cardinality The number of populated elements in the oracle array
+/// @return beforeOrAt The observation which occurred at, or before, the given timestamp
+/// @return atOrAfter The observation which occurred at, or after, the given timestamp
+#[allow(clippy::too_many_arguments)]
+pub fn get_surrounding_observations(
+    observations: &HashMap<u16, Observation>,
+    time: u32,
+    target: u32,
+    tick: i32,
+    index: u16,
+    liquidity: u128,
+    cardinality: u16
+) -> Result<(Observation, Observation)> {
+    // optimistically set before to the newest observation
+    let before_or_at = get(observations, index as u32);
+
+    // if the target is chronologically at or after the newest observation, we can early return
+    if lte(time, before_or_at.block_timestamp, target) {
+        if before_or_at.block_timestamp == target {
+            // if newest observation equals target, we're in the same block, so we can ignore atOrAfter
+            return Ok((before_or_at, Observation::default()))
+        } else {
+            // otherwise, we need to transform
+            return Ok((before_or_at, transform(&before_or_at, target, tick, liquidity)))
+        }
+    }
+
+    // now, set before to the oldest observation
+    let mut before_or_at = get(observations, (index as u32 + 1) % cardinality as u32);
+    if !before_or_at.initialized {
+        before_or_at = get(observations, 0);
+    }
+
+    // ensure that the target is chronologically at or after the oldest observation
+    if !lte(time, before_or_at.block_timestamp, target) {
+        return Err(eyre!("OLD"))
+    }
+
+    // if we've reached this point, we have to binary search
+    binary_search(observations, time, target, index, cardinality)
+}
+
+/// @dev Reverts if an observation at or before the desired observation timestamp does not exist.
+/// 0 may be passed as `secondsAgo' to return the current cumulative values.
+/// If called with a timestamp falling between two observations, returns the counterfactual accumulator values
+/// at exactly the timestamp between the two observations.
+/// @param self The stored oracle array
+/// @param time The current block timestamp
+/// @param secondsAgo The amount of time to look back, in seconds, at which point to return an observation
+/// @param tick The current tick
+/// @param index The index of the observation that was most recently written to the observations array
+/// @param liquidity The current in-range pool liquidity
+/// @param cardinality The number of populated elements in the oracle array
+/// @return tickCumulative The tick * time elapsed since the pool was first initialized, as of `secondsAgo`
+/// @return secondsPerLiquidityCumulativeX128 The time elapsed / max(1, liquidity) since the pool was first initialized, as of `secondsAgo`
+#[allow(clippy::too_many_arguments)]
+pub fn observe_single(
+    observations: &HashMap<u16, Observation>,
+    time: u32,
+    seconds_ago: u32,
+    tick: i32,
+    index: u16,
+    liquidity: u128,
+    cardinality: u16
+) -> Result<(i64, U256)> {
+    if seconds_ago == 0 {
+        let mut last = get(observations, index as u32);
+        if last.block_timestamp != time {
+            last = transform(&last, time, tick, liquidity);
+        }
+        return Ok((last.tick_cumulative, last.seconds_per_liquidity_cumulative_x128))
+    }
+
+    let target = time.wrapping_sub(seconds_ago);
+
+    let (before_or_at, at_or_after) = get_surrounding_observations(observations, time, target, tick, index, liquidity, cardinality)?;
+
+    if target == before_or_at.block_timestamp {
+        // we're at the left boundary
+        Ok((before_or_at.tick_cumulative, before_or_at.seconds_per_liquidity_cumulative_x128))
+    } else if target == at_or_after.block_timestamp {
+        // we're at the right boundary
+        Ok((at_or_after.tick_cumulative, at_or_after.seconds_per_liquidity_cumulative_x128))
+    } else {
+        // we're in the middle
+        let observation_time_delta = at_or_after.block_timestamp.wrapping_sub(before_or_at.block_timestamp);
+        let target_delta = target.wrapping_sub(before_or_at.block_timestamp);
+
+        Ok((
+            before_or_at.tick_cumulative
+                + (at_or_after.tick_cumulative - before_or_at.tick_cumulative) / observation_time_delta as i64 * target_delta as i64,
+            before_or_at.seconds_per_liquidity_cumulative_x128.wrapping_add(
+                (at_or_after.seconds_per_liquidity_cumulative_x128.wrapping_sub(before_or_at.seconds_per_liquidity_cumulative_x128) & UINT160_MASK)
+                    * U256::from(target_delta) / U256::from(observation_time_delta)
+            ) & UINT160_MASK
+        ))
+    }
+}
+
+/// @notice Returns the accumulator values as of each time seconds ago from the given time in the array of `secondsAgos`
+/// @dev Reverts if `secondsAgos` > oldest observation
+/// @param self The stored oracle array
+/// @param time The current block.timestamp
+/// @param secondsAgos Each amount of time to look back, in seconds, at which point to return an observation
+/// @param tick The current tick
+/// @param index The index of the observation that was most recently written to the observations array
+/// @param liquidity The current in-range pool liquidity
+/// @param cardinality The number of populated elements in the oracle array
+/// @return tickCumulatives The tick * time elapsed since the pool was first initialized, as of each `secondsAgo`
+/// @return secondsPerLiquidityCumulativeX128s The cumulative seconds / max(1, liquidity) since the pool was first initialized, as of each `secondsAgo`
+pub fn observe(
+    observations: &HashMap<u16, Observation>,
+    time: u32,
+    seconds_agos: &[u32],
+    tick: i32,
+    index: u16,
+    liquidity: u128,
+    cardinality: u16
+) -> Result<(Vec<i64>, Vec<U256>)> {
+    if cardinality == 0 {
+        return Err(eyre!("I"))
+    }
+
+    let mut tick_cumulatives = Vec::with_capacity(seconds_agos.len());
+    let mut seconds_per_liquidity_cumulative_x128s = Vec::with_capacity(seconds_agos.len());
+    for &seconds_ago in seconds_agos {
+        let (tick_cumulative, seconds_per_liquidity_cumulative_x128) = observe_single(
+            observations, time, seconds_ago, tick, index, liquidity, cardinality
+        )?;
+        tick_cumulatives.push(tick_cumulative);
+        seconds_per_liquidity_cumulative_x128s.push(seconds_per_liquidity_cumulative_x128);
+    }
+
+    Ok((tick_cumulatives, seconds_per_liquidity_cumulative_x128s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_observe_test() {
+        let mut observations = HashMap::new();
+        let (mut cardinality, mut cardinality_next) = initialize(&mut observations, 5);
+        cardinality_next = grow(&mut observations, cardinality_next, 3).unwrap();
+
+        let mut index = 0;
+        (index, cardinality) = write(&mut observations, index, 6, 3, 2, cardinality, cardinality_next);
+        (index, cardinality) = write(&mut observations, index, 9, -7, 5, cardinality, cardinality_next);
+        assert_eq!((index, cardinality), (2, 3));
+
+        // the latest observation is transformed to the current time
+        let (tick_cumulatives, _) = observe(&observations, 13, &[0], 1, index, 5, cardinality).unwrap();
+        assert_eq!(tick_cumulatives, vec![3 - 21 + 4]);
+
+        // interpolated between the observations at 6 and 9
+        let (tick_cumulatives, seconds_per_liquidity_cumulative_x128s) = observe(&observations, 13, &[5], 1, index, 5, cardinality).unwrap();
+        assert_eq!(tick_cumulatives, vec![3 - 14]);
+        assert_eq!(
+            seconds_per_liquidity_cumulative_x128s,
+            vec![(U256::from(1) << 128) / U256::from(2) + ((U256::from(3) << 128) / U256::from(5)) * U256::from(2) / U256::from(3)]
+        );
+
+        // older than the oldest observation
+        assert!(observe(&observations, 13, &[9], 1, index, 5, cardinality).is_err());
+    }
+
+    #[test]
+    fn write_once_per_block_test() {
+        let mut observations = HashMap::new();
+        let (cardinality, cardinality_next) = initialize(&mut observations, 5);
+
+        assert_eq!(write(&mut observations, 0, 5, 10, 1, cardinality, cardinality_next), (0, 1));
+        assert_eq!(write(&mut observations, 0, 6, 10, 1, cardinality, cardinality_next), (0, 1));
+        assert_eq!(observations[&0].tick_cumulative, 10);
+    }
+
+    #[test]
+    fn lte_overflow_test() {
+        assert!(lte(5, u32::MAX - 10, 3));
+        assert!(!lte(5, 3, u32::MAX - 10));
+        assert!(lte(5, 3, 4));
+    }
+}
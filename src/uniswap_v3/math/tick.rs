@@ -1,14 +1,20 @@
 use std::collections::HashMap;
-use super::{liquidity_math::add_delta, tick_math::*}; 
+use super::{liquidity_math::add_delta, oracle::UINT160_MASK, tick_math::*}; 
 use eyre::{eyre, Result}; 
 use alloy::primitives::U256;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Info {
     pub liquidity_gross: u128, 
     pub liquidity_net: i128, 
     pub fee_growth_outside0_x128: U256, 
     pub fee_growth_outside1_x128: U256,
+    // the cumulative tick value on the other side of the tick
+    pub tick_cumulative_outside: i64,
+    // the seconds per unit of liquidity on the _other_ side of this tick (relative to the current tick)
+    pub seconds_per_liquidity_outside_x128: U256,
+    // the seconds spent on the other side of the tick (relative to the current tick)
+    pub seconds_outside: u32,
     pub initialized: bool
 }
 
@@ -39,10 +45,17 @@ pub fn _tick_spacing_to_max_liquidity_per_tick ( tick_spacing: i32 ) -> u128 {
 /// @param upper true for updating a position's upper tick, or false for updating a position's lower tick
 /// @param maxLiquidity The maximum liquidity allocation for a single tick
 /// @return flipped Whether the tick was flipped from initialized to uninitialized, or vice versa
+#[allow(clippy::too_many_arguments)]
 pub fn _update (
     mapping: &mut HashMap<i32, Info>, 
     tick: i32, 
+    tick_current: i32, 
     liquidity_delta: i128,
+    fee_growth_global0_x128: U256, 
+    fee_growth_global1_x128: U256, 
+    seconds_per_liquidity_cumulative_x128: U256,
+    tick_cumulative: i64,
+    time: u32,
     upper: bool, 
     max_liquidity: u128 
 ) -> Result<bool> {
@@ -57,6 +70,14 @@ pub fn _update (
     let flipped = (liquidity_gross_after == 0) != (liquidity_gross_before == 0); 
 
     if liquidity_gross_before == 0 {
+        // by convention, we assume that all growth before a tick was initialized happened _below_ the tick
+        if tick <= tick_current {
+            tick_info.fee_growth_outside0_x128 = fee_growth_global0_x128; 
+            tick_info.fee_growth_outside1_x128 = fee_growth_global1_x128; 
+            tick_info.seconds_per_liquidity_outside_x128 = seconds_per_liquidity_cumulative_x128;
+            tick_info.tick_cumulative_outside = tick_cumulative;
+            tick_info.seconds_outside = time;
+        }
         tick_info.initialized = true; 
     }
 
@@ -80,6 +101,17 @@ pub fn _update (
     }
 }
 
+/// @notice Clears tick data
+/// @param self The mapping containing all initialized tick information for initialized ticks
+/// @param tick The tick that will be cleared
+/// @dev The entry is reset instead of removed so a later lazy load does not bring back the on-chain tick
+pub fn clear (
+    mapping: &mut HashMap<i32, Info>, 
+    tick: i32
+) {
+    mapping.insert(tick, Info::default());
+}
+
 /// @notice Retrieves fee growth data
 /// @param self The mapping containing all tick information for initialized ticks
 /// @param tickLower The lower tick boundary of the position
@@ -103,7 +135,7 @@ pub fn get_fee_growth_inside(
     let lower = tick_lower_info; 
     let upper = tick_upper_info;
 
-    // calculate fee growth below
+    // calculate fee growth below, all fee growth math is unchecked in the pool so it is allowed to wrap
     let fee_growth_below0_x128: U256; 
     let fee_growth_below1_x128: U256;
 
@@ -111,8 +143,8 @@ pub fn get_fee_growth_inside(
         fee_growth_below0_x128 = lower.fee_growth_outside0_x128; 
         fee_growth_below1_x128 = lower.fee_growth_outside1_x128;
     } else {
-        fee_growth_below0_x128 = fee_growth_global0_x128 - lower.fee_growth_outside0_x128; 
-        fee_growth_below1_x128 = fee_growth_global1_x128 - lower.fee_growth_outside1_x128;
+        fee_growth_below0_x128 = fee_growth_global0_x128.wrapping_sub(lower.fee_growth_outside0_x128); 
+        fee_growth_below1_x128 = fee_growth_global1_x128.wrapping_sub(lower.fee_growth_outside1_x128);
     }
 
     let fee_growth_above0_x128: U256; 
@@ -122,12 +154,41 @@ pub fn get_fee_growth_inside(
         fee_growth_above0_x128 = upper.fee_growth_outside0_x128; 
         fee_growth_above1_x128 = upper.fee_growth_outside1_x128;
     } else {
-        fee_growth_above0_x128 = fee_growth_global0_x128 - upper.fee_growth_outside0_x128; 
-        fee_growth_above1_x128 = fee_growth_global1_x128 - upper.fee_growth_outside1_x128;
+        fee_growth_above0_x128 = fee_growth_global0_x128.wrapping_sub(upper.fee_growth_outside0_x128); 
+        fee_growth_above1_x128 = fee_growth_global1_x128.wrapping_sub(upper.fee_growth_outside1_x128);
     }
 
-    let fee_growth_inside0_x128 = fee_growth_global0_x128 - fee_growth_below0_x128 - fee_growth_above0_x128; 
-    let fee_growth_inside1_x128 = fee_growth_global1_x128 - fee_growth_below1_x128 - fee_growth_above1_x128;
+    let fee_growth_inside0_x128 = fee_growth_global0_x128.wrapping_sub(fee_growth_below0_x128).wrapping_sub(fee_growth_above0_x128); 
+    let fee_growth_inside1_x128 = fee_growth_global1_x128.wrapping_sub(fee_growth_below1_x128).wrapping_sub(fee_growth_above1_x128);
 
     Ok((fee_growth_inside0_x128, fee_growth_inside1_x128))
-}
\ No newline at end of file
+}
+
+/// @notice Transitions to next tick as needed by price movement
+/// @param self The mapping containing all tick information for initialized ticks
+/// @param tick The destination tick of the transition
+/// @param feeGrowthGlobal0X128 The all-time global fee growth, per unit of liquidity, in token0
+/// @param feeGrowthGlobal1X128 The all-time global fee growth, per unit of liquidity, in token1
+/// @param secondsPerLiquidityCumulativeX128 The current seconds per liquidity
+/// @param tickCumulative The tick * time elapsed since the pool was first initialized
+/// @param time The current block.timestamp
+/// @return liquidityNet The amount of liquidity added (subtracted) when tick is crossed from left to right (right to left)
+pub fn cross(
+    mapping: &mut HashMap<i32, Info>,
+    tick: i32,
+    fee_growth_global0_x128: U256,
+    fee_growth_global1_x128: U256,
+    seconds_per_liquidity_cumulative_x128: U256,
+    tick_cumulative: i64,
+    time: u32
+) -> Result<i128> {
+    let info = mapping.get_mut(&tick).ok_or(eyre!("Tick {} not in mapping", tick))?;
+    info.fee_growth_outside0_x128 = fee_growth_global0_x128.wrapping_sub(info.fee_growth_outside0_x128);
+    info.fee_growth_outside1_x128 = fee_growth_global1_x128.wrapping_sub(info.fee_growth_outside1_x128);
+    info.seconds_per_liquidity_outside_x128 = seconds_per_liquidity_cumulative_x128
+        .wrapping_sub(info.seconds_per_liquidity_outside_x128) & UINT160_MASK;
+    info.tick_cumulative_outside = tick_cumulative.wrapping_sub(info.tick_cumulative_outside);
+    info.seconds_outside = time.wrapping_sub(info.seconds_outside);
+
+    Ok(info.liquidity_net)
+}
@@ -5,6 +5,7 @@ use alloy::{
 };
 
 use super::{bit_math::*, constants::U256_1, super::pool::PoolState};
+use std::collections::HashMap;
 use eyre::{eyre, Result};
 
 /// @notice Computes the position in the mapping where the initialized bit for a tick lives
@@ -93,3 +94,23 @@ pub async fn next_initialized_tick_within_one_word (
     }
 
 }
+
+/// @notice Flips the initialized state for a given tick from false to true, or vice versa
+/// @param self The mapping in which to flip the tick
+/// @param tick The tick to flip
+/// @param tickSpacing The spacing between usable ticks
+pub fn flip_tick (
+    tick_bitmap: &mut HashMap<i16, U256>,
+    tick: i32,
+    tick_spacing: i32
+) -> Result<()> {
+    if tick % tick_spacing != 0 {
+        return Err(eyre!("Tick is not a multiple of tick spacing"))
+    }
+
+    let (word_pos, bit_pos) = position(tick / tick_spacing); 
+    let mask: U256 = U256_1 << bit_pos; 
+    *tick_bitmap.entry(word_pos).or_default() ^= mask; 
+
+    Ok(())
+}
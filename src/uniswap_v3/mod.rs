@@ -3,4 +3,17 @@ pub mod math;
 pub mod quoter;
 pub mod utils; 
 pub mod multicall;  
-pub mod pool; 
\ No newline at end of file
+pub mod pool; 
+pub mod backfill;
+pub mod audit;
+pub mod position;
+pub mod position_manager;
+pub mod pnl;
+pub mod backtest;
+pub mod markout;
+pub mod twap;
+pub mod manipulation;
+pub mod flash;
+pub mod staker;
+pub mod amm;
+pub mod dex;
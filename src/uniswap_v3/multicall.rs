@@ -2,11 +2,15 @@ use alloy::{
     sol, 
     providers::RootProvider, 
     transports::http::{Client, Http}, 
-    primitives::{Address, address}
+    primitives::{Address, address}, 
+    rpc::types::eth::BlockId
 }; 
 use eyre::Result;
 use IMulticall3::Call3;
 
+// Multicall3 has the same address on every chain built into `chains`, other chains configure theirs
+pub const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
 sol! {
     #[sol(rpc)]
     interface IMulticall3 {
@@ -33,20 +37,38 @@ sol! {
     }
 }
 
+/// Calls `address` once per call data through the Multicall3 at `multicall_address`, results are in the order of the calls
 pub async fn multicall (
     provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
     address: Address, 
     allow_failure: bool, 
-    call_data_list: Vec<Vec<u8>>
+    call_data_list: Vec<Vec<u8>>, 
+    block: BlockId
+) -> Result<Vec<IMulticall3::Result>>{
+    let calls = call_data_list
+        .into_iter()
+        .map(|call_data| (address, call_data))
+        .collect();
+
+    multicall_targets(provider, multicall_address, allow_failure, calls, block).await
+}
+
+/// Same as `multicall` with its own target per call
+pub async fn multicall_targets (
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    allow_failure: bool, 
+    target_call_data_list: Vec<(Address, Vec<u8>)>, 
+    block: BlockId
 ) -> Result<Vec<IMulticall3::Result>>{
-    let multicall_address = address!("cA11bde05977b3631167028862bE2a173976CA11"); 
     let multicall = IMulticall3::new(multicall_address, provider);
 
-    let calls: Vec<Call3> = call_data_list
+    let calls: Vec<Call3> = target_call_data_list
         .into_iter()
-        .map(|call_data| {
+        .map(|(target, call_data)| {
             IMulticall3::Call3{
-                target: address, 
+                target, 
                 allowFailure: allow_failure, 
                 callData: call_data.into()
             }
@@ -58,10 +80,10 @@ pub async fn multicall (
     let mut return_data = Vec::<IMulticall3::Result>::new(); 
 
     for chunk in calls.chunks(chunk_size) {
-        match multicall.aggregate3(chunk.to_vec()).call().await? {
+        match multicall.aggregate3(chunk.to_vec()).block(block).call().await? {
             IMulticall3::aggregate3Return{returnData} => return_data.extend(returnData),
         }
     } 
 
     Ok(return_data)
-}
\ No newline at end of file
+}
@@ -0,0 +1,149 @@
+use alloy::{
+    primitives::U256,
+    providers::RootProvider,
+    transports::http::{Client, Http}
+};
+use super::{
+    math::{liquidity_amounts, tick_math},
+    pool::PoolState,
+    position::Position,
+    utils::sqrt_price_x96_to_price
+};
+use eyre::{eyre, Result};
+
+/// Unit the P&L figures are expressed in
+pub enum Numeraire {
+    Token0,
+    Token1,
+    // prices of token0 and token1 in an external unit (e.g. USD) at entry and now
+    External {
+        entry_prices: (f64, f64),
+        current_prices: (f64, f64)
+    }
+}
+
+/// P&L breakdown of a position, every value is expressed in the chosen numeraire
+#[derive(Debug)]
+pub struct PositionPnl {
+    // value of the tokens deposited at entry, priced at entry
+    pub entry_value: f64,
+    // value of the tokens the position holds now, excluding fees
+    pub position_value: f64,
+    // value of the tokens deposited at entry had they been held instead, priced now
+    pub hodl_value: f64,
+    // position value minus hodl value, negative when the position underperforms holding
+    pub impermanent_loss: f64,
+    pub impermanent_loss_pct: f64,
+    pub fees_value: f64,
+    // position value plus fees minus entry value
+    pub net_pnl: f64
+}
+
+impl PositionPnl {
+    /// Computes the P&L of `position`, opened at `entry_sqrt_price_x96`, against the price of `pool_state`.
+    /// `fees` are the uncollected token0 and token1 fees of the position.
+    pub fn new(
+        pool_state: &PoolState,
+        position: &Position,
+        entry_sqrt_price_x96: U256,
+        fees: (u128, u128),
+        numeraire: &Numeraire
+    ) -> Result<Self> {
+        let sqrt_ratio_lower_x96 = tick_math::get_sqrt_ratio_at_tick(position.tick_lower)?;
+        let sqrt_ratio_upper_x96 = tick_math::get_sqrt_ratio_at_tick(position.tick_upper)?;
+
+        let (entry_amount0, entry_amount1) = liquidity_amounts::get_amounts_for_liquidity(
+            entry_sqrt_price_x96, sqrt_ratio_lower_x96, sqrt_ratio_upper_x96, position.liquidity
+        )?;
+        let (amount0, amount1) = liquidity_amounts::get_amounts_for_liquidity(
+            pool_state.slot0.sqrt_price_x96, sqrt_ratio_lower_x96, sqrt_ratio_upper_x96, position.liquidity
+        )?;
+
+        let (decimals0, decimals1) = (pool_state.token0.decimals, pool_state.token1.decimals);
+        let to_token0 = |amount: U256| f64::from(amount) / 10f64.powi(decimals0 as i32);
+        let to_token1 = |amount: U256| f64::from(amount) / 10f64.powi(decimals1 as i32);
+
+        // price of token0 in token1 at entry and now
+        let entry_price = sqrt_price_x96_to_price(entry_sqrt_price_x96, decimals0, decimals1);
+        let current_price = sqrt_price_x96_to_price(pool_state.slot0.sqrt_price_x96, decimals0, decimals1);
+        if entry_price == 0.0 || current_price == 0.0 {
+            return Err(eyre!("Price can not be zero"))
+        }
+
+        // prices of token0 and token1 in the numeraire at entry and now
+        let (entry_prices, current_prices) = match numeraire {
+            Numeraire::Token0 => ((1.0, 1.0 / entry_price), (1.0, 1.0 / current_price)),
+            Numeraire::Token1 => ((entry_price, 1.0), (current_price, 1.0)),
+            Numeraire::External{entry_prices, current_prices} => (*entry_prices, *current_prices)
+        };
+
+        let value = |amounts: (f64, f64), prices: (f64, f64)| amounts.0 * prices.0 + amounts.1 * prices.1;
+
+        let entry_amounts = (to_token0(entry_amount0), to_token1(entry_amount1));
+        let entry_value = value(entry_amounts, entry_prices);
+        let position_value = value((to_token0(amount0), to_token1(amount1)), current_prices);
+        let hodl_value = value(entry_amounts, current_prices);
+        let fees_value = value((to_token0(U256::from(fees.0)), to_token1(U256::from(fees.1))), current_prices);
+
+        let impermanent_loss = position_value - hodl_value;
+
+        Ok(PositionPnl {
+            entry_value,
+            position_value,
+            hodl_value,
+            impermanent_loss,
+            impermanent_loss_pct: if hodl_value == 0.0 {0.0} else {impermanent_loss / hodl_value * 100.0},
+            fees_value,
+            net_pnl: position_value + fees_value - entry_value
+        })
+    }
+}
+
+/// Computes the P&L of a position including the fees it has earned since its last checkpoint
+pub async fn position_pnl(
+    provider: &RootProvider<Http<Client>>,
+    pool_state: &mut PoolState,
+    position: &Position,
+    entry_sqrt_price_x96: U256,
+    numeraire: &Numeraire
+) -> Result<PositionPnl> {
+    let fees = pool_state.uncollected_fees(provider, position).await?;
+    PositionPnl::new(pool_state, position, entry_sqrt_price_x96, fees, numeraire)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::uniswap_v3::{math::constants::Q96, position::tests::pool_state_at_price_one};
+    use super::*;
+
+    fn position() -> Position {
+        Position {tick_lower: -6000, tick_upper: 6000, liquidity: 1000000000000000000, ..Default::default()}
+    }
+
+    #[test]
+    fn unchanged_price_test() {
+        let pool_state = pool_state_at_price_one();
+        let pnl = PositionPnl::new(&pool_state, &position(), Q96, (1000000000000000000, 0), &Numeraire::Token1).unwrap();
+
+        assert_eq!(pnl.position_value, pnl.hodl_value);
+        assert_eq!(pnl.impermanent_loss, 0.0);
+        assert!((pnl.net_pnl - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn price_moved_test() {
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.slot0.tick = 2000;
+        pool_state.slot0.sqrt_price_x96 = tick_math::get_sqrt_ratio_at_tick(2000).unwrap();
+
+        let pnl = PositionPnl::new(&pool_state, &position(), Q96, (0, 0), &Numeraire::Token1).unwrap();
+
+        // concentrated liquidity always underperforms holding once the price moves
+        assert!(pnl.impermanent_loss < 0.0);
+        assert!(pnl.impermanent_loss_pct < 0.0 && pnl.impermanent_loss_pct > -100.0);
+        assert!(pnl.hodl_value > pnl.entry_value);
+
+        let pnl_token0 = PositionPnl::new(&pool_state, &position(), Q96, (0, 0), &Numeraire::Token0).unwrap();
+        assert!((pnl_token0.impermanent_loss_pct - pnl.impermanent_loss_pct).abs() < 1e-9);
+    }
+}
@@ -1,19 +1,22 @@
 use alloy::{ 
     primitives::{Address, Bytes, U256}, 
-    providers::RootProvider, 
+    providers::{Provider, RootProvider}, 
+    rpc::types::eth::{BlockId, BlockTransactionsKind}, 
     sol, 
     sol_types::SolCall, 
     transports::http::{Client, Http}
 };
 use super::{math::{
-    constants::{Q128, Q96, U256_2}, 
-    full_math::{self, mul_div}, 
+    constants::{Q96, U256_2}, 
+    full_math, 
+    oracle::Observation, 
     tick::{get_fee_growth_inside, Info}, 
     tick_math::{MAX_SQRT_RATIO, MAX_TICK, MAX_WORD_POS, MIN_SQRT_RATIO, MIN_TICK, MIN_WORD_POS}
 }, swap::sqrt};
 use std::collections::HashMap; 
 use eyre::{eyre, Result}; 
-use super::{multicall::multicall, swap, math};
+use crate::chains::ChainDeployment;
+use super::{multicall::{multicall, multicall_targets}, swap, math};
 use polars::{prelude::*, io::prelude::CsvWriter}; 
 use std::fs::File;
 
@@ -31,6 +34,35 @@ sol! {
 sol! {
     #[sol(rpc)]
     interface IPool {
+        event Swap(
+            address indexed sender,
+            address indexed recipient,
+            int256 amount0,
+            int256 amount1,
+            uint160 sqrtPriceX96,
+            uint128 liquidity,
+            int24 tick
+        );
+
+        event Mint(
+            address sender,
+            address indexed owner,
+            int24 indexed tickLower,
+            int24 indexed tickUpper,
+            uint128 amount,
+            uint256 amount0,
+            uint256 amount1
+        );
+
+        event Burn(
+            address indexed owner,
+            int24 indexed tickLower,
+            int24 indexed tickUpper,
+            uint128 amount,
+            uint256 amount0,
+            uint256 amount1
+        );
+
         function slot0()
         external
         view
@@ -76,7 +108,24 @@ sol! {
 
         function feeGrowthGlobal1X128() external view returns (uint256);
 
+        function protocolFees() external view returns (uint128 token0, uint128 token1);
+
         function tickBitmap(int16 wordPosition) external view returns (uint256);
+
+        function observations(uint256 index)
+            external
+            view
+            returns (
+                uint32 blockTimestamp,
+                int56 tickCumulative,
+                uint160 secondsPerLiquidityCumulativeX128,
+                bool initialized
+            );
+
+        function observe(uint32[] calldata secondsAgos)
+            external
+            view
+            returns (int56[] memory tickCumulatives, uint160[] memory secondsPerLiquidityCumulativeX128s);
     }
 }
 
@@ -90,30 +139,80 @@ sol! {
     }
 }
 
+impl From<IPool::ticksReturn> for Info {
+    fn from(ticks: IPool::ticksReturn) -> Self {
+        Info {
+            liquidity_gross: ticks.liquidityGross, 
+            liquidity_net: ticks.liquidityNet, 
+            fee_growth_outside0_x128: ticks.feeGrowthOutside0X128, 
+            fee_growth_outside1_x128: ticks.feeGrowthOutside1X128, 
+            tick_cumulative_outside: ticks.tickCumulativeOutside,
+            seconds_per_liquidity_outside_x128: ticks.secondsPerLiquidityOutsideX128,
+            seconds_outside: ticks.secondsOutside,
+            initialized: ticks.initialized
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Slot0 {
     pub sqrt_price_x96: U256,
     pub tick: i32,
+    // the most-recently updated index of the observations array
+    pub observation_index: u16,
+    // the current maximum number of observations that are being stored
+    pub observation_cardinality: u16,
+    // the next maximum number of observations to store, triggered in observations.write
+    pub observation_cardinality_next: u16,
+    // the current protocol fee as a percentage of the swap fee taken on withdrawal
+    // represented as an integer denominator (1/x)%
+    pub fee_protocol: u8,
     pub unlocked: bool
 }
 
+impl Slot0 {
+    /// Protocol fee denominator applied to swaps in the given direction, 0 when the fee switch is off
+    pub fn protocol_fee_denominator(&self, zero_for_one: bool) -> u8 {
+        if zero_for_one {self.fee_protocol % 16} else {self.fee_protocol >> 4}
+    }
+}
+
+/// Accumulated protocol fees in token0/token1 units
+#[derive(Default, Clone)]
+pub struct ProtocolFees {
+    pub token0: u128,
+    pub token1: u128
+}
+
+#[derive(Clone)]
 pub struct Token {
     pub address: Address, 
     pub symbol: String, 
     pub decimals: u8
 }
 
+#[derive(Clone)]
 pub struct PoolState {
     pub pool_address: Address,
+    // Multicall3 the state is loaded through, lazy tick and bitmap loads use it too
+    pub multicall_address: Address,
     pub tick_spacing: i32, 
     pub fee: u32, 
     pub fee_growth_global0_x128: U256, 
     pub fee_growth_global1_x128: U256, 
+    pub protocol_fees: ProtocolFees, 
     pub token0: Token, 
     pub token1: Token, 
     pub tick_bitmap: HashMap<i16, U256>, 
     pub slot0: Slot0, 
     pub liquidity: u128,
-    pub ticks: HashMap<i32, Info>
+    pub ticks: HashMap<i32, Info>, 
+    // oracle ring buffer, empty until loaded with `load_observations`
+    pub observations: HashMap<u16, Observation>,
+    // block the state was read at, lazy tick and bitmap loads are pinned to it
+    pub block: BlockId,
+    // timestamp of that block truncated to 32 bits, used as the time of simulated oracle writes
+    pub block_timestamp: u32
 }
 
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -139,15 +238,38 @@ pub enum LoadingPattern {
 impl PoolState {
     pub async fn load (
         provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
         pool_factory_address: Address, 
         pair: (Address, Address),
         fee: u32, 
         loading_pattern: LoadingPattern
     ) -> Result<Self> {
-        let pool_address = get_pool_address(provider, pool_factory_address, pair, fee).await?;
+        Self::load_at_block(provider, multicall_address, pool_factory_address, pair, fee, loading_pattern, BlockId::latest()).await
+    }
+
+    pub async fn load_at_block (
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_factory_address: Address, 
+        pair: (Address, Address),
+        fee: u32, 
+        loading_pattern: LoadingPattern, 
+        block: BlockId
+    ) -> Result<Self> {
+        let pool_address = get_pool_address(provider, pool_factory_address, pair, fee, block).await?;
         println!("Pool address {}",pool_address);
-    
-        let (slot0, tick_spacing, liquidity, fee, token0_address, token1_address, fee_growth_global0_x128, fee_growth_global1_x128) = {
+
+        Self::load_from_address_at_block(provider, multicall_address, pool_address, loading_pattern, block).await
+    }
+
+    pub async fn load_from_address_at_block (
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_address: Address, 
+        loading_pattern: LoadingPattern, 
+        block: BlockId
+    ) -> Result<Self> {
+        let (slot0, tick_spacing, liquidity, fee, token0_address, token1_address, fee_growth_global0_x128, fee_growth_global1_x128, protocol_fees) = {
     
             let encoded_calls = vec![
                 IPool::slot0Call{}.abi_encode(), 
@@ -158,9 +280,10 @@ impl PoolState {
                 IPool::token1Call{}.abi_encode(), 
                 IPool::feeGrowthGlobal0X128Call{}.abi_encode(),
                 IPool::feeGrowthGlobal1X128Call{}.abi_encode(),
+                IPool::protocolFeesCall{}.abi_encode(),
             ]; 
     
-            let encoded_return_data: Vec<Bytes> = multicall(provider, pool_address, true, encoded_calls).await?
+            let encoded_return_data: Vec<Bytes> = multicall(provider, multicall_address, pool_address, true, encoded_calls, block).await?
             .into_iter()
             .map(|result| {
                 result.returnData
@@ -171,11 +294,19 @@ impl PoolState {
                 IPool::slot0Return {
                     sqrtPriceX96, 
                     tick,
-                    unlocked,..
+                    observationIndex, 
+                    observationCardinality, 
+                    observationCardinalityNext, 
+                    feeProtocol, 
+                    unlocked
                 } => {
                     Slot0 {
                         sqrt_price_x96: sqrtPriceX96,
                         tick: tick,
+                        observation_index: observationIndex, 
+                        observation_cardinality: observationCardinality, 
+                        observation_cardinality_next: observationCardinalityNext, 
+                        fee_protocol: feeProtocol, 
                         unlocked: unlocked
                     }
                 }
@@ -190,6 +321,9 @@ impl PoolState {
                 IPool::token1Call::abi_decode_returns(&encoded_return_data[5], true)?._0, 
                 IPool::feeGrowthGlobal0X128Call::abi_decode_returns(&encoded_return_data[6], true)?._0,
                 IPool::feeGrowthGlobal1X128Call::abi_decode_returns(&encoded_return_data[7], true)?._0,
+                match IPool::protocolFeesCall::abi_decode_returns(&encoded_return_data[8], true)? {
+                    IPool::protocolFeesReturn {token0, token1} => ProtocolFees {token0, token1}
+                },
             )
         };
 
@@ -217,21 +351,26 @@ impl PoolState {
     
         let ticks: HashMap<i32, Info> = Self::get_ticks(
             provider, 
+            multicall_address, 
             pool_address, 
             slot0.tick, 
             tick_spacing, 
-            &loading_pattern
+            &loading_pattern, 
+            block
         ).await?;
     
         let tick_bitmap: HashMap<i16, U256> = Self::get_tick_bitmap(
             provider, 
+            multicall_address, 
             pool_address, 
             word_pos, 
-            &loading_pattern
+            &loading_pattern, 
+            block
         ).await?; 
     
         Ok(PoolState{
             pool_address, 
+            multicall_address, 
             tick_spacing, 
             fee, 
             token0, 
@@ -241,16 +380,22 @@ impl PoolState {
             liquidity, 
             ticks, 
             fee_growth_global0_x128, 
-            fee_growth_global1_x128
+            fee_growth_global1_x128, 
+            protocol_fees, 
+            observations: HashMap::new(), 
+            block, 
+            block_timestamp: get_block_timestamp(provider, block).await? as u32
         })
     }
 
     pub async fn get_ticks (
         provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
         pool_address: Address ,
         tick: i32, 
         tick_spacing: i32, 
-        load: &LoadingPattern 
+        load: &LoadingPattern, 
+        block: BlockId
     ) -> Result<HashMap<i32, Info>>{
         let compressed = tick / tick_spacing; 
         let min_compressed = MIN_TICK / tick_spacing; 
@@ -284,27 +429,11 @@ impl PoolState {
         })
         .collect(); 
         
-        let return_data = multicall(provider, pool_address, false, liqudity_tickmap_call_data).await?;
+        let return_data = multicall(provider, multicall_address, pool_address, false, liqudity_tickmap_call_data, block).await?;
 
         let mut map = HashMap::new();
         for (tick, data) in tick_list.into_iter().zip(return_data.iter()) {
-            let info: Info = match IPool::ticksCall::abi_decode_returns(&data.returnData, true)? {
-                IPool::ticksReturn{
-                    liquidityGross, 
-                    liquidityNet, 
-                    feeGrowthOutside0X128, 
-                    feeGrowthOutside1X128, 
-                    initialized, ..
-                } => {
-                    Info {
-                        liquidity_gross : liquidityGross, 
-                        liquidity_net: liquidityNet, 
-                        fee_growth_outside0_x128: feeGrowthOutside0X128, 
-                        fee_growth_outside1_x128: feeGrowthOutside1X128, 
-                        initialized: initialized
-                    }
-                }
-            };
+            let info: Info = IPool::ticksCall::abi_decode_returns(&data.returnData, true)?.into();
             map.insert(tick, info);
         } 
 
@@ -322,15 +451,31 @@ impl PoolState {
             LoadingPattern::HIGH
         }; 
 
-        self.ticks = Self::get_ticks(provider, self.pool_address, next_tick, self.tick_spacing, &load).await?;
+        // ticks already in memory are kept, they may carry simulated mints and burns
+        for (tick, info) in Self::get_ticks(provider, self.multicall_address, self.pool_address, next_tick, self.tick_spacing, &load, self.block).await? {
+            self.ticks.entry(tick).or_insert(info);
+        }
+        Ok(())
+    }
+
+    pub async fn load_tick (
+        &mut self,
+        provider: &RootProvider<Http<Client>>, 
+        tick: i32
+    ) -> Result<()> {
+        let pool = IPool::new(self.pool_address, provider);
+        let info: Info = pool.ticks(tick).block(self.block).call().await?.into();
+        self.ticks.insert(tick, info);
         Ok(())
     }
 
     pub async fn get_tick_bitmap (
         provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
         pool_address: Address ,
         word_pos: i16,
-        load: &LoadingPattern 
+        load: &LoadingPattern, 
+        block: BlockId
     ) -> Result<HashMap<i16, U256>>{ 
         // Generate word position list for tick bitmap
         let word_pos_list: Vec<i16> = match load {
@@ -361,7 +506,7 @@ impl PoolState {
         })
         .collect(); 
 
-        let return_data = multicall(provider, pool_address, false, tick_bitmap_call_data).await?;
+        let return_data = multicall(provider, multicall_address, pool_address, false, tick_bitmap_call_data, block).await?;
 
         let mut map = HashMap::new();
         for (tick, data) in word_pos_list.into_iter().zip(return_data.iter()) {
@@ -390,7 +535,20 @@ impl PoolState {
             LoadingPattern::HIGH
         }; 
 
-        self.tick_bitmap = Self::get_tick_bitmap(provider, self.pool_address, word_pos, &load).await?;
+        for (word_pos, word) in Self::get_tick_bitmap(provider, self.multicall_address, self.pool_address, word_pos, &load, self.block).await? {
+            self.tick_bitmap.entry(word_pos).or_insert(word);
+        }
+        Ok(())
+    }
+
+    pub async fn load_tick_bitmap_word (
+        &mut self,
+        provider: &RootProvider<Http<Client>>, 
+        word_pos: i16
+    ) -> Result<()> {
+        let pool = IPool::new(self.pool_address, provider);
+        let word = pool.tickBitmap(word_pos).block(self.block).call().await?._0;
+        self.tick_bitmap.insert(word_pos, word);
         Ok(())
     }
 
@@ -402,8 +560,8 @@ impl PoolState {
         let mut tick = Vec::<i32>::new(); 
         let mut liquidity_net = Vec::<String>::new(); 
         let mut liquidity_gross = Vec::<String>::new(); 
-        let mut fee_inside0 = Vec::<String>::new(); 
-        let mut fee_inside1 = Vec::<String>::new(); 
+        let mut fee_growth_inside0 = Vec::<Option<String>>::new(); 
+        let mut fee_growth_inside1 = Vec::<Option<String>>::new(); 
 
         let token0_decimals = self.token0.decimals as u32; 
         let token1_decimals = self.token1.decimals as u32; 
@@ -415,39 +573,36 @@ impl PoolState {
             tick.push(*_tick); 
             liquidity_net.push(info.liquidity_net.to_string()); 
             liquidity_gross.push(info.liquidity_gross.to_string()); 
-            let lower_tick = _tick; 
+            // fee growth per unit of liquidity inside [tick, tick + tick_spacing), only defined when both ticks are initialized
             let upper_tick = _tick + self.tick_spacing; 
-            let (fee_growth_inside0_x128, fee_growth_inside1_x128) = match ticks.get(&upper_tick) {
-                Some(upper_info) => {
-                    get_fee_growth_inside(
-                        lower_tick, 
+            match ticks.get(&upper_tick) {
+                Some(upper_info) if info.initialized && upper_info.initialized => {
+                    let (fee_growth_inside0_x128, fee_growth_inside1_x128) = get_fee_growth_inside(
+                        _tick, 
                         &upper_tick, 
                         info, 
                         upper_info, 
                         &self.slot0.tick, 
                         self.fee_growth_global0_x128, 
                         self.fee_growth_global1_x128
-                    )?
+                    )?;
+                    fee_growth_inside0.push(Some(fee_growth_inside0_x128.to_string())); 
+                    fee_growth_inside1.push(Some(fee_growth_inside1_x128.to_string())); 
                 }, 
-                None => {
-                    (U256::ZERO, U256::ZERO)
+                _ => {
+                    fee_growth_inside0.push(None); 
+                    fee_growth_inside1.push(None); 
                 }
             }; 
-            
-            let _fee_inside0 = mul_div(fee_growth_inside0_x128, U256::from(info.liquidity_gross), Q128)?; 
-            let _fee_inside1 = mul_div(fee_growth_inside1_x128, U256::from(info.liquidity_gross), Q128)?;  
-            println!("good");
-            fee_inside0.push(_fee_inside0.to_string()); 
-            fee_inside1.push(_fee_inside1.to_string()); 
         }
 
         let tick_series = Series::new("tick", tick); 
         let liquidity_net_series = Series::new("liquidity_net", liquidity_net); 
         let liquidity_gross_series = Series::new("liquidity_gross", liquidity_gross); 
-        let fee_inside0_series = Series::new("fee_inside_0", fee_inside0);
-        let fee_inside1_series = Series::new("fee_inside_1", fee_inside1); 
+        let fee_growth_inside0_series = Series::new("fee_growth_inside_0_x128", fee_growth_inside0);
+        let fee_growth_inside1_series = Series::new("fee_growth_inside_1_x128", fee_growth_inside1); 
 
-        let series_vector = vec![tick_series, liquidity_net_series, liquidity_gross_series, fee_inside0_series, fee_inside1_series]; 
+        let series_vector = vec![tick_series, liquidity_net_series, liquidity_gross_series, fee_growth_inside0_series, fee_growth_inside1_series]; 
 
         let mut df = DataFrame::new(series_vector)?; 
         let mut file = File::create("example.csv").expect("could not create file");
@@ -459,29 +614,73 @@ impl PoolState {
     }
 }
 
+/// Symbol and decimals of every distinct token in `addresses`, loaded with a single multicall
+pub async fn load_tokens(
+    provider: &RootProvider<Http<Client>>,
+    multicall_address: Address,
+    mut addresses: Vec<Address>,
+    block: BlockId
+) -> Result<HashMap<Address, Token>> {
+    addresses.sort();
+    addresses.dedup();
+
+    let call_data: Vec<(Address, Vec<u8>)> = addresses
+        .iter()
+        .flat_map(|&address| [
+            (address, IERC20::symbolCall{}.abi_encode()),
+            (address, IERC20::decimalsCall{}.abi_encode())
+        ])
+        .collect();
+    let return_data = multicall_targets(provider, multicall_address, false, call_data, block).await?;
+
+    let mut tokens = HashMap::new();
+    for (&address, data) in addresses.iter().zip(return_data.chunks(2)) {
+        tokens.insert(address, Token {
+            address,
+            symbol: IERC20::symbolCall::abi_decode_returns(&data[0].returnData, true)?._0,
+            decimals: IERC20::decimalsCall::abi_decode_returns(&data[1].returnData, true)?._0
+        });
+    }
+
+    Ok(tokens)
+}
+
+pub async fn get_block_timestamp(
+    provider: &RootProvider<Http<Client>>,
+    block: BlockId
+) -> Result<u64> {
+    let block = match block {
+        BlockId::Number(number) => provider.get_block_by_number(number, false).await?, 
+        BlockId::Hash(hash) => provider.get_block_by_hash(hash.block_hash, BlockTransactionsKind::Hashes).await?
+    };
+
+    Ok(block.ok_or(eyre!("Block not found"))?.header.timestamp)
+}
+
 pub async fn get_pool_address(
     provider: &RootProvider<Http<Client>>, 
     pool_factory_address: Address, 
     pair: (Address, Address),
-    fee: u32
+    fee: u32, 
+    block: BlockId
 ) -> Result<Address> {
 
     let pool_factory = IPoolFactory::new(pool_factory_address, provider);
 
-    match pool_factory.getPool(pair.0, pair.1, fee).call().await? {
+    match pool_factory.getPool(pair.0, pair.1, fee).block(block).call().await? {
         IPoolFactory::getPoolReturn {pool} => if pool != Address::ZERO {Ok(pool)} else {Err(eyre!("Pool not found for pair: {:?} and fee: {}", pair, fee))},
     }
 }
 
 pub async fn simulate_exact_input_single(
     provider: &RootProvider<Http<Client>>, 
-    pool_factory_address: Address, 
+    deployment: &ChainDeployment, 
     pair: (Address, Address), 
     amount_in: U256,
     one_for_two: bool
 ) -> Result<SwapResult> {
 
-    let mut pool_state = PoolState::load(provider, pool_factory_address, pair, 10000, LoadingPattern::MID).await?; 
+    let mut pool_state = PoolState::load(provider, deployment.multicall, deployment.factory, pair, 10000, LoadingPattern::MID).await?; 
 
     let zero_for_one = if pair.0 == pool_state.token0.address {one_for_two} else {!one_for_two}; 
     let (amount0, amount1) = swap::swap(
@@ -499,13 +698,13 @@ pub async fn simulate_exact_input_single(
 
 pub async fn simulate_swap_slippage(
     provider: &RootProvider<Http<Client>>, 
-    pool_factory_address: Address, 
+    deployment: &ChainDeployment, 
     pair: (Address, Address),
     one_for_two: bool, 
     price_impact: u32
 ) -> Result<SwapResultSlippage> {
 
-    let mut pool_state = PoolState::load(provider, pool_factory_address, pair, 10000, LoadingPattern::MID).await?; 
+    let mut pool_state = PoolState::load(provider, deployment.multicall, deployment.factory, pair, 10000, LoadingPattern::MID).await?; 
 
     let zero_for_one = if pair.0 == pool_state.token0.address {one_for_two} else {!one_for_two}; 
     let ((amount0, amount1), state_exec_sqrt_price_x96) = swap::swap_slippage(
@@ -541,7 +740,7 @@ pub async fn simulate_swap_slippage(
 mod tests {
     use alloy::{
         primitives::{address, U256}, providers::ProviderBuilder}; 
-    use crate::uniswap_v3::{utils::UNISWAP_V3_POOL_FACTORY_ADDRESS, quoter};
+    use crate::{chains::ChainRegistry, uniswap_v3::quoter};
     use super::*; 
 
     #[tokio::test]
@@ -555,9 +754,11 @@ mod tests {
 
         let amount_in = U256::from(20000000000000000 as u128); 
 
+        let registry = ChainRegistry::default();
+        let deployment = registry.for_provider(&provider).await.unwrap();
         assert_eq!(
-            simulate_exact_input_single(&provider, UNISWAP_V3_POOL_FACTORY_ADDRESS, (weth, usdc), amount_in, false).await.unwrap(), 
-            quoter::_quote_exact_input_single(&provider, (weth, usdc), amount_in, false).await.unwrap()
+            simulate_exact_input_single(&provider, deployment, (weth, usdc), amount_in, false).await.unwrap(), 
+            quoter::_quote_exact_input_single(&provider, deployment, (weth, usdc), amount_in, false).await.unwrap()
         );  
     }
 
@@ -571,14 +772,16 @@ mod tests {
         let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
 
         let mut price_impact = 10; 
+        let registry = ChainRegistry::default();
+        let deployment = registry.for_provider(&provider).await.unwrap();
 
-        let mut swap_result = simulate_swap_slippage(&provider, UNISWAP_V3_POOL_FACTORY_ADDRESS, (weth, usdc), true, price_impact).await.unwrap(); 
+        let mut swap_result = simulate_swap_slippage(&provider, deployment, (weth, usdc), true, price_impact).await.unwrap(); 
         println!("Swap Result : {:?}", swap_result); 
         //assert less than 1% difference between executed price impact and initial price impact
         assert!(swap_result.price_impact - U256::from(price_impact * 1000) < U256::from(1000));  
 
         price_impact = 20;
-        swap_result = simulate_swap_slippage(&provider, UNISWAP_V3_POOL_FACTORY_ADDRESS, (weth, usdc), true, price_impact).await.unwrap(); 
+        swap_result = simulate_swap_slippage(&provider, deployment, (weth, usdc), true, price_impact).await.unwrap(); 
         println!("Swap Result : {:?}", swap_result); 
         //assert less than 1% difference between executed price impact and initial price impact
         assert!(swap_result.price_impact - U256::from(price_impact * 1000) < U256::from(1000));  
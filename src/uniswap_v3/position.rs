@@ -0,0 +1,510 @@
+use alloy::{
+    primitives::{I256, U256},
+    providers::RootProvider,
+    transports::http::{Client, Http}
+};
+use super::{
+    math::{constants::Q128, full_math, liquidity_math, oracle::UINT160_MASK, sqrt_price_math, tick, tick_bitmap, tick_math::{self, MAX_TICK, MIN_TICK}},
+    pool::PoolState
+};
+use eyre::{eyre, Result};
+
+/// Position.Info together with the tick range it is keyed by
+#[derive(Default, Clone, Debug)]
+pub struct Position {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    // the amount of liquidity owned by this position
+    pub liquidity: u128,
+    // fee growth per unit of liquidity as of the last update to liquidity or fees owed
+    pub fee_growth_inside0_last_x128: U256,
+    pub fee_growth_inside1_last_x128: U256,
+    // the fees owed to the position owner in token0/token1
+    pub tokens_owed0: u128,
+    pub tokens_owed1: u128
+}
+
+impl Position {
+    /// @notice Computes the fees owed to the position given the current fee growth inside its range, including tokens already owed
+    /// @dev Mirrors Position.update: the fee growth delta is unchecked and the result is truncated to uint128, overflow is
+    /// acceptable as positions have to withdraw before hitting type(uint128).max fees
+    /// @param feeGrowthInside0X128 The all-time fee growth in token0, per unit of liquidity, inside the position's tick boundaries
+    /// @param feeGrowthInside1X128 The all-time fee growth in token1, per unit of liquidity, inside the position's tick boundaries
+    /// @return tokensOwed0 The fees owed in token0
+    /// @return tokensOwed1 The fees owed in token1
+    pub fn fees_owed(
+        &self,
+        fee_growth_inside0_x128: U256,
+        fee_growth_inside1_x128: U256
+    ) -> Result<(u128, u128)> {
+        let tokens_owed0: u128 = full_math::mul_div(
+            fee_growth_inside0_x128.wrapping_sub(self.fee_growth_inside0_last_x128),
+            U256::from(self.liquidity),
+            Q128
+        )?.wrapping_to();
+        let tokens_owed1: u128 = full_math::mul_div(
+            fee_growth_inside1_x128.wrapping_sub(self.fee_growth_inside1_last_x128),
+            U256::from(self.liquidity),
+            Q128
+        )?.wrapping_to();
+
+        Ok((self.tokens_owed0.wrapping_add(tokens_owed0), self.tokens_owed1.wrapping_add(tokens_owed1)))
+    }
+}
+
+/// @dev Common checks for valid tick inputs.
+pub fn check_ticks(tick_lower: i32, tick_upper: i32) -> Result<()> {
+    if tick_lower >= tick_upper {
+        return Err(eyre!("TLU"))
+    }
+    if tick_lower < MIN_TICK {
+        return Err(eyre!("TLM"))
+    }
+    if tick_upper > MAX_TICK {
+        return Err(eyre!("TUM"))
+    }
+    Ok(())
+}
+
+impl PoolState {
+    /// Simulates `UniswapV3Pool.mint` and returns the amounts of token0 and token1 owed to the pool for the minted liquidity
+    pub async fn mint(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        tick_lower: i32,
+        tick_upper: i32,
+        amount: u128
+    ) -> Result<(U256, U256)> {
+        if amount == 0 {
+            return Err(eyre!("Amount is zero, no mint"))
+        }
+
+        let (amount0, amount1) = self.modify_position(provider, tick_lower, tick_upper, i128::try_from(amount)?).await?;
+
+        Ok((amount0.into_raw(), amount1.into_raw()))
+    }
+
+    /// Simulates `UniswapV3Pool.burn` and returns the amounts of token0 and token1 owed to the position for the burned liquidity
+    pub async fn burn(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        tick_lower: i32,
+        tick_upper: i32,
+        amount: u128
+    ) -> Result<(U256, U256)> {
+        let (amount0, amount1) = self.modify_position(provider, tick_lower, tick_upper, -i128::try_from(amount)?).await?;
+
+        Ok((amount0.unsigned_abs(), amount1.unsigned_abs()))
+    }
+
+    /// @dev Effect some changes to a position
+    /// @param tickLower the lower tick of the position's tick range
+    /// @param tickUpper the upper tick of the position's tick range
+    /// @param liquidityDelta the change to the position's liquidity to effect
+    /// @return amount0 the amount of token0 owed to the pool, negative if the pool should pay the recipient
+    /// @return amount1 the amount of token1 owed to the pool, negative if the pool should pay the recipient
+    pub async fn modify_position(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128
+    ) -> Result<(I256, I256)> {
+        check_ticks(tick_lower, tick_upper)?;
+        if tick_lower % self.tick_spacing != 0 || tick_upper % self.tick_spacing != 0 {
+            return Err(eyre!("Ticks are not multiples of tick spacing"))
+        }
+
+        // boundary ticks and their bitmap words have to be in memory before they are updated
+        self.load_missing_ticks(provider, tick_lower, tick_upper).await?;
+        for tick in [tick_lower, tick_upper] {
+            let (word_pos, _) = tick_bitmap::position(tick / self.tick_spacing);
+            if !self.tick_bitmap.contains_key(&word_pos) {
+                self.load_tick_bitmap_word(provider, word_pos).await?;
+            }
+        }
+
+        self.update_position(tick_lower, tick_upper, liquidity_delta)?;
+
+        let mut amount0 = I256::ZERO;
+        let mut amount1 = I256::ZERO;
+
+        if liquidity_delta != 0 {
+            let sqrt_ratio_lower_x96 = tick_math::get_sqrt_ratio_at_tick(tick_lower)?;
+            let sqrt_ratio_upper_x96 = tick_math::get_sqrt_ratio_at_tick(tick_upper)?;
+
+            if self.slot0.tick < tick_lower {
+                // current tick is below the passed range; liquidity can only become in range by crossing from left to
+                // right, when we'll need _more_ token0 (it's becoming more valuable) so user must provide it
+                amount0 = sqrt_price_math::_get_amount0_delta(sqrt_ratio_lower_x96, sqrt_ratio_upper_x96, liquidity_delta)?;
+            } else if self.slot0.tick < tick_upper {
+                // current tick is inside the passed range, write an oracle entry
+                self.write_observation(self.slot0.tick, self.liquidity);
+
+                amount0 = sqrt_price_math::_get_amount0_delta(self.slot0.sqrt_price_x96, sqrt_ratio_upper_x96, liquidity_delta)?;
+                amount1 = sqrt_price_math::_get_amount1_delta(sqrt_ratio_lower_x96, self.slot0.sqrt_price_x96, liquidity_delta)?;
+
+                self.liquidity = liquidity_math::add_delta(self.liquidity, liquidity_delta)?;
+            } else {
+                // current tick is above the passed range; liquidity can only become in range by crossing from right to
+                // left, when we'll need _more_ token1 (it's becoming more valuable) so user must provide it
+                amount1 = sqrt_price_math::_get_amount1_delta(sqrt_ratio_lower_x96, sqrt_ratio_upper_x96, liquidity_delta)?;
+            }
+        }
+
+        Ok((amount0, amount1))
+    }
+
+    /// Fee growth per unit of liquidity inside `[tick_lower, tick_upper)`, loading the boundary ticks if they are not in memory
+    pub async fn fee_growth_inside(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        tick_lower: i32,
+        tick_upper: i32
+    ) -> Result<(U256, U256)> {
+        check_ticks(tick_lower, tick_upper)?;
+        self.load_missing_ticks(provider, tick_lower, tick_upper).await?;
+
+        tick::get_fee_growth_inside(
+            &tick_lower,
+            &tick_upper,
+            &self.ticks[&tick_lower],
+            &self.ticks[&tick_upper],
+            &self.slot0.tick,
+            self.fee_growth_global0_x128,
+            self.fee_growth_global1_x128
+        )
+    }
+
+    /// Fees earned by the position since its last checkpoint plus the tokens it is already owed
+    pub async fn uncollected_fees(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        position: &Position
+    ) -> Result<(u128, u128)> {
+        let (fee_growth_inside0_x128, fee_growth_inside1_x128) = self.fee_growth_inside(provider, position.tick_lower, position.tick_upper).await?;
+        position.fees_owed(fee_growth_inside0_x128, fee_growth_inside1_x128)
+    }
+
+    /// @notice Returns a snapshot of the tick cumulative, seconds per liquidity and seconds inside a tick range
+    /// @dev Snapshots must only be compared to other snapshots, taken over a period for which a position existed.
+    /// I.e., snapshots cannot be compared if a position is not held for the entire period between when the first
+    /// snapshot is taken and the second snapshot is taken. Boundary ticks are loaded if they are not in memory and the
+    /// observations have to be loaded when the range is active.
+    /// @param tickLower The lower tick of the range
+    /// @param tickUpper The upper tick of the range
+    /// @return tickCumulativeInside The snapshot of the tick accumulator for the range
+    /// @return secondsPerLiquidityInsideX128 The snapshot of seconds per liquidity for the range
+    /// @return secondsInside The snapshot of seconds per liquidity for the range
+    pub async fn snapshot_cumulatives_inside(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        tick_lower: i32,
+        tick_upper: i32
+    ) -> Result<(i64, U256, u32)> {
+        check_ticks(tick_lower, tick_upper)?;
+        self.load_missing_ticks(provider, tick_lower, tick_upper).await?;
+
+        let lower = &self.ticks[&tick_lower];
+        let upper = &self.ticks[&tick_upper];
+        if !lower.initialized || !upper.initialized {
+            return Err(eyre!("NI"))
+        }
+
+        if self.slot0.tick < tick_lower {
+            Ok((
+                lower.tick_cumulative_outside.wrapping_sub(upper.tick_cumulative_outside),
+                lower.seconds_per_liquidity_outside_x128.wrapping_sub(upper.seconds_per_liquidity_outside_x128) & UINT160_MASK,
+                lower.seconds_outside.wrapping_sub(upper.seconds_outside)
+            ))
+        } else if self.slot0.tick < tick_upper {
+            if self.observations.is_empty() {
+                return Err(eyre!("Observations are not loaded"))
+            }
+            let (tick_cumulative, seconds_per_liquidity_cumulative_x128) = self.current_cumulatives()?;
+            Ok((
+                tick_cumulative.wrapping_sub(lower.tick_cumulative_outside).wrapping_sub(upper.tick_cumulative_outside),
+                seconds_per_liquidity_cumulative_x128
+                    .wrapping_sub(lower.seconds_per_liquidity_outside_x128)
+                    .wrapping_sub(upper.seconds_per_liquidity_outside_x128) & UINT160_MASK,
+                self.block_timestamp.wrapping_sub(lower.seconds_outside).wrapping_sub(upper.seconds_outside)
+            ))
+        } else {
+            Ok((
+                upper.tick_cumulative_outside.wrapping_sub(lower.tick_cumulative_outside),
+                upper.seconds_per_liquidity_outside_x128.wrapping_sub(lower.seconds_per_liquidity_outside_x128) & UINT160_MASK,
+                upper.seconds_outside.wrapping_sub(lower.seconds_outside)
+            ))
+        }
+    }
+
+    async fn load_missing_ticks(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        tick_lower: i32,
+        tick_upper: i32
+    ) -> Result<()> {
+        for tick in [tick_lower, tick_upper] {
+            if !self.ticks.contains_key(&tick) {
+                self.load_tick(provider, tick).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// @dev Gets and updates a position with the given liquidity delta
+    /// @param tickLower the lower tick of the position's tick range
+    /// @param tickUpper the upper tick of the position's tick range
+    /// Both boundary ticks are expected to be loaded, see `modify_position`
+    pub fn update_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128
+    ) -> Result<()> {
+        if liquidity_delta == 0 {
+            return Ok(())
+        }
+
+        let time = self.block_timestamp;
+        let (tick_cumulative, seconds_per_liquidity_cumulative_x128) = self.current_cumulatives()?;
+        let max_liquidity_per_tick = tick::_tick_spacing_to_max_liquidity_per_tick(self.tick_spacing);
+
+        // the pool reverts the whole mint if either tick ends up above the max, so both are checked before any is updated.
+        // liquidity net cannot overflow afterwards as its absolute value is bounded by liquidity gross.
+        for tick in [tick_lower, tick_upper] {
+            let liquidity_gross_before = self.ticks.get(&tick).map_or(0, |info| info.liquidity_gross);
+            if liquidity_math::add_delta(liquidity_gross_before, liquidity_delta)? > max_liquidity_per_tick {
+                return Err(eyre!("Liquidity gross larger than max liquidity"))
+            }
+        }
+
+        let flipped_lower = tick::_update(
+            &mut self.ticks,
+            tick_lower,
+            self.slot0.tick,
+            liquidity_delta,
+            self.fee_growth_global0_x128,
+            self.fee_growth_global1_x128,
+            seconds_per_liquidity_cumulative_x128,
+            tick_cumulative,
+            time,
+            false,
+            max_liquidity_per_tick
+        )?;
+        let flipped_upper = tick::_update(
+            &mut self.ticks,
+            tick_upper,
+            self.slot0.tick,
+            liquidity_delta,
+            self.fee_growth_global0_x128,
+            self.fee_growth_global1_x128,
+            seconds_per_liquidity_cumulative_x128,
+            tick_cumulative,
+            time,
+            true,
+            max_liquidity_per_tick
+        )?;
+
+        if flipped_lower {
+            tick_bitmap::flip_tick(&mut self.tick_bitmap, tick_lower, self.tick_spacing)?;
+        }
+        if flipped_upper {
+            tick_bitmap::flip_tick(&mut self.tick_bitmap, tick_upper, self.tick_spacing)?;
+        }
+
+        // clear any tick data that is no longer needed
+        if liquidity_delta < 0 {
+            if flipped_lower {
+                tick::clear(&mut self.ticks, tick_lower);
+            }
+            if flipped_upper {
+                tick::clear(&mut self.ticks, tick_upper);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloy::{primitives::{address, Address}, rpc::types::eth::BlockId};
+    use std::collections::HashMap;
+    use crate::{
+        test_utils::{offline_provider, token},
+        uniswap_v3::{math::{constants::Q96, liquidity_amounts, oracle}, multicall::MULTICALL3_ADDRESS, pool::Slot0, swap}
+    };
+    use super::*;
+
+    pub(crate) fn pool_state_at_price_one() -> PoolState {
+        let mut ticks = HashMap::new();
+        let mut tick_bitmap = HashMap::new();
+        for tick in [-600, 600] {
+            ticks.insert(tick, Default::default());
+            tick_bitmap.insert(tick_bitmap::position(tick / 60).0, U256::ZERO);
+        }
+
+        PoolState {
+            pool_address: Address::ZERO,
+            multicall_address: MULTICALL3_ADDRESS,
+            tick_spacing: 60,
+            fee: 3000,
+            fee_growth_global0_x128: U256::ZERO,
+            fee_growth_global1_x128: U256::ZERO,
+            protocol_fees: Default::default(),
+            token0: token(address!("0000000000000000000000000000000000000001"), 18),
+            token1: token(address!("0000000000000000000000000000000000000002"), 18),
+            tick_bitmap,
+            slot0: Slot0 {
+                sqrt_price_x96: Q96,
+                tick: 0,
+                observation_index: 0,
+                observation_cardinality: 0,
+                observation_cardinality_next: 0,
+                fee_protocol: 0,
+                unlocked: true
+            },
+            liquidity: 0,
+            ticks,
+            observations: HashMap::new(),
+            block: BlockId::latest(),
+            block_timestamp: 0
+        }
+    }
+
+    /// `pool_state_at_price_one` with 1000 of liquidity between ticks -600 and 600, the ticks and bitmap words around
+    /// the price are preloaded
+    pub(crate) async fn pool_state_with_liquidity() -> PoolState {
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.mint(&offline_provider(), -600, 600, 1000000000000000000000).await.unwrap();
+        pool_state
+    }
+
+    #[tokio::test]
+    async fn mint_and_burn_test() {
+        // ticks are preloaded
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        let liquidity: u128 = 1000000000000000000;
+
+        let (amount0, amount1) = pool_state.mint(&provider, -600, 600, liquidity).await.unwrap();
+        let (expected0, expected1) = liquidity_amounts::get_amounts_for_liquidity(
+            Q96,
+            tick_math::get_sqrt_ratio_at_tick(-600).unwrap(),
+            tick_math::get_sqrt_ratio_at_tick(600).unwrap(),
+            liquidity
+        ).unwrap();
+
+        // minting rounds up in favor of the pool
+        assert_eq!(amount0, expected0 + U256::from(1));
+        assert_eq!(amount1, expected1 + U256::from(1));
+        assert_eq!(pool_state.liquidity, liquidity);
+        assert_eq!(pool_state.ticks[&-600].liquidity_net, liquidity as i128);
+        assert_eq!(pool_state.ticks[&600].liquidity_net, -(liquidity as i128));
+        assert!(!pool_state.tick_bitmap[&tick_bitmap::position(-10).0].is_zero());
+        assert!(!pool_state.tick_bitmap[&tick_bitmap::position(10).0].is_zero());
+
+        let (amount0, amount1) = pool_state.burn(&provider, -600, 600, liquidity).await.unwrap();
+
+        assert_eq!((amount0, amount1), (expected0, expected1));
+        assert_eq!(pool_state.liquidity, 0);
+        assert_eq!(pool_state.ticks[&-600].liquidity_gross, 0);
+        assert!(pool_state.tick_bitmap.values().all(|word| word.is_zero()));
+    }
+
+    #[test]
+    fn fees_owed_test() {
+        let position = Position {
+            tick_lower: -600,
+            tick_upper: 600,
+            liquidity: 1000000,
+            fee_growth_inside0_last_x128: Q128 * U256::from(5),
+            fee_growth_inside1_last_x128: U256::MAX - Q128 + U256::from(1),
+            tokens_owed0: 7,
+            ..Default::default()
+        };
+
+        // token1 fee growth wrapped around since the last checkpoint
+        let (owed0, owed1) = position.fees_owed(Q128 * U256::from(8), Q128 * U256::from(2)).unwrap();
+        assert_eq!(owed0, 3000007);
+        assert_eq!(owed1, 3000000);
+    }
+
+    #[tokio::test]
+    async fn uncollected_fees_test() {
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.mint(&provider, -600, 600, 1000000).await.unwrap();
+
+        let position = Position {tick_lower: -600, tick_upper: 600, liquidity: 1000000, ..Default::default()};
+
+        // all fee growth since the position was opened happened while the price was in range
+        pool_state.fee_growth_global0_x128 = Q128 * U256::from(3);
+        assert_eq!(pool_state.uncollected_fees(&provider, &position).await.unwrap(), (3000000, 0));
+    }
+
+    #[tokio::test]
+    async fn mint_above_max_liquidity_per_tick_test() {
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        let max_liquidity = tick::_tick_spacing_to_max_liquidity_per_tick(60);
+        let tick_liquidity = |pool_state: &PoolState| {
+            let mut ticks: Vec<(i32, u128, i128, bool)> = pool_state.ticks
+                .iter()
+                .map(|(&tick, info)| (tick, info.liquidity_gross, info.liquidity_net, info.initialized))
+                .collect();
+            ticks.sort();
+            ticks
+        };
+
+        // both ticks overflow
+        let (ticks_before, tick_bitmap_before) = (tick_liquidity(&pool_state), pool_state.tick_bitmap.clone());
+        assert!(pool_state.mint(&provider, -600, 600, max_liquidity + 1).await.is_err());
+        assert_eq!(tick_liquidity(&pool_state), ticks_before);
+        assert_eq!(pool_state.tick_bitmap, tick_bitmap_before);
+
+        // only the upper tick overflows, the lower tick is left as it was
+        pool_state.ticks.insert(60, Default::default());
+        pool_state.mint(&provider, 60, 600, 1000).await.unwrap();
+        let (ticks_before, tick_bitmap_before) = (tick_liquidity(&pool_state), pool_state.tick_bitmap.clone());
+        assert!(pool_state.mint(&provider, -600, 600, max_liquidity - 999).await.is_err());
+        assert_eq!(tick_liquidity(&pool_state), ticks_before);
+        assert_eq!(pool_state.tick_bitmap, tick_bitmap_before);
+
+        pool_state.mint(&provider, -600, 600, max_liquidity - 1000).await.unwrap();
+        assert_eq!(pool_state.ticks[&600].liquidity_gross, max_liquidity);
+    }
+
+    #[tokio::test]
+    async fn snapshot_cumulatives_inside_test() {
+        // ticks are preloaded
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.ticks.insert(60, Default::default());
+        (pool_state.slot0.observation_cardinality, pool_state.slot0.observation_cardinality_next) = oracle::initialize(&mut pool_state.observations, 0);
+
+        pool_state.block_timestamp = 100;
+        pool_state.mint(&provider, -600, 600, 1000000000000000000000).await.unwrap();
+        pool_state.mint(&provider, 60, 600, 1000000000000000000000).await.unwrap();
+
+        // nothing accrues inside a range that has not been active
+        let (_, _, seconds_inside) = pool_state.snapshot_cumulatives_inside(&provider, 60, 600).await.unwrap();
+        assert_eq!(seconds_inside, 0);
+
+        // the price enters [60, 600) at 200
+        pool_state.block_timestamp = 200;
+        let sqrt_price_limit_x96 = tick_math::get_sqrt_ratio_at_tick(120).unwrap();
+        swap::swap(&provider, &mut pool_state, false, I256::MAX, sqrt_price_limit_x96).await.unwrap();
+        assert_eq!(pool_state.ticks[&60].seconds_outside, 200);
+
+        pool_state.block_timestamp = 500;
+        let (tick_cumulative_inside, _, seconds_inside) = pool_state.snapshot_cumulatives_inside(&provider, 60, 600).await.unwrap();
+        assert_eq!(seconds_inside, 300);
+        assert_eq!(tick_cumulative_inside, 120 * 300);
+
+        // the wide range was active the whole time since it was minted
+        let (_, _, seconds_inside) = pool_state.snapshot_cumulatives_inside(&provider, -600, 600).await.unwrap();
+        assert_eq!(seconds_inside, 400);
+    }
+}
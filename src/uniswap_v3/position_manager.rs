@@ -0,0 +1,270 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use crate::chains::ChainDeployment;
+use super::{
+    math::{liquidity_amounts, tick_math},
+    multicall::multicall_targets,
+    pool::{LoadingPattern, PoolState},
+    position::Position
+};
+use eyre::Result;
+use polars::prelude::*;
+
+sol! {
+    #[sol(rpc)]
+    interface INonfungiblePositionManager {
+        function balanceOf(address owner) external view returns (uint256);
+
+        function tokenOfOwnerByIndex(address owner, uint256 index) external view returns (uint256);
+
+        function positions(uint256 tokenId)
+            external
+            view
+            returns (
+                uint96 nonce,
+                address operator,
+                address token0,
+                address token1,
+                uint24 fee,
+                int24 tickLower,
+                int24 tickUpper,
+                uint128 liquidity,
+                uint256 feeGrowthInside0LastX128,
+                uint256 feeGrowthInside1LastX128,
+                uint128 tokensOwed0,
+                uint128 tokensOwed1
+            );
+    }
+}
+
+pub struct ManagedPosition {
+    pub token_id: U256,
+    pub position: Position
+}
+
+/// Pool state together with the NFT positions of an owner in that pool
+pub struct OwnerPool {
+    pub pool_state: PoolState,
+    pub positions: Vec<ManagedPosition>
+}
+
+pub struct PositionReport {
+    pub token_id: U256,
+    pub pool_address: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    // token amounts the position would be worth if burned at the current price
+    pub amount0: U256,
+    pub amount1: U256,
+    pub fees0: u128,
+    pub fees1: u128,
+    pub in_range: bool
+}
+
+/// Loads every position NFT held by `owner` and the pools they belong to, grouped per pool, from the position
+/// manager and factory of the chain's deployment
+pub async fn load_owner_positions(
+    provider: &RootProvider<Http<Client>>,
+    deployment: &ChainDeployment,
+    owner: Address
+) -> Result<Vec<OwnerPool>> {
+    let position_manager = INonfungiblePositionManager::new(deployment.position_manager, provider);
+    let balance = u64::try_from(position_manager.balanceOf(owner).call().await?._0)?;
+
+    let token_id_call_data: Vec<(Address, Vec<u8>)> = (0..balance)
+        .map(|index| {
+            (deployment.position_manager, INonfungiblePositionManager::tokenOfOwnerByIndexCall{owner, index: U256::from(index)}.abi_encode())
+        })
+        .collect();
+
+    let mut token_ids = Vec::new();
+    for data in multicall_targets(provider, deployment.multicall, false, token_id_call_data, BlockId::latest()).await? {
+        token_ids.push(INonfungiblePositionManager::tokenOfOwnerByIndexCall::abi_decode_returns(&data.returnData, true)?._0);
+    }
+
+    let position_call_data: Vec<(Address, Vec<u8>)> = token_ids
+        .iter()
+        .map(|&token_id| {
+            (deployment.position_manager, INonfungiblePositionManager::positionsCall{tokenId: token_id}.abi_encode())
+        })
+        .collect();
+
+    let return_data = multicall_targets(provider, deployment.multicall, false, position_call_data, BlockId::latest()).await?;
+
+    // positions grouped by (token0, token1, fee), in the order the pools are first seen
+    let mut groups: Vec<((Address, Address, u32), Vec<ManagedPosition>)> = Vec::new();
+    for (token_id, data) in token_ids.into_iter().zip(return_data.iter()) {
+        let position = INonfungiblePositionManager::positionsCall::abi_decode_returns(&data.returnData, true)?;
+        let key = (position.token0, position.token1, position.fee);
+
+        let managed_position = ManagedPosition {
+            token_id,
+            position: Position {
+                tick_lower: position.tickLower,
+                tick_upper: position.tickUpper,
+                liquidity: position.liquidity,
+                fee_growth_inside0_last_x128: position.feeGrowthInside0LastX128,
+                fee_growth_inside1_last_x128: position.feeGrowthInside1LastX128,
+                tokens_owed0: position.tokensOwed0,
+                tokens_owed1: position.tokensOwed1
+            }
+        };
+
+        match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+            Some((_, positions)) => positions.push(managed_position),
+            None => groups.push((key, vec![managed_position]))
+        }
+    }
+
+    let mut owner_pools = Vec::new();
+    for ((token0, token1, fee), positions) in groups {
+        let pool_state = PoolState::load(provider, deployment.multicall, deployment.factory, (token0, token1), fee, LoadingPattern::MID).await?;
+        owner_pools.push(OwnerPool{pool_state, positions});
+    }
+
+    Ok(owner_pools)
+}
+
+impl OwnerPool {
+    /// Values every position of the pool at the current price
+    pub async fn report(
+        &mut self,
+        provider: &RootProvider<Http<Client>>
+    ) -> Result<Vec<PositionReport>> {
+        let mut reports = Vec::new();
+
+        for managed_position in self.positions.iter() {
+            let position = &managed_position.position;
+
+            let (amount0, amount1) = liquidity_amounts::get_amounts_for_liquidity(
+                self.pool_state.slot0.sqrt_price_x96,
+                tick_math::get_sqrt_ratio_at_tick(position.tick_lower)?,
+                tick_math::get_sqrt_ratio_at_tick(position.tick_upper)?,
+                position.liquidity
+            )?;
+            let (fees0, fees1) = self.pool_state.uncollected_fees(provider, position).await?;
+            let in_range = position.tick_lower <= self.pool_state.slot0.tick && self.pool_state.slot0.tick < position.tick_upper;
+
+            reports.push(PositionReport {
+                token_id: managed_position.token_id,
+                pool_address: self.pool_state.pool_address,
+                tick_lower: position.tick_lower,
+                tick_upper: position.tick_upper,
+                liquidity: position.liquidity,
+                amount0,
+                amount1,
+                fees0,
+                fees1,
+                in_range
+            });
+        }
+
+        Ok(reports)
+    }
+}
+
+/// Values every position of every pool and exports the result, one row per position
+pub async fn export_positions_to_df(
+    provider: &RootProvider<Http<Client>>,
+    owner_pools: &mut [OwnerPool]
+) -> Result<DataFrame> {
+    let mut token_id = Vec::<String>::new();
+    let mut pool = Vec::<String>::new();
+    let mut symbol0 = Vec::<String>::new();
+    let mut symbol1 = Vec::<String>::new();
+    let mut tick_lower = Vec::<i32>::new();
+    let mut tick_upper = Vec::<i32>::new();
+    let mut liquidity = Vec::<String>::new();
+    let mut amount0 = Vec::<String>::new();
+    let mut amount1 = Vec::<String>::new();
+    let mut fees0 = Vec::<String>::new();
+    let mut fees1 = Vec::<String>::new();
+    let mut in_range = Vec::<bool>::new();
+
+    for owner_pool in owner_pools.iter_mut() {
+        for report in owner_pool.report(provider).await? {
+            token_id.push(report.token_id.to_string());
+            pool.push(report.pool_address.to_string());
+            symbol0.push(owner_pool.pool_state.token0.symbol.clone());
+            symbol1.push(owner_pool.pool_state.token1.symbol.clone());
+            tick_lower.push(report.tick_lower);
+            tick_upper.push(report.tick_upper);
+            liquidity.push(report.liquidity.to_string());
+            amount0.push(report.amount0.to_string());
+            amount1.push(report.amount1.to_string());
+            fees0.push(report.fees0.to_string());
+            fees1.push(report.fees1.to_string());
+            in_range.push(report.in_range);
+        }
+    }
+
+    let series_vector = vec![
+        Series::new("token_id", token_id),
+        Series::new("pool", pool),
+        Series::new("token0", symbol0),
+        Series::new("token1", symbol1),
+        Series::new("tick_lower", tick_lower),
+        Series::new("tick_upper", tick_upper),
+        Series::new("liquidity", liquidity),
+        Series::new("amount0", amount0),
+        Series::new("amount1", amount1),
+        Series::new("fees0", fees0),
+        Series::new("fees1", fees1),
+        Series::new("in_range", in_range)
+    ];
+
+    Ok(DataFrame::new(series_vector)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_utils::offline_provider,
+        uniswap_v3::{math::constants::Q128, position::tests::pool_state_at_price_one}
+    };
+    use super::*;
+
+    #[tokio::test]
+    async fn report_positions_test() {
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.ticks.insert(1200, Default::default());
+        pool_state.mint(&provider, -600, 600, 1000000).await.unwrap();
+        pool_state.mint(&provider, 600, 1200, 1000000).await.unwrap();
+        // every fee since the mints was earned at price one
+        pool_state.fee_growth_global0_x128 = Q128 * U256::from(3);
+
+        let position = |tick_lower: i32, tick_upper: i32| Position {tick_lower, tick_upper, liquidity: 1000000, ..Default::default()};
+        let mut owner_pools = vec![OwnerPool {
+            pool_state,
+            positions: vec![
+                ManagedPosition {token_id: U256::from(1), position: position(-600, 600)},
+                ManagedPosition {token_id: U256::from(2), position: position(600, 1200)}
+            ]
+        }];
+
+        // L * (1 - 1.0001^-300) = 29553.01 on both sides of the symmetric range, L * (1.0001^-300 - 1.0001^-600) =
+        // 28679.63 of token0 above the price, both rounded down
+        let reports = owner_pools[0].report(&provider).await.unwrap();
+        assert_eq!((reports[0].amount0, reports[0].amount1), (U256::from(29553), U256::from(29553)));
+        assert_eq!((reports[0].fees0, reports[0].fees1), (3000000, 0));
+        assert!(reports[0].in_range);
+        assert_eq!((reports[1].amount0, reports[1].amount1), (U256::from(28679), U256::ZERO));
+        assert_eq!((reports[1].fees0, reports[1].fees1), (0, 0));
+        assert!(!reports[1].in_range);
+
+        let df = export_positions_to_df(&provider, &mut owner_pools).await.unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!(df.column("token_id").unwrap().str().unwrap().get(1), Some("2"));
+        assert_eq!(df.column("fees0").unwrap().str().unwrap().get(0), Some("3000000"));
+        assert_eq!(df.column("tick_lower").unwrap().i32().unwrap().get(1), Some(600));
+        assert_eq!(df.column("in_range").unwrap().bool().unwrap().get(1), Some(false));
+    }
+}
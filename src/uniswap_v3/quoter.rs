@@ -5,32 +5,63 @@ use alloy::{
     transports::http::{Client, Http}
 };
 use eyre::Result; 
-use super::utils::UNISWAP_V3_QUOTER_ADDRESS;
 use super::pool::SwapResult;
+use super::dex::Dex;
+use crate::chains::ChainDeployment;
 
+/// Quotes an exact input swap in the 1% pool of `pair` with the QuoterV2 of the chain's deployment
 pub async fn _quote_exact_input_single(
     provider: &RootProvider<Http<Client>>,
+    deployment: &ChainDeployment,
     pair: (Address, Address), 
     amount_in: U256,
     one_for_two: bool
 ) -> Result<SwapResult> {
-    sol! {
-        #[sol(rpc)]
-        interface IQuoter {
-            function quoteExactInputSingle(
-                address tokenIn,
-                address tokenOut,
-                uint24 fee,
-                uint256 amountIn,
-                uint160 sqrtPriceLimitX96
-            ) external returns (uint256 amountOut);
+    let (token_in, token_out) = if one_for_two {pair} else {(pair.1, pair.0)}; 
+
+    quote_exact_input_single_v2(provider, &deployment.dex(), token_in, token_out, 10000, amount_in).await
+}
+
+sol! {
+    #[sol(rpc)]
+    interface IQuoterV2 {
+        struct QuoteExactInputSingleParams {
+            address tokenIn;
+            address tokenOut;
+            uint256 amountIn;
+            uint24 fee;
+            uint160 sqrtPriceLimitX96;
         }
+
+        function quoteExactInputSingle(QuoteExactInputSingleParams memory params)
+            external
+            returns (
+                uint256 amountOut,
+                uint160 sqrtPriceX96After,
+                uint32 initializedTicksCrossed,
+                uint256 gasEstimate
+            );
     }
+}
 
-    let (token_in, token_out) = if one_for_two {pair} else {(pair.1, pair.0)}; 
+/// Quotes an exact input swap of `token_in` for `token_out` in the `fee` pool with the QuoterV2 of `dex`
+pub async fn quote_exact_input_single_v2(
+    provider: &RootProvider<Http<Client>>,
+    dex: &Dex,
+    token_in: Address,
+    token_out: Address,
+    fee: u32,
+    amount_in: U256
+) -> Result<SwapResult> {
+    let quoter = IQuoterV2::new(dex.quoter, provider);
+    let params = IQuoterV2::QuoteExactInputSingleParams {
+        tokenIn: token_in,
+        tokenOut: token_out,
+        amountIn: amount_in,
+        fee,
+        sqrtPriceLimitX96: U256::ZERO
+    };
 
-    let quoter = IQuoter::new(UNISWAP_V3_QUOTER_ADDRESS, provider); 
-    match quoter.quoteExactInputSingle(token_in, token_out, 10000, amount_in, U256::ZERO).call().await? {
-        IQuoter::quoteExactInputSingleReturn{amountOut} => Ok(SwapResult{amount_in, amount_out: amountOut}),
-    }
-}
\ No newline at end of file
+    let quote = quoter.quoteExactInputSingle(params).call().await?;
+    Ok(SwapResult{amount_in, amount_out: quote.amountOut})
+}
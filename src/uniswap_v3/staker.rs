@@ -0,0 +1,313 @@
+use alloy::{
+    primitives::{keccak256, Address, B256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    sol_types::SolValue,
+    transports::http::{Client, Http}
+};
+use super::{
+    math::{full_math, liquidity_amounts, oracle::UINT160_MASK, tick_math},
+    pool::PoolState
+};
+use eyre::{eyre, Report, Result};
+use polars::prelude::*;
+
+sol! {
+    #[sol(rpc)]
+    interface IUniswapV3Staker {
+        struct IncentiveKey {
+            address rewardToken;
+            address pool;
+            uint256 startTime;
+            uint256 endTime;
+            address refundee;
+        }
+
+        function incentives(bytes32 incentiveId)
+            external
+            view
+            returns (
+                uint256 totalRewardUnclaimed,
+                uint160 totalSecondsClaimedX128,
+                uint96 numberOfStakes
+            );
+
+        function stakes(uint256 tokenId, bytes32 incentiveId)
+            external
+            view
+            returns (uint160 secondsPerLiquidityInsideInitialX128, uint128 liquidity);
+    }
+}
+
+/// Incentive program of the staker together with its unclaimed state
+#[derive(Clone)]
+pub struct Incentive {
+    pub key: IUniswapV3Staker::IncentiveKey,
+    pub total_reward_unclaimed: U256,
+    pub total_seconds_claimed_x128: U256,
+    pub number_of_stakes: u128
+}
+
+/// Stake of a position NFT in an incentive
+pub struct Stake {
+    pub seconds_per_liquidity_inside_initial_x128: U256,
+    pub liquidity: u128
+}
+
+/// @notice Calculate the key for a staking incentive
+/// @param key The components used to compute the incentive identifier
+/// @return incentiveId The identifier for the incentive
+pub fn incentive_id(key: &IUniswapV3Staker::IncentiveKey) -> B256 {
+    keccak256(key.abi_encode())
+}
+
+/// @notice Compute the amount of rewards owed given parameters of the incentive and stake
+/// @param totalRewardUnclaimed The total amount of unclaimed rewards left for an incentive
+/// @param totalSecondsClaimedX128 How many full liquidity-seconds have been already claimed for the incentive
+/// @param startTime When the incentive rewards began in epoch seconds
+/// @param endTime When rewards are no longer being dripped out in epoch seconds
+/// @param liquidity The amount of liquidity, assumed to be constant over the period over which the snapshots are measured
+/// @param secondsPerLiquidityInsideInitialX128 The seconds per liquidity of the liquidity tick range as of the beginning of the period
+/// @param secondsPerLiquidityInsideX128 The seconds per liquidity of the liquidity tick range as of the current block timestamp
+/// @param currentTime The current block timestamp, which must be greater than or equal to the start time
+/// @return reward The amount of rewards owed
+/// @return secondsInsideX128 The total liquidity seconds inside the position's range for the duration of the stake
+#[allow(clippy::too_many_arguments)]
+pub fn compute_reward_amount(
+    total_reward_unclaimed: U256,
+    total_seconds_claimed_x128: U256,
+    start_time: U256,
+    end_time: U256,
+    liquidity: u128,
+    seconds_per_liquidity_inside_initial_x128: U256,
+    seconds_per_liquidity_inside_x128: U256,
+    current_time: U256
+) -> Result<(U256, U256)> {
+    // this should never be called before the start time
+    if current_time < start_time {
+        return Err(eyre!("Incentive has not started"))
+    }
+
+    // this operation is safe, as the difference cannot be greater than 1/stake.liquidity
+    let seconds_inside_x128 = (seconds_per_liquidity_inside_x128.wrapping_sub(seconds_per_liquidity_inside_initial_x128) & UINT160_MASK)
+        .wrapping_mul(U256::from(liquidity)) & UINT160_MASK;
+
+    let total_seconds_unclaimed_x128 = ((end_time.max(current_time) - start_time) << 128usize)
+        .checked_sub(total_seconds_claimed_x128)
+        .ok_or(eyre!("Claimed seconds exceed the incentive duration"))?;
+
+    let reward = full_math::mul_div(total_reward_unclaimed, seconds_inside_x128, total_seconds_unclaimed_x128)?;
+
+    Ok((reward, seconds_inside_x128))
+}
+
+/// Reads the unclaimed state of the incentive described by `key` from the staker at `block`
+pub async fn load_incentive(
+    provider: &RootProvider<Http<Client>>,
+    staker_address: Address,
+    key: IUniswapV3Staker::IncentiveKey,
+    block: BlockId
+) -> Result<Incentive> {
+    let incentive = IUniswapV3Staker::new(staker_address, provider)
+        .incentives(incentive_id(&key))
+        .block(block)
+        .call()
+        .await?;
+
+    Ok(Incentive {
+        key,
+        total_reward_unclaimed: incentive.totalRewardUnclaimed,
+        total_seconds_claimed_x128: incentive.totalSecondsClaimedX128,
+        number_of_stakes: incentive.numberOfStakes
+    })
+}
+
+/// Reads the stake of position `token_id` in the incentive at `block`
+pub async fn load_stake(
+    provider: &RootProvider<Http<Client>>,
+    staker_address: Address,
+    incentive: &Incentive,
+    token_id: U256,
+    block: BlockId
+) -> Result<Stake> {
+    let stake = IUniswapV3Staker::new(staker_address, provider)
+        .stakes(token_id, incentive_id(&incentive.key))
+        .block(block)
+        .call()
+        .await?;
+
+    Ok(Stake {
+        seconds_per_liquidity_inside_initial_x128: stake.secondsPerLiquidityInsideInitialX128,
+        liquidity: stake.liquidity
+    })
+}
+
+impl Incentive {
+    /// Reward owed to a stake in `[tick_lower, tick_upper)` as of the pool state, the local equivalent of
+    /// `getRewardInfo`. The observations of the pool have to be loaded when the range is active.
+    pub async fn reward_info(
+        &self,
+        provider: &RootProvider<Http<Client>>,
+        pool_state: &mut PoolState,
+        tick_lower: i32,
+        tick_upper: i32,
+        stake: &Stake
+    ) -> Result<(U256, U256)> {
+        let (_, seconds_per_liquidity_inside_x128, _) = pool_state.snapshot_cumulatives_inside(provider, tick_lower, tick_upper).await?;
+
+        self.reward(
+            stake.liquidity,
+            stake.seconds_per_liquidity_inside_initial_x128,
+            seconds_per_liquidity_inside_x128,
+            pool_state.block_timestamp
+        )
+    }
+
+    /// Reward for holding `liquidity` in range for `seconds_in_range` seconds next to `pool_liquidity` in range
+    /// liquidity, including the position itself, claimed at `current_time`
+    pub fn project_reward(
+        &self,
+        liquidity: u128,
+        pool_liquidity: u128,
+        seconds_in_range: u32,
+        current_time: u32
+    ) -> Result<U256> {
+        if pool_liquidity == 0 {
+            return Err(eyre!("No liquidity in range"))
+        }
+
+        let seconds_per_liquidity_inside_x128 = (U256::from(seconds_in_range) << 128usize) / U256::from(pool_liquidity);
+        let (reward, _) = self.reward(liquidity, U256::ZERO, seconds_per_liquidity_inside_x128, current_time)?;
+
+        Ok(reward)
+    }
+
+    fn reward(
+        &self,
+        liquidity: u128,
+        seconds_per_liquidity_inside_initial_x128: U256,
+        seconds_per_liquidity_inside_x128: U256,
+        current_time: u32
+    ) -> Result<(U256, U256)> {
+        compute_reward_amount(
+            self.total_reward_unclaimed,
+            self.total_seconds_claimed_x128,
+            self.key.startTime,
+            self.key.endTime,
+            liquidity,
+            seconds_per_liquidity_inside_initial_x128,
+            seconds_per_liquidity_inside_x128,
+            U256::from(current_time)
+        )
+    }
+}
+
+/// Rewards `liquidity` would have earned in each range between the `start` and `end` states of the pool, one row per
+/// range with the seconds it was active and the amounts the liquidity takes at the end price. Both states need their
+/// observations loaded and ranges have to be bounded by initialized ticks, other ranges are left out of the table and
+/// returned with the reason. The extra liquidity is not added to the pool, so rewards of large positions are overstated.
+pub async fn range_rewards(
+    provider: &RootProvider<Http<Client>>,
+    incentive: &Incentive,
+    start: &mut PoolState,
+    end: &mut PoolState,
+    ranges: &[(i32, i32)],
+    liquidity: u128
+) -> Result<(DataFrame, Vec<((i32, i32), Report)>)> {
+    let mut tick_lower = Vec::<i32>::new();
+    let mut tick_upper = Vec::<i32>::new();
+    let mut seconds_inside = Vec::<u32>::new();
+    let mut amount0 = Vec::<String>::new();
+    let mut amount1 = Vec::<String>::new();
+    let mut reward = Vec::<String>::new();
+    let mut reward_share = Vec::<f64>::new();
+    let mut skipped = Vec::new();
+
+    for &(lower, upper) in ranges {
+        let snapshots = match (
+            start.snapshot_cumulatives_inside(provider, lower, upper).await,
+            end.snapshot_cumulatives_inside(provider, lower, upper).await
+        ) {
+            (Ok(initial), Ok(current)) => (initial, current),
+            (Err(e), _) | (_, Err(e)) => {
+                skipped.push(((lower, upper), e));
+                continue;
+            }
+        };
+        let ((_, seconds_per_liquidity_initial_x128, seconds_initial), (_, seconds_per_liquidity_x128, seconds_current)) = snapshots;
+
+        let (range_reward, _) = incentive.reward(liquidity, seconds_per_liquidity_initial_x128, seconds_per_liquidity_x128, end.block_timestamp)?;
+        let (range_amount0, range_amount1) = liquidity_amounts::get_amounts_for_liquidity(
+            end.slot0.sqrt_price_x96,
+            tick_math::get_sqrt_ratio_at_tick(lower)?,
+            tick_math::get_sqrt_ratio_at_tick(upper)?,
+            liquidity
+        )?;
+
+        tick_lower.push(lower);
+        tick_upper.push(upper);
+        seconds_inside.push(seconds_current.wrapping_sub(seconds_initial));
+        amount0.push(range_amount0.to_string());
+        amount1.push(range_amount1.to_string());
+        reward.push(range_reward.to_string());
+        reward_share.push(if incentive.total_reward_unclaimed.is_zero() {
+            0.0
+        } else {
+            f64::from(range_reward) / f64::from(incentive.total_reward_unclaimed)
+        });
+    }
+
+    let series_vector = vec![
+        Series::new("tick_lower", tick_lower),
+        Series::new("tick_upper", tick_upper),
+        Series::new("seconds_inside", seconds_inside),
+        Series::new("amount0", amount0),
+        Series::new("amount1", amount1),
+        Series::new("reward", reward),
+        Series::new("reward_share", reward_share)
+    ];
+
+    Ok((DataFrame::new(series_vector)?, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use super::*;
+
+    fn incentive(total_reward_unclaimed: u64) -> Incentive {
+        Incentive {
+            key: IUniswapV3Staker::IncentiveKey {
+                rewardToken: address!("0000000000000000000000000000000000000003"),
+                pool: Address::ZERO,
+                startTime: U256::from(100),
+                endTime: U256::from(200),
+                refundee: Address::ZERO
+            },
+            total_reward_unclaimed: U256::from(total_reward_unclaimed),
+            total_seconds_claimed_x128: U256::ZERO,
+            number_of_stakes: 1
+        }
+    }
+
+    #[test]
+    fn compute_reward_amount_test() {
+        let incentive = incentive(1000);
+        let ten_seconds_per_five_liquidity = (U256::from(10) << 128usize) / U256::from(5);
+
+        // 10 of 100 incentive seconds with all of the in range liquidity
+        let (reward, seconds_inside_x128) = incentive.reward(5, U256::ZERO, ten_seconds_per_five_liquidity, 150).unwrap();
+        assert_eq!(reward, U256::from(100));
+        assert_eq!(seconds_inside_x128, U256::from(10) << 128usize);
+
+        // past the end time the rewards are spread over the longer period
+        let (reward, _) = incentive.reward(5, U256::ZERO, ten_seconds_per_five_liquidity, 300).unwrap();
+        assert_eq!(reward, U256::from(50));
+
+        // a quarter of the liquidity in range for the full incentive
+        assert_eq!(incentive.project_reward(1, 4, 100, 200).unwrap(), U256::from(250));
+
+        assert!(incentive.reward(5, U256::ZERO, ten_seconds_per_five_liquidity, 99).is_err());
+    }
+}
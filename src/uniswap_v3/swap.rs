@@ -5,7 +5,7 @@ use alloy::{
     transports::http::{Client, Http}, 
     providers::RootProvider
 };
-use super::{math::{constants::{Q96, U256_1, U256_2}, full_math, tick_math::get_sqrt_ratio_at_tick}, pool::{self, PoolState}};
+use super::{math::{constants::{Q96, Q128, U256_1, U256_2}, full_math, tick, tick_math::get_sqrt_ratio_at_tick}, pool::PoolState};
 use super::math::{liquidity_math, low_gas_safe_math, safe_cast, swap_math, tick_bitmap, tick_math};
 use eyre::{eyre, Result};
 
@@ -18,6 +18,12 @@ pub struct SwapState {
     sqrt_price_x96: U256, 
     // the tick associated with the current price
     tick: i32,
+    // the global fee growth of the input token
+    fee_growth_global_x128: U256,
+    // amount of input token paid as protocol fee
+    protocol_fee: u128,
+    // amount of input token paid as fee to the liquidity providers
+    lp_fee: U256,
     // the current liquidity in range
     liquidity: u128
 }
@@ -54,6 +60,13 @@ pub struct StepComputations {
     fee_amount: U256
 }
 
+/// Split of the fee paid by a swap, in the input token
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SwapFees {
+    pub lp_fee: U256,
+    pub protocol_fee: U256
+}
+
 pub async fn swap (
     provider: &RootProvider<Http<Client>>, 
     pool_state: &mut PoolState,
@@ -61,6 +74,18 @@ pub async fn swap (
     amount_specified: I256, 
     sqrt_price_limit_x96: U256
 ) -> Result<(I256, I256)>{
+    let (amounts, _) = swap_with_fees(provider, pool_state, zero_for_one, amount_specified, sqrt_price_limit_x96).await?;
+    Ok(amounts)
+}
+
+/// Same as `swap`, also returning how the fee was split between the liquidity providers and the protocol
+pub async fn swap_with_fees (
+    provider: &RootProvider<Http<Client>>, 
+    pool_state: &mut PoolState,
+    zero_for_one: bool, 
+    amount_specified: I256, 
+    sqrt_price_limit_x96: U256
+) -> Result<((I256, I256), SwapFees)>{
     if amount_specified == I256::ZERO {
         return Err(eyre!("Amount specified is zero, no swap"))
     }
@@ -81,6 +106,10 @@ pub async fn swap (
         }
     }
 
+    let (tick_start, liquidity_start) = (slot0_start.tick, pool_state.liquidity);
+    // the oracle accumulators at the start of the swap, computed on the first initialized tick crossed
+    let mut latest_observation: Option<(i64, U256)> = None;
+    let fee_protocol = slot0_start.protocol_fee_denominator(zero_for_one);
     let exact_input = amount_specified > I256::ZERO;
 
     let mut state:SwapState = SwapState {
@@ -88,6 +117,9 @@ pub async fn swap (
         amount_calculated: I256::ZERO, 
         sqrt_price_x96: slot0_start.sqrt_price_x96, 
         tick: slot0_start.tick,
+        fee_growth_global_x128: if zero_for_one {pool_state.fee_growth_global0_x128} else {pool_state.fee_growth_global1_x128},
+        protocol_fee: 0,
+        lp_fee: U256::ZERO,
         liquidity: pool_state.liquidity
     }; 
 
@@ -133,17 +165,41 @@ pub async fn swap (
             state.amount_calculated = low_gas_safe_math::signed_add(state.amount_calculated, safe_cast::to_int256(step.amount_in + step.fee_amount)?)?;
         }
 
+        // if the protocol fee is on, calculate how much is owed, decrement feeAmount, and increment protocolFee
+        if fee_protocol > 0 {
+            let delta = step.fee_amount / U256::from(fee_protocol);
+            step.fee_amount -= delta;
+            state.protocol_fee = state.protocol_fee.wrapping_add(delta.wrapping_to());
+        }
+        state.lp_fee += step.fee_amount;
+
+        // update global fee tracker, the accumulator is allowed to overflow
+        if state.liquidity > 0 {
+            state.fee_growth_global_x128 = state.fee_growth_global_x128.wrapping_add(full_math::mul_div(step.fee_amount, Q128, U256::from(state.liquidity))?);
+        }
+
         if state.sqrt_price_x96 == step.sqrt_price_next_x96 {
             if step.initialized {
-                let mut liquidity_net: i128 = match pool_state.ticks.get(&step.tick_next) {
-                    Some(val) => val.liquidity_net, 
-                    None => {
-                        println!("Tick {} out of range: loading new liquidity map", step.tick_next); 
-                        pool_state.update_ticks(provider, step.tick_next).await?; 
-                        pool_state.ticks.get(&step.tick_next).ok_or(eyre!("Next tick out of allowed range"))?.liquidity_net
-                    }
+                if !pool_state.ticks.contains_key(&step.tick_next) {
+                    println!("Tick {} out of range: loading new liquidity map", step.tick_next); 
+                    pool_state.update_ticks(provider, step.tick_next).await?; 
+                }
+
+                let (tick_cumulative, seconds_per_liquidity_cumulative_x128) = match latest_observation {
+                    Some(observation) => observation,
+                    None => *latest_observation.insert(pool_state.current_cumulatives()?)
                 };
 
+                let mut liquidity_net: i128 = tick::cross(
+                    &mut pool_state.ticks, 
+                    step.tick_next, 
+                    if zero_for_one {state.fee_growth_global_x128} else {pool_state.fee_growth_global0_x128}, 
+                    if zero_for_one {pool_state.fee_growth_global1_x128} else {state.fee_growth_global_x128},
+                    seconds_per_liquidity_cumulative_x128,
+                    tick_cumulative,
+                    pool_state.block_timestamp
+                ).map_err(|_| eyre!("Next tick out of allowed range"))?;
+
                 if zero_for_one {liquidity_net = -liquidity_net} 
                 state.liquidity = liquidity_math::add_delta(state.liquidity, liquidity_net)?;
             }
@@ -154,10 +210,28 @@ pub async fn swap (
         }
     }
 
+    // update the pool state the same way the pool writes slot0, liquidity and fee growth back to storage
+    if state.tick != tick_start {
+        pool_state.write_observation(tick_start, liquidity_start);
+    }
+    pool_state.slot0.sqrt_price_x96 = state.sqrt_price_x96;
+    pool_state.slot0.tick = state.tick;
+    pool_state.liquidity = state.liquidity;
+    // overflow is acceptable, protocol has to withdraw before it hits type(uint128).max fees
+    if zero_for_one {
+        pool_state.fee_growth_global0_x128 = state.fee_growth_global_x128;
+        pool_state.protocol_fees.token0 = pool_state.protocol_fees.token0.wrapping_add(state.protocol_fee);
+    } else {
+        pool_state.fee_growth_global1_x128 = state.fee_growth_global_x128;
+        pool_state.protocol_fees.token1 = pool_state.protocol_fees.token1.wrapping_add(state.protocol_fee);
+    }
+
+    let fees = SwapFees {lp_fee: state.lp_fee, protocol_fee: U256::from(state.protocol_fee)};
+
     if zero_for_one == exact_input {
-        Ok((amount_specified - state.amount_specified_remaining, state.amount_calculated))
+        Ok(((amount_specified - state.amount_specified_remaining, state.amount_calculated), fees))
     } else {
-        Ok((state.amount_calculated, amount_specified - state.amount_specified_remaining))
+        Ok(((state.amount_calculated, amount_specified - state.amount_specified_remaining), fees))
     }
 }
 
@@ -439,7 +513,10 @@ fn find_tick_at_slippage(
 #[cfg(test)]
 mod tests {
     use alloy::primitives::U160;
-
+    use crate::{
+        test_utils::offline_provider,
+        uniswap_v3::{backfill::estimate_swap_fees, position::tests::pool_state_at_price_one}
+    };
     use super::*;
 
     #[test]
@@ -472,4 +549,31 @@ mod tests {
         let sqrt_price_x96 = U256::from(U160::MAX); 
         assert_eq!(calc_sqrt_price_limit_from_price_impact(sqrt_price_x96, 100, true).unwrap(), U256::from(0)); 
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn swap_protocol_fee_test() {
+        // ticks and bitmap words around the price are preloaded
+        let provider = offline_provider();
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.mint(&provider, -600, 600, 1000000000000000000000).await.unwrap();
+        // 1/4 of the fee of zeroForOne swaps and 1/5 of oneForZero swaps goes to the protocol
+        pool_state.slot0.fee_protocol = 4 | (5 << 4);
+        let amount_in = U256::from(1000000000000000000u128);
+        let liquidity = U256::from(1000000000000000000000u128);
+
+        // the 0.3% fee of 1e18 is 3e15 in both directions, no tick is crossed
+        let (_, fees) = swap_with_fees(&provider, &mut pool_state, true, I256::from_raw(amount_in), tick_math::MIN_SQRT_RATIO + U256::from(1)).await.unwrap();
+        assert_eq!(fees, SwapFees {lp_fee: U256::from(2250000000000000u64), protocol_fee: U256::from(750000000000000u64)});
+        assert_eq!(pool_state.protocol_fees.token0, 750000000000000);
+        assert_eq!(pool_state.fee_growth_global0_x128, full_math::mul_div(U256::from(2250000000000000u64), Q128, liquidity).unwrap());
+        assert_eq!(estimate_swap_fees(&pool_state, true, amount_in).unwrap(), fees);
+
+        let (_, fees) = swap_with_fees(&provider, &mut pool_state, false, I256::from_raw(amount_in), tick_math::MAX_SQRT_RATIO - U256::from(1)).await.unwrap();
+        assert_eq!(fees, SwapFees {lp_fee: U256::from(2400000000000000u64), protocol_fee: U256::from(600000000000000u64)});
+        assert_eq!(pool_state.protocol_fees.token1, 600000000000000);
+        assert_eq!(pool_state.fee_growth_global1_x128, full_math::mul_div(U256::from(2400000000000000u64), Q128, liquidity).unwrap());
+        assert_eq!(estimate_swap_fees(&pool_state, false, amount_in).unwrap(), fees);
+        // the token0 side is untouched by the second swap
+        assert_eq!(pool_state.protocol_fees.token0, 750000000000000);
+    }
+}
@@ -0,0 +1,230 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol_types::SolCall,
+    transports::http::{Client, Http}
+};
+use super::{
+    math::oracle::{self, Observation, UINT160_MASK},
+    multicall::multicall,
+    pool::{IPool, PoolState}
+};
+use eyre::{eyre, Result};
+
+/// Time-weighted averages of a pool over a window
+#[derive(Debug, PartialEq)]
+pub struct Twap {
+    pub arithmetic_mean_tick: i32,
+    pub harmonic_mean_liquidity: u128
+}
+
+/// @notice Calculates time-weighted means of tick and liquidity from the accumulators at the start and end of a window
+/// @dev Mirrors OracleLibrary.consult, the accumulators are the result of observe([secondsAgo, 0])
+/// @param tickCumulatives Tick accumulators at the start and end of the window
+/// @param secondsPerLiquidityCumulativeX128s Seconds per liquidity accumulators at the start and end of the window
+/// @param secondsAgo Length of the window in seconds
+/// @return arithmeticMeanTick The arithmetic mean tick from (block.timestamp - secondsAgo) to block.timestamp
+/// @return harmonicMeanLiquidity The harmonic mean liquidity from (block.timestamp - secondsAgo) to block.timestamp
+pub fn consult(
+    tick_cumulatives: (i64, i64),
+    seconds_per_liquidity_cumulative_x128s: (U256, U256),
+    seconds_ago: u32
+) -> Result<Twap> {
+    if seconds_ago == 0 {
+        return Err(eyre!("BP"))
+    }
+
+    let tick_cumulatives_delta = tick_cumulatives.1 - tick_cumulatives.0;
+    let seconds_per_liquidity_cumulatives_delta = seconds_per_liquidity_cumulative_x128s.1
+        .wrapping_sub(seconds_per_liquidity_cumulative_x128s.0) & UINT160_MASK;
+
+    let mut arithmetic_mean_tick = (tick_cumulatives_delta / seconds_ago as i64) as i32;
+    // always round to negative infinity
+    if tick_cumulatives_delta < 0 && tick_cumulatives_delta % seconds_ago as i64 != 0 {
+        arithmetic_mean_tick -= 1;
+    }
+
+    if seconds_per_liquidity_cumulatives_delta.is_zero() {
+        return Err(eyre!("Seconds per liquidity did not change over the window"))
+    }
+
+    // we are multiplying here instead of shifting to ensure that harmonicMeanLiquidity doesn't overflow uint128
+    let seconds_ago_x160 = U256::from(seconds_ago) * UINT160_MASK;
+    let harmonic_mean_liquidity = (seconds_ago_x160 / (seconds_per_liquidity_cumulatives_delta << 32usize)).wrapping_to::<u128>();
+
+    Ok(Twap {arithmetic_mean_tick, harmonic_mean_liquidity})
+}
+
+/// Calls `observe(secondsAgos)` on the pool at `block`
+pub async fn observe(
+    provider: &RootProvider<Http<Client>>,
+    pool_address: Address,
+    seconds_agos: Vec<u32>,
+    block: BlockId
+) -> Result<(Vec<i64>, Vec<U256>)> {
+    let observed = IPool::new(pool_address, provider)
+        .observe(seconds_agos)
+        .block(block)
+        .call()
+        .await?;
+
+    Ok((observed.tickCumulatives, observed.secondsPerLiquidityCumulativeX128s))
+}
+
+/// TWAP of the pool over the last `seconds_ago` seconds as of `block`, read from the pool's oracle
+pub async fn consult_pool(
+    provider: &RootProvider<Http<Client>>,
+    pool_address: Address,
+    seconds_ago: u32,
+    block: BlockId
+) -> Result<Twap> {
+    let (tick_cumulatives, seconds_per_liquidity_cumulative_x128s) = observe(provider, pool_address, vec![seconds_ago, 0], block).await?;
+
+    consult(
+        (tick_cumulatives[0], tick_cumulatives[1]),
+        (seconds_per_liquidity_cumulative_x128s[0], seconds_per_liquidity_cumulative_x128s[1]),
+        seconds_ago
+    )
+}
+
+impl PoolState {
+    /// Loads the populated part of the oracle ring buffer, after which simulated swaps and mints write to it
+    pub async fn load_observations(
+        &mut self,
+        provider: &RootProvider<Http<Client>>
+    ) -> Result<()> {
+        let call_data: Vec<Vec<u8>> = (0..self.slot0.observation_cardinality)
+            .map(|index| IPool::observationsCall{index: U256::from(index)}.abi_encode())
+            .collect();
+
+        let return_data = multicall(provider, self.multicall_address, self.pool_address, false, call_data, self.block).await?;
+
+        self.observations.clear();
+        for (index, data) in return_data.iter().enumerate() {
+            let observation = IPool::observationsCall::abi_decode_returns(&data.returnData, true)?;
+            self.observations.insert(index as u16, Observation {
+                block_timestamp: observation.blockTimestamp,
+                tick_cumulative: observation.tickCumulative,
+                seconds_per_liquidity_cumulative_x128: observation.secondsPerLiquidityCumulativeX128,
+                initialized: observation.initialized
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Writes an oracle observation at `block_timestamp` the way the pool does before a price or in range liquidity change.
+    /// Nothing is written while the ring buffer is not loaded.
+    pub fn write_observation(&mut self, tick: i32, liquidity: u128) {
+        if self.observations.is_empty() {
+            return
+        }
+
+        (self.slot0.observation_index, self.slot0.observation_cardinality) = oracle::write(
+            &mut self.observations,
+            self.slot0.observation_index,
+            self.block_timestamp,
+            tick,
+            liquidity,
+            self.slot0.observation_cardinality,
+            self.slot0.observation_cardinality_next
+        );
+    }
+
+    /// Oracle accumulators at `block_timestamp` as `observeSingle(time, 0, ...)` computes them when ticks are updated or
+    /// crossed. They are zero while the ring buffer is not loaded, the cumulatives outside of ticks touched in that state
+    /// are not meaningful.
+    pub fn current_cumulatives(&self) -> Result<(i64, U256)> {
+        if self.observations.is_empty() {
+            return Ok((0, U256::ZERO))
+        }
+
+        oracle::observe_single(
+            &self.observations,
+            self.block_timestamp,
+            0,
+            self.slot0.tick,
+            self.slot0.observation_index,
+            self.liquidity,
+            self.slot0.observation_cardinality
+        )
+    }
+
+    /// Simulates `increaseObservationCardinalityNext` on the local ring buffer
+    pub fn increase_observation_cardinality_next(&mut self, observation_cardinality_next: u16) -> Result<()> {
+        self.slot0.observation_cardinality_next = oracle::grow(
+            &mut self.observations,
+            self.slot0.observation_cardinality_next,
+            observation_cardinality_next
+        )?;
+        Ok(())
+    }
+
+    /// `observe(secondsAgos)` answered from the local ring buffer at `block_timestamp`
+    pub fn observe(&self, seconds_agos: &[u32]) -> Result<(Vec<i64>, Vec<U256>)> {
+        if self.observations.is_empty() {
+            return Err(eyre!("Observations are not loaded"))
+        }
+
+        oracle::observe(
+            &self.observations,
+            self.block_timestamp,
+            seconds_agos,
+            self.slot0.tick,
+            self.slot0.observation_index,
+            self.liquidity,
+            self.slot0.observation_cardinality
+        )
+    }
+
+    /// TWAP over the last `seconds_ago` seconds from the local ring buffer, including simulated writes
+    pub fn consult(&self, seconds_ago: u32) -> Result<Twap> {
+        let (tick_cumulatives, seconds_per_liquidity_cumulative_x128s) = self.observe(&[seconds_ago, 0])?;
+
+        consult(
+            (tick_cumulatives[0], tick_cumulatives[1]),
+            (seconds_per_liquidity_cumulative_x128s[0], seconds_per_liquidity_cumulative_x128s[1]),
+            seconds_ago
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::uniswap_v3::position::tests::pool_state_at_price_one;
+    use super::*;
+
+    #[test]
+    fn consult_test() {
+        let one_second_per_liquidity = (U256::from(1) << 128) / U256::from(1000);
+        let twap = consult((0, 1000), (U256::ZERO, one_second_per_liquidity * U256::from(10)), 10).unwrap();
+        assert_eq!(twap, Twap {arithmetic_mean_tick: 100, harmonic_mean_liquidity: 1000});
+
+        // rounds to negative infinity
+        let twap = consult((0, -25), (U256::ZERO, one_second_per_liquidity), 10).unwrap();
+        assert_eq!(twap.arithmetic_mean_tick, -3);
+
+        // uint128() truncates when the liquidity does not fit, 10 * 2^128 - 1 wraps to the max
+        let twap = consult((0, 0), (U256::ZERO, U256::from(1)), 10).unwrap();
+        assert_eq!(twap.harmonic_mean_liquidity, u128::MAX);
+    }
+
+    #[test]
+    fn simulated_writes_move_twap_test() {
+        let mut pool_state = pool_state_at_price_one();
+        pool_state.liquidity = 1000;
+        pool_state.block_timestamp = 1000;
+        (pool_state.slot0.observation_cardinality, pool_state.slot0.observation_cardinality_next) = oracle::initialize(&mut pool_state.observations, 0);
+        pool_state.increase_observation_cardinality_next(10).unwrap();
+
+        // the price sat at tick 0 until now and moves to tick 600 for the next 100 seconds
+        pool_state.write_observation(0, 1000);
+        pool_state.slot0.tick = 600;
+        pool_state.block_timestamp = 1100;
+
+        assert_eq!(pool_state.consult(100).unwrap().arithmetic_mean_tick, 600);
+        assert_eq!(pool_state.consult(200).unwrap().arithmetic_mean_tick, 300);
+        assert!(pool_state.consult(1200).is_err());
+    }
+}
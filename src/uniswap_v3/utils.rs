@@ -1,4 +1,18 @@
-use alloy::primitives::{address, Address}; 
+use alloy::primitives::{address, b256, Address, B256, I256, U256}; 
 
-pub const UNISWAP_V3_QUOTER_ADDRESS: Address = address!("b27308f9F90D607463bb33eA1BeBb41C27CE5AB6");
-pub const UNISWAP_V3_POOL_FACTORY_ADDRESS: Address = address!("1F98431c8aD98523631AE4a59f267346ea31F984"); 
\ No newline at end of file
+pub const UNISWAP_V3_POOL_FACTORY_ADDRESS: Address = address!("1F98431c8aD98523631AE4a59f267346ea31F984");
+pub const UNISWAP_V3_POSITION_MANAGER_ADDRESS: Address = address!("C36442b4a4522E871399CD717aBDD847Ab11FE88");
+pub const UNISWAP_V3_POOL_INIT_CODE_HASH: B256 = b256!("e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54");
+pub const UNISWAP_V3_STAKER_ADDRESS: Address = address!("e34139463bA50bD61336E0c446Bd8C0867c6fE65");
+
+/// Converts a Q64.96 sqrt price into the price of token0 quoted in token1, adjusted for token decimals
+pub fn sqrt_price_x96_to_price(sqrt_price_x96: U256, decimals0: u8, decimals1: u8) -> f64 {
+    let sqrt_price = f64::from(sqrt_price_x96) / 2f64.powi(96); 
+    sqrt_price * sqrt_price * 10f64.powi(decimals0 as i32 - decimals1 as i32)
+}
+
+/// Converts a signed token amount into a float, keeping the sign
+pub fn i256_to_f64(value: I256) -> f64 {
+    let abs = f64::from(value.unsigned_abs());
+    if value < I256::ZERO {-abs} else {abs}
+}
@@ -0,0 +1,149 @@
+use alloy::{
+    primitives::{Address, I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    transports::http::{Client, Http}
+};
+use crate::{
+    amm::Amm,
+    uniswap_v3::{math::{safe_cast, tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO}}, utils::sqrt_price_x96_to_price}
+};
+use super::pool::V4PoolState;
+use eyre::{eyre, Result};
+use futures::future::BoxFuture;
+
+impl V4PoolState {
+    fn zero_for_one(&self, token_in: Address, token_out: Address) -> Result<bool> {
+        if token_in == self.key.currency0 && token_out == self.key.currency1 {
+            Ok(true)
+        } else if token_in == self.key.currency1 && token_out == self.key.currency0 {
+            Ok(false)
+        } else {
+            Err(eyre!("Pool {} does not trade {} for {}", self.id, token_in, token_out))
+        }
+    }
+
+    async fn swap_exact(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        zero_for_one: bool,
+        amount_specified: I256
+    ) -> Result<(U256, U256)> {
+        let sqrt_price_limit_x96 = if zero_for_one {MIN_SQRT_RATIO + U256::from(1)} else {MAX_SQRT_RATIO - U256::from(1)};
+        let (amount0, amount1) = self.swap(provider, zero_for_one, amount_specified, sqrt_price_limit_x96).await?;
+
+        let (amount_in, amount_out) = if zero_for_one {(amount0, amount1)} else {(amount1, amount0)};
+        Ok((amount_in.unsigned_abs(), amount_out.unsigned_abs()))
+    }
+}
+
+/// The pool is identified by its id, `address` is the PoolManager holding it and is shared by every V4 pool. Native
+/// ETH is the zero address.
+impl Amm for V4PoolState {
+    fn address(&self) -> Address {
+        self.engine.pool_address
+    }
+
+    fn tokens(&self) -> Vec<Address> {
+        vec![self.key.currency0, self.key.currency1]
+    }
+
+    fn spot_price(&self, base: Address, quote: Address) -> Result<f64> {
+        let price = sqrt_price_x96_to_price(self.engine.slot0.sqrt_price_x96, self.engine.token0.decimals, self.engine.token1.decimals);
+        if self.zero_for_one(base, quote)? {Ok(price)} else {Ok(1.0 / price)}
+    }
+
+    fn quote_exact_input<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (swapped_in, amount_out) = self.clone().swap_exact(provider, zero_for_one, safe_cast::to_int256(amount_in)?).await?;
+            if swapped_in != amount_in {
+                return Err(eyre!("Not enough liquidity to swap {} of {}", amount_in, token_in))
+            }
+            Ok(amount_out)
+        })
+    }
+
+    fn quote_exact_output<'a>(
+        &'a self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_out: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (amount_in, swapped_out) = self.clone().swap_exact(provider, zero_for_one, -safe_cast::to_int256(amount_out)?).await?;
+            if swapped_out != amount_out {
+                return Err(eyre!("Not enough liquidity to receive {} of {}", amount_out, token_out))
+            }
+            Ok(amount_in)
+        })
+    }
+
+    fn swap_exact_input<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256
+    ) -> BoxFuture<'a, Result<U256>> {
+        Box::pin(async move {
+            let zero_for_one = self.zero_for_one(token_in, token_out)?;
+            let (_, amount_out) = self.swap_exact(provider, zero_for_one, safe_cast::to_int256(amount_in)?).await?;
+            Ok(amount_out)
+        })
+    }
+
+    // the PoolManager emits the events of every pool, the pool is reloaded instead of replaying them
+    fn sync<'a>(
+        &'a mut self,
+        provider: &'a RootProvider<Http<Client>>,
+        to_block: u64
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let hook_override = self.hook_override;
+            *self = V4PoolState::load_at_block(
+                provider,
+                self.engine.multicall_address,
+                self.engine.pool_address,
+                self.key.clone(),
+                BlockId::number(to_block)
+            ).await?;
+            self.hook_override = hook_override;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::offline_provider, uniswap_v3::position::tests::pool_state_with_liquidity, uniswap_v4::pool_key::PoolKey};
+    use super::*;
+
+    #[tokio::test]
+    async fn v4_amm_test() {
+        let provider = offline_provider();
+        let engine = pool_state_with_liquidity().await;
+        let (token0, token1) = (engine.token0.address, engine.token1.address);
+
+        let key = PoolKey {currency0: token0, currency1: token1, fee: 3000, tickSpacing: 60, hooks: Address::ZERO};
+        let mut pool: Box<dyn Amm> = Box::new(V4PoolState {key: key.clone(), id: key.to_id(), protocol_fee: 0, lp_fee: 3000, hook_override: None, engine: engine.clone()});
+
+        // a hookless pool quotes like the V3 pool holding the same liquidity
+        let amount_in = U256::from(1000000000000000000u128);
+        let quoted_out = pool.quote_exact_input(&provider, token0, token1, amount_in).await.unwrap();
+        assert_eq!(quoted_out, engine.quote_exact_input(&provider, token0, token1, amount_in).await.unwrap());
+        assert_eq!(pool.quote_exact_output(&provider, token0, token1, quoted_out).await.unwrap(), amount_in);
+        assert!(pool.spot_price(token0, Address::ZERO).is_err());
+
+        assert_eq!(pool.swap_exact_input(&provider, token0, token1, amount_in).await.unwrap(), quoted_out);
+        assert!(pool.spot_price(token0, token1).unwrap() < 1.0);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod utils;
+pub mod pool_key;
+pub mod state_library;
+pub mod pool;
+pub mod amm;
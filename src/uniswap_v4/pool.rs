@@ -0,0 +1,226 @@
+use alloy::{
+    primitives::{Address, B256, I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::{
+    math::{tick::Info, tick_math::{MAX_TICK, MIN_TICK}},
+    pool::{self as v3_pool, get_block_timestamp, load_tokens, PoolState, Token},
+    swap
+};
+use super::{
+    pool_key::PoolKey,
+    state_library::{self, decode_slot0, decode_tick_info, extsload, get_pool_state_slot, get_tick_bitmap_slot, get_tick_info_slot}
+};
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+
+/// Stand-in for the hooks of a pool, asserting they do not change swap amounts. The swap is simulated as if the pool
+/// had no hooks, with `lp_fee` replacing the fee in slot0 when given, e.g. the fee a dynamic fee hook would return from
+/// `beforeSwap`. Only dynamic fee pools take an `lp_fee`, the fee of a static fee pool is fixed by its key.
+#[derive(Debug, Clone, Copy)]
+pub struct HookOverride {
+    pub lp_fee: Option<u32>
+}
+
+/// State of a pool in the singleton PoolManager. Ticks, liquidity and prices live in a V3 `PoolState` so swaps run
+/// through the V3 engine, its `pool_address` is the PoolManager.
+#[derive(Clone)]
+pub struct V4PoolState {
+    pub key: PoolKey,
+    pub id: B256,
+    // protocol fee in hundredths of a bip, 12 bits per swap direction
+    pub protocol_fee: u32,
+    // lp fee as of the last load, set by the hooks for dynamic fee pools
+    pub lp_fee: u32,
+    pub hook_override: Option<HookOverride>,
+    pub engine: PoolState
+}
+
+impl V4PoolState {
+    /// Loads the pool with its full tick bitmap and every initialized tick through `extsload`. The whole bitmap is read
+    /// up front as the V3 engine can only load missing words from V3 pools.
+    pub async fn load_at_block(
+        provider: &RootProvider<Http<Client>>,
+        multicall_address: Address,
+        pool_manager: Address,
+        key: PoolKey,
+        block: BlockId
+    ) -> Result<Self> {
+        let id = key.to_id();
+        let state_slot = get_pool_state_slot(id);
+
+        // slot0, both fee growth globals and the liquidity
+        let offsets = [0, state_library::FEE_GROWTH_GLOBAL0_OFFSET, state_library::FEE_GROWTH_GLOBAL0_OFFSET + 1, state_library::LIQUIDITY_OFFSET];
+        let values = extsload(provider, pool_manager, offsets.iter().map(|&offset| state_library::offset(state_slot, offset)).collect(), block).await?;
+        let slot0 = decode_slot0(values[0]);
+        if slot0.sqrt_price_x96.is_zero() {
+            return Err(eyre!("Pool {} is not initialized", id))
+        }
+        let fee_growth_global0_x128 = U256::from_be_bytes(values[1].0);
+        let fee_growth_global1_x128 = U256::from_be_bytes(values[2].0);
+        let liquidity = U256::from_be_bytes(values[3].0).to::<u128>();
+
+        let tick_spacing = key.tickSpacing;
+        let min_word_pos = (MIN_TICK.div_euclid(tick_spacing) >> 8) as i16;
+        let max_word_pos = ((MAX_TICK / tick_spacing) >> 8) as i16;
+        let words = extsload(provider, pool_manager, (min_word_pos..=max_word_pos).map(|word_pos| get_tick_bitmap_slot(state_slot, word_pos)).collect(), block).await?;
+
+        let mut tick_bitmap = HashMap::new();
+        let mut initialized_ticks = Vec::new();
+        for (word_pos, word) in (min_word_pos..=max_word_pos).zip(words) {
+            let word = U256::from_be_bytes(word.0);
+            for bit_pos in 0..256 {
+                if word.bit(bit_pos) {
+                    initialized_ticks.push(((word_pos as i32) * 256 + bit_pos as i32) * tick_spacing);
+                }
+            }
+            tick_bitmap.insert(word_pos, word);
+        }
+
+        let tick_slots = initialized_ticks
+            .iter()
+            .flat_map(|&tick| {
+                let tick_slot = get_tick_info_slot(state_slot, tick);
+                (0..3).map(move |offset| state_library::offset(tick_slot, offset))
+            })
+            .collect();
+        let tick_words = extsload(provider, pool_manager, tick_slots, block).await?;
+        let ticks: HashMap<i32, Info> = initialized_ticks
+            .into_iter()
+            .zip(tick_words.chunks(3))
+            .map(|(tick, words)| (tick, decode_tick_info(words)))
+            .collect();
+
+        let mut tokens = load_tokens(
+            provider,
+            multicall_address,
+            [key.currency0, key.currency1].into_iter().filter(|currency| *currency != Address::ZERO).collect(),
+            block
+        ).await?;
+        tokens.insert(Address::ZERO, Token {address: Address::ZERO, symbol: "ETH".to_string(), decimals: 18});
+
+        let engine = PoolState {
+            pool_address: pool_manager,
+            multicall_address,
+            tick_spacing,
+            fee: slot0.lp_fee,
+            fee_growth_global0_x128,
+            fee_growth_global1_x128,
+            protocol_fees: Default::default(),
+            token0: tokens[&key.currency0].clone(),
+            token1: tokens[&key.currency1].clone(),
+            tick_bitmap,
+            slot0: v3_pool::Slot0 {
+                sqrt_price_x96: slot0.sqrt_price_x96,
+                tick: slot0.tick,
+                observation_index: 0,
+                observation_cardinality: 0,
+                observation_cardinality_next: 0,
+                fee_protocol: 0,
+                unlocked: true
+            },
+            liquidity,
+            ticks,
+            observations: HashMap::new(),
+            block,
+            block_timestamp: get_block_timestamp(provider, block).await? as u32
+        };
+
+        Ok(V4PoolState {key, id, protocol_fee: slot0.protocol_fee, lp_fee: slot0.lp_fee, hook_override: None, engine})
+    }
+
+    /// Pools whose hooks do not run on swaps are simulated exactly, pools with swap hooks only with a `HookOverride`
+    pub fn is_simulable(&self) -> bool {
+        !self.key.has_swap_hooks() || self.hook_override.is_some()
+    }
+
+    /// @notice calculate the swap fee including the protocol fee
+    /// @dev Mirrors ProtocolFeeLibrary.calculateSwapFee, protocolFee + lpFee - (protocolFee * lpFee / 1_000_000)
+    pub fn swap_fee(&self, zero_for_one: bool) -> u32 {
+        let lp_fee = self.hook_override.and_then(|hook_override| hook_override.lp_fee).unwrap_or(self.lp_fee);
+        let protocol_fee = state_library::directional_protocol_fee(self.protocol_fee, zero_for_one);
+
+        if protocol_fee == 0 {
+            lp_fee
+        } else {
+            protocol_fee + lp_fee - (protocol_fee as u64 * lp_fee as u64 / 1000000) as u32
+        }
+    }
+
+    /// Simulates a swap with the V3 sign convention, a positive `amount_specified` is an exact input. Amounts are exact,
+    /// the protocol fee is left in the LP fee growth as the V3 engine splits it differently.
+    pub async fn swap(
+        &mut self,
+        provider: &RootProvider<Http<Client>>,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: U256
+    ) -> Result<(I256, I256)> {
+        if !self.is_simulable() {
+            return Err(eyre!("Pool {} has hooks {}, a HookOverride is needed to simulate it", self.id, self.key.hooks))
+        }
+        if self.hook_override.is_some_and(|hook_override| hook_override.lp_fee.is_some()) && !self.key.is_dynamic_fee() {
+            return Err(eyre!("Pool {} has a static fee of {}, its lp fee cannot be overridden", self.id, self.key.fee))
+        }
+
+        self.engine.fee = self.swap_fee(zero_for_one);
+        swap::swap(provider, &mut self.engine, zero_for_one, amount_specified, sqrt_price_limit_x96).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+    use crate::{
+        test_utils::offline_provider,
+        uniswap_v3::{math::tick_math::MIN_SQRT_RATIO, position::tests::pool_state_with_liquidity},
+        uniswap_v4::pool_key::DYNAMIC_FEE_FLAG
+    };
+    use super::*;
+
+    #[tokio::test]
+    async fn hooked_pool_test() {
+        let provider = offline_provider();
+        let engine = pool_state_with_liquidity().await;
+
+        let key = PoolKey {
+            currency0: engine.token0.address,
+            currency1: engine.token1.address,
+            fee: 3000,
+            tickSpacing: 60,
+            hooks: Address::ZERO
+        };
+        let mut pool = V4PoolState {key: key.clone(), id: key.to_id(), protocol_fee: 0, lp_fee: 3000, hook_override: None, engine: engine.clone()};
+
+        // a hookless pool swaps like the V3 pool
+        let amount_in = I256::from_raw(U256::from(1000000000000000000u128));
+        let sqrt_price_limit_x96 = MIN_SQRT_RATIO + U256::from(1);
+        let expected = swap::swap(&provider, &mut engine.clone(), true, amount_in, sqrt_price_limit_x96).await.unwrap();
+        assert_eq!(pool.clone().swap(&provider, true, amount_in, sqrt_price_limit_x96).await.unwrap(), expected);
+
+        // 0.1% protocol fee for zero for one swaps on top of the 0.3% lp fee
+        pool.protocol_fee = 1000;
+        assert_eq!((pool.swap_fee(true), pool.swap_fee(false)), (3997, 3000));
+
+        // hooks that only run on initialize and liquidity changes leave swaps alone
+        pool.key.hooks = address!("0000000000000000000000000000000000002a00");
+        assert!(!pool.key.has_swap_hooks());
+        assert!(pool.clone().swap(&provider, true, amount_in, sqrt_price_limit_x96).await.is_ok());
+
+        // a pool with a before swap hook needs an override
+        pool.key.hooks = address!("0000000000000000000000000000000000000080");
+        assert!(pool.key.has_swap_hooks());
+        assert!(pool.swap(&provider, true, amount_in, sqrt_price_limit_x96).await.is_err());
+
+        // only a dynamic fee pool can have its lp fee overridden
+        pool.protocol_fee = 0;
+        pool.hook_override = Some(HookOverride {lp_fee: Some(500)});
+        assert!(pool.clone().swap(&provider, true, amount_in, sqrt_price_limit_x96).await.is_err());
+
+        pool.key.fee = DYNAMIC_FEE_FLAG;
+        let (_, amount1) = pool.swap(&provider, true, amount_in, sqrt_price_limit_x96).await.unwrap();
+        assert!(amount1 < expected.1);
+    }
+}
@@ -0,0 +1,77 @@
+use alloy::{
+    primitives::{keccak256, B256},
+    sol,
+    sol_types::SolValue
+};
+
+/// @notice An lp fee of exactly 0b1000000... signals a dynamic fee pool. This isn't a valid static fee as it is > MAX_LP_FEE
+pub const DYNAMIC_FEE_FLAG: u32 = 0x800000;
+/// @notice the lp fee is represented in hundredths of a bip, so the max is 100%
+pub const MAX_LP_FEE: u32 = 1000000;
+
+// hook permissions are encoded in the lowest bits of the hook address
+pub const BEFORE_SWAP_FLAG: u16 = 1 << 7;
+pub const AFTER_SWAP_FLAG: u16 = 1 << 6;
+pub const BEFORE_SWAP_RETURNS_DELTA_FLAG: u16 = 1 << 3;
+pub const AFTER_SWAP_RETURNS_DELTA_FLAG: u16 = 1 << 2;
+const ALL_HOOK_MASK: u16 = (1 << 14) - 1;
+
+sol! {
+    /// @notice Returns the key for identifying a pool
+    #[derive(Debug, PartialEq)]
+    struct PoolKey {
+        /// @notice The lower currency of the pool, sorted numerically, the zero address for native ETH
+        address currency0;
+        /// @notice The higher currency of the pool, sorted numerically
+        address currency1;
+        /// @notice The pool LP fee, capped at 1_000_000. If the highest bit is 1, the pool has a dynamic fee and must be exactly equal to 0x800000
+        uint24 fee;
+        /// @notice Ticks that involve positions must be a multiple of tick spacing
+        int24 tickSpacing;
+        /// @notice The hooks of the pool
+        address hooks;
+    }
+}
+
+impl PoolKey {
+    /// @notice Returns value equal to keccak256(abi.encode(poolKey))
+    pub fn to_id(&self) -> B256 {
+        keccak256(self.abi_encode())
+    }
+
+    /// @notice returns true if a value is the dynamic fee flag
+    pub fn is_dynamic_fee(&self) -> bool {
+        self.fee == DYNAMIC_FEE_FLAG
+    }
+
+    /// Hook permission bits of the pool, zero for a pool without hooks
+    pub fn hook_permissions(&self) -> u16 {
+        u16::from_be_bytes([self.hooks[18], self.hooks[19]]) & ALL_HOOK_MASK
+    }
+
+    /// True when the hooks run on swaps and can change their amounts or fee
+    pub fn has_swap_hooks(&self) -> bool {
+        self.hook_permissions() & (BEFORE_SWAP_FLAG | AFTER_SWAP_FLAG | BEFORE_SWAP_RETURNS_DELTA_FLAG | AFTER_SWAP_RETURNS_DELTA_FLAG) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, b256, Address};
+    use super::*;
+
+    #[test]
+    fn pool_id_test() {
+        // ETH/USDC 0.05% pool of the mainnet PoolManager
+        let key = PoolKey {
+            currency0: Address::ZERO,
+            currency1: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            fee: 500,
+            tickSpacing: 10,
+            hooks: Address::ZERO
+        };
+        assert_eq!(key.to_id(), b256!("21c67e77068de97969ba93d4aab21826d33ca12bb9f565d8496e8fda8a82ca27"));
+        assert!(!key.is_dynamic_fee());
+        assert!(!key.has_swap_hooks());
+    }
+}
@@ -0,0 +1,169 @@
+use alloy::{
+    primitives::{keccak256, Address, B256, I256, U256},
+    providers::RootProvider,
+    rpc::types::eth::BlockId,
+    sol,
+    transports::http::{Client, Http}
+};
+use crate::uniswap_v3::math::tick::Info;
+use eyre::Result;
+
+sol! {
+    #[sol(rpc)]
+    interface IExtsload {
+        /// @notice Called by external contracts to access sparse pool state
+        /// @param slots List of slots to SLOAD from.
+        /// @return values List of loaded values.
+        function extsload(bytes32[] calldata slots) external view returns (bytes32[] memory values);
+    }
+}
+
+/// index of pools mapping in the PoolManager
+pub const POOLS_SLOT: U256 = U256::from_limbs([6, 0, 0, 0]);
+/// index of feeGrowthGlobal0X128 in Pool.State
+pub const FEE_GROWTH_GLOBAL0_OFFSET: u64 = 1;
+/// index of liquidity in Pool.State
+pub const LIQUIDITY_OFFSET: u64 = 3;
+/// index of TicksInfo mapping in Pool.State: mapping(int24 => TickInfo) ticks;
+pub const TICKS_OFFSET: u64 = 4;
+/// index of tickBitmap mapping in Pool.State
+pub const TICK_BITMAP_OFFSET: u64 = 5;
+
+// slots read per extsload call
+const EXTSLOAD_CHUNK_SIZE: usize = 500;
+
+/// Slot0 of a V4 pool, packed into a single word
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slot0 {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    // protocol fee in hundredths of a bip, 12 bits for zero for one swaps and 12 bits for one for zero swaps
+    pub protocol_fee: u32,
+    pub lp_fee: u32
+}
+
+impl Slot0 {
+    /// Protocol fee of swaps in the given direction, in hundredths of a bip
+    pub fn protocol_fee(&self, zero_for_one: bool) -> u32 {
+        directional_protocol_fee(self.protocol_fee, zero_for_one)
+    }
+}
+
+/// Protocol fee of swaps in the given direction out of the two packed 12 bit fees, ProtocolFeeLibrary.getZeroForOneFee
+/// and getOneForZeroFee
+pub fn directional_protocol_fee(protocol_fee: u32, zero_for_one: bool) -> u32 {
+    if zero_for_one {protocol_fee & 0xfff} else {protocol_fee >> 12}
+}
+
+/// Storage slot of `Pool.State` of the pool with id `pool_id`
+pub fn get_pool_state_slot(pool_id: B256) -> B256 {
+    keccak256([pool_id.as_slice(), &POOLS_SLOT.to_be_bytes::<32>()].concat())
+}
+
+/// Storage slot of the `ticks` entry of `tick` in the pool state at `state_slot`
+pub fn get_tick_info_slot(state_slot: B256, tick: i32) -> B256 {
+    mapping_slot(I256::try_from(tick).unwrap_or_default().into_raw(), offset(state_slot, TICKS_OFFSET))
+}
+
+/// Storage slot of the `tickBitmap` word at `word_pos` in the pool state at `state_slot`
+pub fn get_tick_bitmap_slot(state_slot: B256, word_pos: i16) -> B256 {
+    mapping_slot(I256::try_from(word_pos).unwrap_or_default().into_raw(), offset(state_slot, TICK_BITMAP_OFFSET))
+}
+
+/// Storage slot `offset` words after `slot`
+pub fn offset(slot: B256, offset: u64) -> B256 {
+    B256::from(U256::from_be_bytes(slot.0) + U256::from(offset))
+}
+
+fn mapping_slot(key: U256, mapping: B256) -> B256 {
+    keccak256([key.to_be_bytes::<32>().as_slice(), mapping.as_slice()].concat())
+}
+
+/// Unpacks slot0: 24 bits lpFee | 24 bits protocolFee | 24 bits tick | 160 bits sqrtPriceX96
+pub fn decode_slot0(word: B256) -> Slot0 {
+    let word = U256::from_be_bytes(word.0);
+    let mask24 = U256::from(0xffffff);
+
+    let tick = (word >> 160usize & mask24).to::<u32>();
+    // sign extend the 24 bit tick
+    let tick = ((tick << 8) as i32) >> 8;
+
+    Slot0 {
+        sqrt_price_x96: word & ((U256::from(1) << 160usize) - U256::from(1)),
+        tick,
+        protocol_fee: (word >> 184usize & mask24).to::<u32>(),
+        lp_fee: (word >> 208usize & mask24).to::<u32>()
+    }
+}
+
+/// Unpacks the first word of a tick: 128 bits liquidityNet | 128 bits liquidityGross, followed by the fee growth words
+pub fn decode_tick_info(words: &[B256]) -> Info {
+    let word = U256::from_be_bytes(words[0].0);
+    let liquidity_gross = (word & U256::from(u128::MAX)).to::<u128>();
+    let liquidity_net = (word >> 128usize).to::<u128>() as i128;
+
+    Info {
+        liquidity_gross,
+        liquidity_net,
+        fee_growth_outside0_x128: U256::from_be_bytes(words[1].0),
+        fee_growth_outside1_x128: U256::from_be_bytes(words[2].0),
+        initialized: liquidity_gross != 0,
+        ..Default::default()
+    }
+}
+
+/// Reads `slots` of the pool manager at `block` with batched `extsload` calls
+pub async fn extsload(
+    provider: &RootProvider<Http<Client>>,
+    pool_manager: Address,
+    slots: Vec<B256>,
+    block: BlockId
+) -> Result<Vec<B256>> {
+    let pool_manager = IExtsload::new(pool_manager, provider);
+
+    let mut values = Vec::with_capacity(slots.len());
+    for chunk in slots.chunks(EXTSLOAD_CHUNK_SIZE) {
+        values.extend(pool_manager.extsload(chunk.to_vec()).block(block).call().await?.values);
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::b256;
+    use super::*;
+
+    #[test]
+    fn decode_slot0_test() {
+        let sqrt_price_x96 = U256::from(79228162514264337593543950336u128);
+        let packed = U256::from(3000) << 208usize
+            | U256::from(0x001002) << 184usize
+            | (U256::from(-887272i32 as u32 & 0xffffff) << 160usize)
+            | sqrt_price_x96;
+
+        let slot0 = decode_slot0(B256::from(packed));
+        assert_eq!(slot0.sqrt_price_x96, sqrt_price_x96);
+        assert_eq!(slot0.tick, -887272);
+        assert_eq!((slot0.protocol_fee(true), slot0.protocol_fee(false)), (2, 1));
+        assert_eq!(slot0.lp_fee, 3000);
+
+        let liquidity_net: i128 = -5;
+        let info = decode_tick_info(&[
+            B256::from(U256::from(liquidity_net as u128) << 128usize | U256::from(10)),
+            B256::ZERO,
+            B256::ZERO
+        ]);
+        assert_eq!((info.liquidity_gross, info.liquidity_net, info.initialized), (10, -5, true));
+    }
+
+    #[test]
+    fn slots_test() {
+        // slots of the mainnet ETH/USDC 0.05% pool, keccak256(abi.encode(poolId, POOLS_SLOT)) and
+        // keccak256(abi.encode(int24(-200000), stateSlot + TICKS_OFFSET)) as solc lays out the mappings
+        let pool_id = b256!("21c67e77068de97969ba93d4aab21826d33ca12bb9f565d8496e8fda8a82ca27");
+        let state_slot = get_pool_state_slot(pool_id);
+        assert_eq!(state_slot, b256!("da8cac368d67cd2f2d8aaa5cc531768e0fa3b1d205c5c5de60da078e1f59bdfc"));
+        assert_eq!(get_tick_info_slot(state_slot, -200000), b256!("26e736bbb8ae8931c33d263b39b8b546fda35909c9cd48cc5b7f5e24bda89dc2"));
+    }
+}
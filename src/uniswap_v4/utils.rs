@@ -0,0 +1,3 @@
+use alloy::primitives::{address, Address};
+
+pub const UNISWAP_V4_POOL_MANAGER_ADDRESS: Address = address!("000000000004444c5dc75cB358380D2e3dE08A90");